@@ -0,0 +1,315 @@
+use std::{cmp::Ordering, collections::HashSet, fmt::Display};
+
+use crate::{
+    parsing_error::ParsingError,
+    value::{Collation, Value, parse_numeric},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Total,
+    GroupConcat,
+}
+
+impl AggregateFunction {
+    /// Looks up an aggregate function by (case-insensitive) name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            "TOTAL" => Some(AggregateFunction::Total),
+            "GROUP_CONCAT" => Some(AggregateFunction::GroupConcat),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+            AggregateFunction::Total => "TOTAL",
+            AggregateFunction::GroupConcat => "GROUP_CONCAT",
+        }
+    }
+}
+
+impl Display for AggregateFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Running sum that stays an integer until a non integer value is added, like SQLite's.
+/// Reals are added with Kahan-Babuska-Neumaier summation, `error` holding the compensation.
+#[derive(Default)]
+struct Sum {
+    integer: i64,
+    real: f64,
+    error: f64,
+    is_real: bool,
+    /// Whether the integer sum overflowed, which SUM fails with while TOTAL and AVG go on with
+    /// the real sum.
+    overflowed: bool,
+}
+
+impl Sum {
+    fn add(&mut self, value: &Value) {
+        let integer = match value {
+            Value::Integer(value) => Some(*value),
+            Value::Text(text) => parse_numeric(text).and_then(|value| value.as_integer()),
+            _ => None,
+        };
+        match integer {
+            Some(integer) if !self.is_real => match self.integer.checked_add(integer) {
+                Some(sum) => self.integer = sum,
+                None => {
+                    self.overflowed = true;
+                    self.switch_to_real();
+                    self.add_real(integer as f64);
+                }
+            },
+            Some(integer) => self.add_real(integer as f64),
+            None => {
+                self.switch_to_real();
+                self.add_real(value.to_real());
+            }
+        }
+    }
+
+    fn switch_to_real(&mut self) {
+        if !self.is_real {
+            self.is_real = true;
+            self.real = self.integer as f64;
+        }
+    }
+
+    fn add_real(&mut self, value: f64) {
+        let sum = self.real + value;
+        if self.real.abs() >= value.abs() {
+            self.error += (self.real - sum) + value;
+        } else {
+            self.error += (value - sum) + self.real;
+        }
+        self.real = sum;
+    }
+
+    fn to_real(&self) -> f64 {
+        if self.is_real {
+            self.real + self.error
+        } else {
+            self.integer as f64
+        }
+    }
+}
+
+/// State of one aggregate while the rows it runs over are fed to it.
+pub struct Accumulator {
+    function: AggregateFunction,
+    distinct: bool,
+    separator: String,
+    /// Collation DISTINCT, MIN and MAX compare text with.
+    collation: Collation,
+    /// Values already seen by a DISTINCT aggregate, encoded by `group_key`.
+    seen: HashSet<Vec<u8>>,
+    count: i64,
+    sum: Sum,
+    extreme: Option<Value>,
+    concat: Option<String>,
+}
+
+impl Accumulator {
+    pub fn new(
+        function: AggregateFunction,
+        distinct: bool,
+        separator: String,
+        collation: Collation,
+    ) -> Self {
+        Accumulator {
+            function,
+            distinct,
+            separator,
+            collation,
+            seen: HashSet::new(),
+            count: 0,
+            sum: Sum::default(),
+            extreme: None,
+            concat: None,
+        }
+    }
+
+    /// Feeds the argument of the aggregate for one row, `None` standing for the `*` of
+    /// `COUNT(*)`. Returns whether the value became the new MIN or MAX.
+    pub fn step(&mut self, value: Option<Value>) -> Result<bool, ParsingError> {
+        let Some(value) = value else {
+            self.count += 1;
+            return Ok(false);
+        };
+        // Aggregates skip NULLs
+        if value.is_null() {
+            return Ok(false);
+        }
+        if self.distinct
+            && !self
+                .seen
+                .insert(group_key(std::slice::from_ref(&value), &[self.collation]))
+        {
+            return Ok(false);
+        }
+
+        self.count += 1;
+        match self.function {
+            AggregateFunction::Count => {}
+            AggregateFunction::Sum | AggregateFunction::Avg | AggregateFunction::Total => {
+                self.sum.add(&value);
+                if self.function == AggregateFunction::Sum && self.sum.overflowed {
+                    return Err(ParsingError::IntegerOverflow);
+                }
+            }
+            AggregateFunction::Min | AggregateFunction::Max => {
+                let wanted = match self.function {
+                    AggregateFunction::Min => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                let replaces = self
+                    .extreme
+                    .as_ref()
+                    .is_none_or(|extreme| value.compare_with(extreme, self.collation) == wanted);
+                if replaces {
+                    self.extreme = Some(value);
+                }
+                return Ok(replaces);
+            }
+            AggregateFunction::GroupConcat => {
+                let text = value.to_text();
+                match &mut self.concat {
+                    Some(concat) => {
+                        concat.push_str(&self.separator);
+                        concat.push_str(&text);
+                    }
+                    None => self.concat = Some(text),
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Result of the aggregate over the rows fed so far.
+    pub fn finish(self) -> Value {
+        match self.function {
+            AggregateFunction::Count => Value::Integer(self.count),
+            AggregateFunction::Sum if self.count == 0 => Value::Null,
+            AggregateFunction::Sum if self.sum.is_real => Value::Real(self.sum.to_real()),
+            AggregateFunction::Sum => Value::Integer(self.sum.integer),
+            AggregateFunction::Avg if self.count == 0 => Value::Null,
+            AggregateFunction::Avg => Value::Real(self.sum.to_real() / self.count as f64),
+            AggregateFunction::Total => Value::Real(self.sum.to_real()),
+            AggregateFunction::Min | AggregateFunction::Max => self.extreme.unwrap_or(Value::Null),
+            AggregateFunction::GroupConcat => self.concat.map_or(Value::Null, Value::Text),
+        }
+    }
+}
+
+/// Encodes the GROUP BY values of a row so that rows of the same group, whose values compare
+/// equal, get the same bytes. Integral reals are encoded like the integer they're equal to,
+/// and texts in the form their collation compares them in.
+pub fn group_key(values: &[Value], collations: &[Collation]) -> Vec<u8> {
+    let mut key = vec![];
+    for (value, collation) in values.iter().zip(collations) {
+        match value {
+            Value::Null => key.push(0),
+            Value::Real(real)
+                if real.fract() == 0.0 && *real >= i64::MIN as f64 && *real < i64::MAX as f64 =>
+            {
+                key.push(1);
+                key.extend_from_slice(&(*real as i64).to_be_bytes());
+            }
+            Value::Integer(integer) => {
+                key.push(1);
+                key.extend_from_slice(&integer.to_be_bytes());
+            }
+            Value::Real(real) => {
+                key.push(2);
+                key.extend_from_slice(&real.to_bits().to_be_bytes());
+            }
+            Value::Text(text) => {
+                let text = collation.normalize(text);
+                key.push(3);
+                key.extend_from_slice(&text.len().to_be_bytes());
+                key.extend_from_slice(text.as_bytes());
+            }
+            Value::Blob(blob) => {
+                key.push(4);
+                key.extend_from_slice(&blob.len().to_be_bytes());
+                key.extend_from_slice(blob);
+            }
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `function` over `values`.
+    fn aggregate(
+        function: AggregateFunction,
+        distinct: bool,
+        values: &[Value],
+    ) -> Result<Value, ParsingError> {
+        let mut accumulator =
+            Accumulator::new(function, distinct, ",".to_string(), Collation::Binary);
+        for value in values {
+            accumulator.step(Some(value.clone()))?;
+        }
+        Ok(accumulator.finish())
+    }
+
+    #[test]
+    fn integer_overflow_only_fails_sum() {
+        let values = [Value::Integer(i64::MAX), Value::Integer(1)];
+        assert_eq!(
+            aggregate(AggregateFunction::Total, false, &values).unwrap(),
+            Value::Real(9.223372036854775808e18)
+        );
+        assert_eq!(
+            aggregate(AggregateFunction::Avg, false, &values).unwrap(),
+            Value::Real(4.611686018427387904e18)
+        );
+        assert!(matches!(
+            aggregate(AggregateFunction::Sum, false, &values),
+            Err(ParsingError::IntegerOverflow)
+        ));
+    }
+
+    #[test]
+    fn distinct_values_compare_equal_across_storage_classes() {
+        let values = [
+            Value::Integer(1),
+            Value::Real(1.0),
+            Value::Text("1".to_string()),
+            Value::Integer(2),
+            Value::Null,
+            Value::Integer(1),
+        ];
+        assert_eq!(
+            aggregate(AggregateFunction::Count, true, &values).unwrap(),
+            Value::Integer(3)
+        );
+        assert_eq!(
+            aggregate(AggregateFunction::GroupConcat, true, &values).unwrap(),
+            Value::Text("1,1,2".to_string())
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use crate::{
+    index_parser::IndexData, select_parser::ParsedSelect, table_parser::Table,
+    trigger_parser::Trigger, view_parser::View,
+};
+
+/// A parsed SQL statement. Each variant holds the node produced by the grammar in the
+/// module of the same name.
+#[derive(Debug)]
+pub enum Statement {
+    Select(ParsedSelect),
+    CreateTable(Table),
+    CreateIndex(IndexData),
+    CreateView(View),
+    CreateTrigger(Trigger),
+}
@@ -0,0 +1,563 @@
+use crate::{
+    expression::{CaseBranch, Comparator, Expression},
+    expression_parser::{BinaryOp, ColumnName, ParsedAggregate, ParsedExpression, UnaryOp},
+    function::FunctionRegistry,
+    parsing_error::ParsingError,
+    select_builder::{AggregateCall, Op, SourceTable, WhereColumn},
+    select_parser::{ResultColumn, TableReference},
+    table_parser::{ColumnStorage, TableColumn},
+    value::{Affinity, Collation, Value},
+};
+
+/// Looks up a collation by name, for COLLATE clauses.
+pub(crate) fn collation(name: &str) -> Result<Collation, ParsingError> {
+    Collation::from_name(name)
+        .ok_or_else(|| ParsingError::Semantic(format!("No such collation sequence: {name}")))
+}
+
+/// Collation an expression explicitly asks for with COLLATE.
+fn explicit_collation(expression: &ParsedExpression) -> Result<Option<Collation>, ParsingError> {
+    match expression {
+        ParsedExpression::Collate(_, name) => collation(name).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Binds the expressions of a SELECT to the columns of the tables of its FROM clause, and
+/// collects the aggregates they call.
+pub(crate) struct Binder<'a> {
+    tables: &'a [SourceTable],
+    references: Vec<&'a TableReference>,
+    /// Position in joined rows of the rowid of each table, its columns following it.
+    pub(crate) offsets: Vec<usize>,
+    /// Expressions of the result columns by alias, which other clauses can refer to.
+    aliases: Vec<(&'a str, &'a ParsedExpression)>,
+    aggregate_terms: Vec<ParsedAggregate>,
+    pub(crate) aggregates: Vec<AggregateCall>,
+    functions: &'a FunctionRegistry,
+    /// Position of the table whose generated column is being bound, its names only refer to
+    /// the columns of that table.
+    generating: Option<usize>,
+}
+
+impl<'a> Binder<'a> {
+    pub(crate) fn new(
+        tables: &'a [SourceTable],
+        references: Vec<&'a TableReference>,
+        columns: &'a [ResultColumn],
+        functions: &'a FunctionRegistry,
+    ) -> Self {
+        let offsets = tables
+            .iter()
+            .scan(0, |offset, source| {
+                let table_offset = *offset;
+                *offset += source.table.record_affinities().len() + 1;
+                Some(table_offset)
+            })
+            .collect();
+        let aliases = columns
+            .iter()
+            .filter_map(|column| match column {
+                ResultColumn::Expression {
+                    expression,
+                    alias: Some(alias),
+                    ..
+                } => Some((alias.as_str(), expression)),
+                _ => None,
+            })
+            .collect();
+        Binder {
+            tables,
+            references,
+            offsets,
+            aliases,
+            aggregate_terms: vec![],
+            aggregates: vec![],
+            functions,
+            generating: None,
+        }
+    }
+
+    /// Finds the table a name refers to, by position, and its column.
+    fn find_column(
+        &self,
+        column: &ColumnName,
+    ) -> Result<Option<(usize, &'a TableColumn)>, ParsingError> {
+        let mut found = self
+            .tables
+            .iter()
+            .zip(&self.references)
+            .enumerate()
+            .filter(|(position, (_, reference))| match self.generating {
+                Some(generating) => *position == generating,
+                None => column
+                    .table
+                    .as_ref()
+                    .is_none_or(|name| name.eq_ignore_ascii_case(reference.visible_name())),
+            })
+            .filter_map(|(position, (source, _))| {
+                source
+                    .table
+                    .get_column_by_name(&column.name)
+                    .map(|table_column| (position, table_column))
+            });
+        match (found.next(), found.next()) {
+            (Some(found), None) => Ok(Some(found)),
+            (Some(_), Some(_)) => Err(ParsingError::Semantic(format!(
+                "ambiguous column name: {column}"
+            ))),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Finds the table column a name refers to, `None` when no table has it.
+    fn resolve(&mut self, column: &ColumnName) -> Result<Option<Expression>, ParsingError> {
+        let Some((position, table_column)) = self.find_column(column)? else {
+            return Ok(None);
+        };
+
+        // Columns are read from the table row, or from the joined row when there are joins or
+        // the table is a view
+        let is_join =
+            self.tables.len() > 1 || self.tables.iter().any(|source| source.view.is_some());
+        let offset = self.offsets[position];
+        let affinity = table_column.affinity();
+        Ok(Some(match (table_column.storage, is_join) {
+            (ColumnStorage::RowId, false) => {
+                Expression::Column(WhereColumn::RowId, Affinity::Integer)
+            }
+            (ColumnStorage::Record(index), false) => {
+                Expression::Column(WhereColumn::Column(index), affinity)
+            }
+            (ColumnStorage::RowId, true) => {
+                Expression::Column(WhereColumn::Column(offset), Affinity::Integer)
+            }
+            (ColumnStorage::Record(index), true) => {
+                Expression::Column(WhereColumn::Column(offset + 1 + index), affinity)
+            }
+            // Names in the expression are the columns of the same table
+            (ColumnStorage::Virtual, _) => {
+                let expression = table_column
+                    .generated()
+                    .ok_or(ParsingError::InvalidStatement)?;
+                let generating = self.generating.replace(position);
+                let bound = self.bind(expression, false);
+                self.generating = generating;
+                bound?
+            }
+        }))
+    }
+
+    /// Binds a name that isn't a table column to the expression of the result column it's
+    /// the alias of.
+    fn bind_alias(
+        &mut self,
+        name: &str,
+        aggregates: bool,
+    ) -> Result<Option<Expression>, ParsingError> {
+        let Some(expression) = self.aliases.iter().find_map(|(alias, expression)| {
+            alias.eq_ignore_ascii_case(name).then_some(*expression)
+        }) else {
+            return Ok(None);
+        };
+        // The expression of an alias can't refer to aliases itself
+        let aliases = std::mem::take(&mut self.aliases);
+        let expression = self.bind(expression, aggregates);
+        self.aliases = aliases;
+        expression.map(Some)
+    }
+
+    /// Binds an expression, which may call aggregate functions when `aggregates` is set.
+    pub(crate) fn bind(
+        &mut self,
+        expression: &ParsedExpression,
+        aggregates: bool,
+    ) -> Result<Expression, ParsingError> {
+        Ok(match expression {
+            ParsedExpression::Literal(value) => Expression::Literal(value.clone()),
+            ParsedExpression::Column(column) => match self.resolve(column)? {
+                Some(expression) => expression,
+                None => match column.table {
+                    None => self.bind_alias(&column.name, aggregates)?,
+                    Some(_) => None,
+                }
+                .ok_or_else(|| ParsingError::Semantic(format!("No such column: {column}")))?,
+            },
+            // A double quoted name that isn't a column is a string
+            ParsedExpression::QuotedName(name) => {
+                let column = ColumnName {
+                    table: None,
+                    name: name.clone(),
+                };
+                match self.resolve(&column)? {
+                    Some(expression) => expression,
+                    None => self
+                        .bind_alias(name, aggregates)?
+                        .unwrap_or_else(|| Expression::Literal(Value::Text(name.clone()))),
+                }
+            }
+            ParsedExpression::Unary(op, operand) => {
+                Expression::Unary(*op, Box::new(self.bind(operand, aggregates)?))
+            }
+            ParsedExpression::Binary(lhs, BinaryOp::Comparison(op), rhs) => {
+                let collation = self.comparison_collation(lhs, rhs)?;
+                Expression::comparison(
+                    self.bind(lhs, aggregates)?,
+                    *op,
+                    self.bind(rhs, aggregates)?,
+                    collation,
+                )
+            }
+            ParsedExpression::Binary(lhs, op, rhs) => Expression::Binary(
+                Box::new(self.bind(lhs, aggregates)?),
+                *op,
+                Box::new(self.bind(rhs, aggregates)?),
+            ),
+            ParsedExpression::Is { lhs, rhs, negated } => {
+                let collation = self.comparison_collation(lhs, rhs)?;
+                Expression::is(
+                    self.bind(lhs, aggregates)?,
+                    self.bind(rhs, aggregates)?,
+                    *negated,
+                    collation,
+                )
+            }
+            ParsedExpression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                // `x BETWEEN low AND high` is `x >= low AND x <= high`
+                let low_collation = self.comparison_collation(expression, low)?;
+                let high_collation = self.comparison_collation(expression, high)?;
+                let between = Expression::comparison(
+                    self.bind(expression, aggregates)?,
+                    Op::GtEq,
+                    self.bind(low, aggregates)?,
+                    low_collation,
+                )
+                .and(Expression::comparison(
+                    self.bind(expression, aggregates)?,
+                    Op::LtEq,
+                    self.bind(high, aggregates)?,
+                    high_collation,
+                ));
+                if *negated {
+                    Expression::Unary(UnaryOp::Not, Box::new(between))
+                } else {
+                    between
+                }
+            }
+            ParsedExpression::In {
+                expression,
+                list,
+                negated,
+            } => {
+                let collation = explicit_collation(expression)?
+                    .or(self.declared_collation(expression)?)
+                    .unwrap_or_default();
+                let list = list
+                    .iter()
+                    .map(|element| self.bind(element, aggregates))
+                    .collect::<Result<_, _>>()?;
+                Expression::in_list(
+                    self.bind(expression, aggregates)?,
+                    list,
+                    *negated,
+                    collation,
+                )
+            }
+            ParsedExpression::Like {
+                expression,
+                pattern,
+                escape,
+                negated,
+                glob,
+            } => Expression::Like {
+                expression: Box::new(self.bind(expression, aggregates)?),
+                pattern: Box::new(self.bind(pattern, aggregates)?),
+                escape: escape
+                    .as_ref()
+                    .map(|escape| self.bind(escape, aggregates).map(Box::new))
+                    .transpose()?,
+                negated: *negated,
+                glob: *glob,
+            },
+            ParsedExpression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = operand
+                    .as_ref()
+                    .map(|operand| self.bind(operand, aggregates))
+                    .transpose()?;
+                let branches = branches
+                    .iter()
+                    .map(|(when, then)| {
+                        let when = self.bind(when, aggregates)?;
+                        let comparator = Comparator::new(
+                            operand.as_ref().and_then(Expression::affinity),
+                            when.affinity(),
+                            Collation::Binary,
+                        );
+                        Ok(CaseBranch {
+                            when,
+                            comparator,
+                            then: self.bind(then, aggregates)?,
+                        })
+                    })
+                    .collect::<Result<_, ParsingError>>()?;
+                Expression::Case {
+                    operand: operand.map(Box::new),
+                    branches,
+                    otherwise: otherwise
+                        .as_ref()
+                        .map(|otherwise| self.bind(otherwise, aggregates).map(Box::new))
+                        .transpose()?,
+                }
+            }
+            ParsedExpression::Cast(operand, type_name) => Expression::Cast(
+                Box::new(self.bind(operand, aggregates)?),
+                Affinity::from_declared_type(type_name),
+            ),
+            // The collation only matters to the comparison the expression is an operand of
+            ParsedExpression::Collate(operand, name) => {
+                collation(name)?;
+                self.bind(operand, aggregates)?
+            }
+            ParsedExpression::Aggregate(aggregate) => {
+                if !aggregates {
+                    return Err(ParsingError::Semantic(format!(
+                        "misuse of aggregate: {}()",
+                        aggregate.function
+                    )));
+                }
+                if let Some(position) = self
+                    .aggregate_terms
+                    .iter()
+                    .position(|term| term == aggregate)
+                {
+                    return Ok(Expression::Aggregate(position));
+                }
+                let argument = aggregate
+                    .argument
+                    .as_ref()
+                    .map(|argument| self.bind(argument, false))
+                    .transpose()?;
+                let collation = match &aggregate.argument {
+                    Some(argument) => self.value_collation(argument)?,
+                    None => Collation::Binary,
+                };
+                self.aggregate_terms.push(aggregate.clone());
+                self.aggregates.push(AggregateCall {
+                    function: aggregate.function,
+                    argument,
+                    distinct: aggregate.distinct,
+                    separator: aggregate
+                        .separator
+                        .as_ref()
+                        .map_or_else(|| ",".to_string(), Value::to_text),
+                    collation,
+                });
+                Expression::Aggregate(self.aggregates.len() - 1)
+            }
+            ParsedExpression::Function { name, arguments } => {
+                let function = self.functions.resolve(name, arguments.len())?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.bind(argument, aggregates))
+                    .collect::<Result<_, _>>()?;
+                Expression::Function(function, arguments)
+            }
+        })
+    }
+
+    /// Collation of a comparison: the one of the left operand, else the one of the right
+    /// operand, else BINARY.
+    fn comparison_collation(
+        &self,
+        lhs: &ParsedExpression,
+        rhs: &ParsedExpression,
+    ) -> Result<Collation, ParsingError> {
+        Ok(explicit_collation(lhs)?
+            .or(explicit_collation(rhs)?)
+            .or(self.declared_collation(lhs)?)
+            .or(self.declared_collation(rhs)?)
+            .unwrap_or_default())
+    }
+
+    /// The table column an expression is, if it is one, and the position of its table.
+    fn find_declared_column(
+        &self,
+        expression: &ParsedExpression,
+    ) -> Result<Option<(usize, &'a TableColumn)>, ParsingError> {
+        let column = match expression {
+            ParsedExpression::Column(column) => column.clone(),
+            ParsedExpression::QuotedName(name) => ColumnName {
+                table: None,
+                name: name.clone(),
+            },
+            _ => return Ok(None),
+        };
+        self.find_column(&column)
+    }
+
+    /// The table column an expression is, if it is one.
+    fn declared_column(
+        &self,
+        expression: &ParsedExpression,
+    ) -> Result<Option<&'a TableColumn>, ParsingError> {
+        Ok(self
+            .find_declared_column(expression)?
+            .map(|(_, table_column)| table_column))
+    }
+
+    /// Name of a result column without an alias: the name the table column it is was declared
+    /// with, which for the rowid is the name of its alias, or else the expression as written.
+    pub(crate) fn result_column_name(
+        &self,
+        expression: &ParsedExpression,
+        text: &str,
+    ) -> Result<String, ParsingError> {
+        let Some((position, table_column)) = self.find_declared_column(expression)? else {
+            return Ok(text.to_string());
+        };
+        let table_column = if table_column.is_rowid() {
+            self.tables[position]
+                .table
+                .columns
+                .iter()
+                .find(|column| column.is_rowid())
+                .unwrap_or(table_column)
+        } else {
+            table_column
+        };
+        Ok(match table_column.name() {
+            "" => text.to_string(),
+            name => name.to_string(),
+        })
+    }
+
+    /// Collation of the table column an expression is, if it is one: the one it's declared
+    /// with, else BINARY.
+    pub(crate) fn declared_collation(
+        &self,
+        expression: &ParsedExpression,
+    ) -> Result<Option<Collation>, ParsingError> {
+        match self.declared_column(expression)? {
+            Some(TableColumn {
+                collation: Some(name),
+                ..
+            }) => collation(name).map(Some),
+            Some(_) => Ok(Some(Collation::Binary)),
+            None => Ok(None),
+        }
+    }
+
+    /// Collation the values of an expression are compared with on their own, by DISTINCT, MIN,
+    /// MAX and GROUP BY: the one it asks for with COLLATE, else the one of the table column or
+    /// result column it names, else BINARY.
+    pub(crate) fn value_collation(
+        &self,
+        expression: &ParsedExpression,
+    ) -> Result<Collation, ParsingError> {
+        if let Some(collation) = explicit_collation(expression)? {
+            return Ok(collation);
+        }
+        if let Some(collation) = self.declared_collation(expression)? {
+            return Ok(collation);
+        }
+        let name = match expression {
+            ParsedExpression::Column(ColumnName { table: None, name })
+            | ParsedExpression::QuotedName(name)
+                if self.find_declared_column(expression)?.is_none() =>
+            {
+                name
+            }
+            _ => return Ok(Collation::Binary),
+        };
+        // The expression of an alias can't refer to aliases itself
+        match self
+            .aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        {
+            Some((_, expression)) => Ok(explicit_collation(expression)?
+                .or(self.declared_collation(expression)?)
+                .unwrap_or_default()),
+            None => Ok(Collation::Binary),
+        }
+    }
+
+    /// Name of the collation a result column keeps in a view: the one it asks for with
+    /// COLLATE, or the one of the table column it is.
+    pub(crate) fn result_collation(
+        &self,
+        expression: &ParsedExpression,
+    ) -> Result<Option<String>, ParsingError> {
+        Ok(match expression {
+            ParsedExpression::Collate(_, name) => Some(name.clone()),
+            expression => self
+                .declared_column(expression)?
+                .and_then(|table_column| table_column.collation.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{select_parser::parse_select, statistics::Statistics, table_parser::parse_table};
+
+    fn source_table(sql: &str) -> SourceTable {
+        SourceTable {
+            root_page: 2,
+            table: parse_table(sql).unwrap(),
+            indexes: vec![],
+            statistics: Statistics::default(),
+            view: None,
+        }
+    }
+
+    /// Binds the result columns of `select` to `tables`, and evaluates them on `row`.
+    fn evaluate(select: &str, tables: &[SourceTable], row: Vec<Value>) -> Vec<Value> {
+        let select = parse_select(select).unwrap();
+        let functions = FunctionRegistry::default();
+        let mut binder = Binder::new(
+            tables,
+            select.tables().collect(),
+            &select.columns,
+            &functions,
+        );
+        select
+            .columns
+            .iter()
+            .map(|column| match column {
+                ResultColumn::Expression { expression, .. } => binder
+                    .bind(expression, false)
+                    .unwrap()
+                    .evaluate(&row)
+                    .unwrap(),
+                _ => panic!("expected an expression"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comparisons_use_the_collation_of_the_left_column() {
+        let tables = [source_table(
+            "CREATE TABLE t(p TEXT, q TEXT COLLATE NOCASE)",
+        )];
+        let row = vec![Value::Text("a".to_string()), Value::Text("A".to_string())];
+        assert_eq!(
+            evaluate(
+                "SELECT p = q, q = p, p = q COLLATE NOCASE, q = 'a', p = 'A' FROM t",
+                &tables,
+                row
+            ),
+            [0, 1, 1, 1, 0].map(Value::Integer)
+        );
+    }
+}
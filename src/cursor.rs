@@ -0,0 +1,666 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::{
+    cell::{
+        parse_index_interior_cell, parse_index_leaf_cell, parse_index_record, parse_interior_cell,
+        parse_leaf_cell_lazy,
+    },
+    leaf_cell::LazyLeafCell,
+    page::Page,
+    page_header::BtreePageType,
+    parsing_error::ParsingError,
+    reader::{SqliteReader, get_num_from_be},
+    select_parser::SortOrder,
+    value::{Collation, Value},
+    varint::parse_varint,
+};
+
+/// A b-tree page on the path from the root to the current cell.
+struct Frame {
+    page: Page,
+    cells: Vec<u16>,
+    /// Current cell on a leaf page. On an interior page even slots `2 * i` stand for the
+    /// child to the left of cell `i` (the rightmost pointer being `2 * cells.len()`) and odd
+    /// slots `2 * i + 1` for cell `i` itself.
+    index: usize,
+}
+
+impl Frame {
+    fn is_leaf(&self) -> bool {
+        matches!(
+            self.page.page_header.page_type,
+            BtreePageType::LeafTablePage | BtreePageType::LeafIndexPage
+        )
+    }
+
+    fn slot_count(&self) -> usize {
+        if self.is_leaf() {
+            self.cells.len()
+        } else {
+            2 * self.cells.len() + 1
+        }
+    }
+
+    /// Whether the frame points at an entry, index b-trees also storing entries in the
+    /// cells of interior pages.
+    fn has_row(&self) -> bool {
+        match self.page.page_header.page_type {
+            BtreePageType::LeafTablePage | BtreePageType::LeafIndexPage => {
+                self.index < self.cells.len()
+            }
+            BtreePageType::InteriorIndexPage => self.index % 2 == 1,
+            BtreePageType::InteriorTablePage => false,
+        }
+    }
+
+    fn cell_offset(&self) -> usize {
+        let cell = if self.is_leaf() {
+            self.index
+        } else {
+            self.index / 2
+        };
+        self.cells[cell] as usize
+    }
+
+    fn child_page(&self, child: usize) -> Result<u64, ParsingError> {
+        if child < self.cells.len() {
+            let mut offset = self.cells[child] as usize;
+            let page_number: u32 = get_num_from_be(&mut offset, &self.page.page)?;
+            return Ok(page_number as u64);
+        }
+        self.page
+            .page_header
+            .rightmost_pointer
+            .map(|page| page as u64)
+            .ok_or(ParsingError::InvalidPageType)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    BeforeFirst,
+    OnRow,
+    AfterLast,
+}
+
+/// Walks the entries of a b-tree in order, keeping only the pages on the path from the root
+/// to the current entry in memory. Shared by the table and index cursors, which decode the
+/// entries.
+struct BtreeWalker<'a> {
+    reader: &'a SqliteReader,
+    root_page: u64,
+    stack: Vec<Frame>,
+    position: Position,
+}
+
+impl<'a> BtreeWalker<'a> {
+    fn new(reader: &'a SqliteReader, root_page: u64) -> Self {
+        BtreeWalker {
+            reader,
+            root_page,
+            stack: vec![],
+            position: Position::BeforeFirst,
+        }
+    }
+
+    fn first(&mut self) -> Result<bool, ParsingError> {
+        self.stack.clear();
+        self.descend(self.root_page, true)?;
+        self.settle(true, false)
+    }
+
+    fn last(&mut self) -> Result<bool, ParsingError> {
+        self.stack.clear();
+        self.descend(self.root_page, false)?;
+        self.settle(false, false)
+    }
+
+    fn next(&mut self) -> Result<bool, ParsingError> {
+        match self.position {
+            Position::BeforeFirst => self.first(),
+            Position::OnRow => self.settle(true, true),
+            Position::AfterLast => Ok(false),
+        }
+    }
+
+    fn prev(&mut self) -> Result<bool, ParsingError> {
+        match self.position {
+            Position::BeforeFirst => Ok(false),
+            Position::OnRow => self.settle(false, true),
+            Position::AfterLast => self.last(),
+        }
+    }
+
+    /// Moves to the first entry for which `is_before` is false. `is_before` is called with
+    /// a page and the offset of one of its cells, and has to hold for a prefix of the entries.
+    fn seek<F>(&mut self, mut is_before: F) -> Result<bool, ParsingError>
+    where
+        F: FnMut(&Frame, usize) -> Result<bool, ParsingError>,
+    {
+        self.stack.clear();
+        let mut page_number = self.root_page;
+        loop {
+            let page = self.reader.read_page(page_number)?;
+            let cells = page.parse_cell_pointer_array();
+            let mut frame = Frame {
+                page,
+                cells,
+                index: 0,
+            };
+
+            let mut low = 0;
+            let mut high = frame.cells.len();
+            while low < high {
+                let middle = low + (high - low) / 2;
+                if is_before(&frame, frame.cells[middle] as usize)? {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+
+            if frame.is_leaf() {
+                frame.index = low;
+                self.stack.push(frame);
+                break;
+            }
+            // Everything left of cell `low` sorts before the target, which is either in the
+            // cell's left child or the cell itself.
+            frame.index = 2 * low;
+            page_number = frame.child_page(low)?;
+            self.stack.push(frame);
+        }
+
+        // The leaf position is one past its last cell when all of its entries sort before.
+        self.settle(true, false)
+    }
+
+    /// Page, cell offset and page type of the current entry.
+    fn current(&self) -> Option<(&Rc<[u8]>, usize, BtreePageType)> {
+        if self.position != Position::OnRow {
+            return None;
+        }
+        let frame = self.stack.last()?;
+        Some((
+            &frame.page.page,
+            frame.cell_offset(),
+            frame.page.page_header.page_type,
+        ))
+    }
+
+    /// Pushes the pages from `page_number` down to its first (or last) leaf.
+    fn descend(&mut self, mut page_number: u64, forward: bool) -> Result<(), ParsingError> {
+        loop {
+            let page = self.reader.read_page(page_number)?;
+            let cells = page.parse_cell_pointer_array();
+            let mut frame = Frame {
+                page,
+                cells,
+                index: 0,
+            };
+            if !forward {
+                frame.index = frame.slot_count().saturating_sub(1);
+            }
+
+            if frame.is_leaf() {
+                self.stack.push(frame);
+                return Ok(());
+            }
+            page_number = frame.child_page(frame.index / 2)?;
+            self.stack.push(frame);
+        }
+    }
+
+    /// Steps in the given direction until the walker is on an entry or the b-tree is
+    /// exhausted, and returns whether an entry was found.
+    fn settle(&mut self, forward: bool, skip_current: bool) -> Result<bool, ParsingError> {
+        self.step(forward, skip_current)?;
+        let on_row = self.stack.last().is_some_and(Frame::has_row);
+        self.position = if on_row {
+            Position::OnRow
+        } else if forward {
+            Position::AfterLast
+        } else {
+            Position::BeforeFirst
+        };
+        Ok(on_row)
+    }
+
+    /// Moves to the nearest entry in the given direction, the current one included unless
+    /// `skip_current` is set. The stack is left empty past either end.
+    fn step(&mut self, forward: bool, mut skip_current: bool) -> Result<(), ParsingError> {
+        while let Some(frame) = self.stack.last_mut() {
+            if !skip_current && frame.has_row() {
+                return Ok(());
+            }
+            skip_current = false;
+
+            let slot = if forward {
+                Some(frame.index + 1).filter(|slot| *slot < frame.slot_count())
+            } else {
+                frame.index.checked_sub(1)
+            };
+            let Some(slot) = slot else {
+                self.stack.pop();
+                continue;
+            };
+            frame.index = slot;
+            if !frame.is_leaf() && slot % 2 == 0 {
+                let page_number = frame.child_page(slot / 2)?;
+                self.descend(page_number, forward)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Walks the rows of a table b-tree in rowid order.
+///
+/// Only the pages on the path from the root to the current row are kept in memory, so a
+/// table of any size can be read in constant memory. Iterating the cursor moves it forward,
+/// starting from the first row when it has not been positioned yet.
+pub struct TableCursor<'a> {
+    walker: BtreeWalker<'a>,
+    defaults: Rc<[Value]>,
+}
+
+impl<'a> TableCursor<'a> {
+    pub fn new(reader: &'a SqliteReader, root_page: u64) -> Self {
+        TableCursor {
+            walker: BtreeWalker::new(reader, root_page),
+            defaults: Rc::from([]),
+        }
+    }
+
+    /// Sets the values of the columns that rows written before they were added don't have.
+    pub fn with_defaults(mut self, defaults: Rc<[Value]>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Moves to the first row of the table.
+    pub fn first(&mut self) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker.first()?;
+        self.current()
+    }
+
+    /// Moves to the last row of the table.
+    pub fn seek_last(&mut self) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker.last()?;
+        self.current()
+    }
+
+    /// Moves to the row with the smallest rowid greater than or equal to `rowid`.
+    pub fn seek(&mut self, rowid: i128) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker.seek(|frame, cell_offset| {
+            let cell_rowid = if frame.is_leaf() {
+                let mut offset = cell_offset;
+                parse_varint(&mut offset, &frame.page.page)?;
+                parse_varint(&mut offset, &frame.page.page)?
+            } else {
+                // The left child of an interior cell holds the rowids up to the cell's key.
+                parse_interior_cell(&frame.page.page, cell_offset)?.rowid
+            };
+            Ok(cell_rowid < rowid)
+        })?;
+        self.current()
+    }
+
+    /// Row an index entry leads to, `entries` being on that entry: the row with its rowid, or
+    /// the entry itself when the index is the b-tree of this WITHOUT ROWID table.
+    pub fn seek_entry(
+        &mut self,
+        entries: &IndexCursor,
+        entry: &IndexEntry,
+    ) -> Result<Option<LazyLeafCell>, ParsingError> {
+        if entries.walker.root_page == self.walker.root_page {
+            let mut cell = entries.current_record()?;
+            if let Some(cell) = &mut cell {
+                cell.defaults = self.defaults.clone();
+            }
+            return Ok(cell);
+        }
+        Ok(self
+            .seek(entry.rowid)?
+            .filter(|cell| cell.rowid == entry.rowid))
+    }
+
+    /// Moves to the previous row, or to the last one when the cursor is past the end.
+    pub fn prev(&mut self) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker.prev()?;
+        self.current()
+    }
+
+    /// Row the cursor is currently on. The rows of WITHOUT ROWID tables are the entries of
+    /// an index b-tree, and have no rowid.
+    pub fn current(&self) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker
+            .current()
+            .map(|(page, offset, page_type)| {
+                let reader = self.walker.reader;
+                let mut cell = match page_type {
+                    BtreePageType::LeafTablePage | BtreePageType::InteriorTablePage => {
+                        parse_leaf_cell_lazy(page, offset, reader)?
+                    }
+                    BtreePageType::LeafIndexPage => index_record(reader, page, offset, true)?,
+                    BtreePageType::InteriorIndexPage => index_record(reader, page, offset, false)?,
+                };
+                cell.defaults = self.defaults.clone();
+                Ok(cell)
+            })
+            .transpose()
+    }
+}
+
+impl Iterator for TableCursor<'_> {
+    type Item = Result<LazyLeafCell, ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walker.next() {
+            Ok(_) => self.current().transpose(),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// An entry of an index: the values of the indexed columns and the rowid of the table row.
+#[derive(Debug)]
+pub struct IndexEntry {
+    pub key: Vec<Value>,
+    pub rowid: i128,
+}
+
+/// Collation and direction of each key column of an index.
+pub type KeyOrder = Vec<(Collation, SortOrder)>;
+
+/// Walks the entries of an index b-tree in key order, in constant memory like `TableCursor`.
+pub struct IndexCursor<'a> {
+    walker: BtreeWalker<'a>,
+    key_order: KeyOrder,
+    /// Whether the last column of the entries is a rowid, and not part of the key.
+    has_rowid: bool,
+}
+
+impl<'a> IndexCursor<'a> {
+    /// Cursor comparing every key column with BINARY in ascending order, see `with_key_order`.
+    pub fn new(reader: &'a SqliteReader, root_page: u64) -> Self {
+        IndexCursor {
+            walker: BtreeWalker::new(reader, root_page),
+            key_order: vec![],
+            has_rowid: true,
+        }
+    }
+
+    pub fn with_key_order(self, key_order: KeyOrder) -> Self {
+        Self { key_order, ..self }
+    }
+
+    /// Reads the b-tree of a WITHOUT ROWID table, whose entries are rows keyed by all of
+    /// their columns, the primary key coming first.
+    pub fn without_rowid(self) -> Self {
+        Self {
+            has_rowid: false,
+            ..self
+        }
+    }
+
+    /// Orders `key` against `probe` the way the index does, only the first `probe.len()` key
+    /// columns taking part.
+    pub fn compare_key(&self, key: &[Value], probe: &[Value]) -> Ordering {
+        compare_key(&self.key_order, key, probe)
+    }
+
+    /// Moves to the first entry of the index.
+    pub fn first(&mut self) -> Result<Option<IndexEntry>, ParsingError> {
+        self.walker.first()?;
+        self.current()
+    }
+
+    /// Moves to the last entry of the index.
+    pub fn seek_last(&mut self) -> Result<Option<IndexEntry>, ParsingError> {
+        self.walker.last()?;
+        self.current()
+    }
+
+    /// Moves to the first entry whose key is greater than or equal to `probe`.
+    pub fn seek(&mut self, probe: &[Value]) -> Result<Option<IndexEntry>, ParsingError> {
+        self.seek_by(probe, Ordering::is_lt)
+    }
+
+    /// Moves to the first entry whose key is greater than `probe`.
+    pub fn seek_past(&mut self, probe: &[Value]) -> Result<Option<IndexEntry>, ParsingError> {
+        self.seek_by(probe, Ordering::is_le)
+    }
+
+    fn seek_by(
+        &mut self,
+        probe: &[Value],
+        is_before: fn(Ordering) -> bool,
+    ) -> Result<Option<IndexEntry>, ParsingError> {
+        let reader = self.walker.reader;
+        let key_order = &self.key_order;
+        let has_rowid = self.has_rowid;
+        self.walker.seek(|frame, cell_offset| {
+            let entry = read_entry(
+                reader,
+                &frame.page.page,
+                cell_offset,
+                frame.is_leaf(),
+                has_rowid,
+            )?;
+            Ok(is_before(compare_key(key_order, &entry.key, probe)))
+        })?;
+        self.current()
+    }
+
+    /// Moves to the previous entry, or to the last one when the cursor is past the end.
+    pub fn prev(&mut self) -> Result<Option<IndexEntry>, ParsingError> {
+        self.walker.prev()?;
+        self.current()
+    }
+
+    /// Entry the cursor is currently on.
+    pub fn current(&self) -> Result<Option<IndexEntry>, ParsingError> {
+        self.walker
+            .current()
+            .map(|(page, offset, page_type)| {
+                let is_leaf = matches!(page_type, BtreePageType::LeafIndexPage);
+                read_entry(self.walker.reader, page, offset, is_leaf, self.has_rowid)
+            })
+            .transpose()
+    }
+
+    /// Record of the entry the cursor is on, which is a row in WITHOUT ROWID tables.
+    pub fn current_record(&self) -> Result<Option<LazyLeafCell>, ParsingError> {
+        self.walker
+            .current()
+            .map(|(page, offset, page_type)| {
+                let is_leaf = matches!(page_type, BtreePageType::LeafIndexPage);
+                index_record(self.walker.reader, page, offset, is_leaf)
+            })
+            .transpose()
+    }
+}
+
+impl Iterator for IndexCursor<'_> {
+    type Item = Result<IndexEntry, ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walker.next() {
+            Ok(_) => self.current().transpose(),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+fn compare_key(key_order: &[(Collation, SortOrder)], key: &[Value], probe: &[Value]) -> Ordering {
+    key.iter()
+        .zip(probe)
+        .enumerate()
+        .map(|(column, (key, probe))| {
+            let (collation, order) = key_order
+                .get(column)
+                .copied()
+                .unwrap_or((Collation::Binary, SortOrder::Asc));
+            let ordering = key.compare_with(probe, collation);
+            match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Record of an index cell, which follows the child page number in interior cells.
+fn index_record(
+    reader: &SqliteReader,
+    page: &Rc<[u8]>,
+    cell_offset: usize,
+    is_leaf: bool,
+) -> Result<LazyLeafCell, ParsingError> {
+    let record_offset = if is_leaf {
+        cell_offset
+    } else {
+        cell_offset + 4
+    };
+    parse_index_record(page, record_offset, reader)
+}
+
+fn read_entry(
+    reader: &SqliteReader,
+    page: &Rc<[u8]>,
+    cell_offset: usize,
+    is_leaf: bool,
+    has_rowid: bool,
+) -> Result<IndexEntry, ParsingError> {
+    if !has_rowid {
+        let cell = index_record(reader, page, cell_offset, is_leaf)?;
+        let key = (0..cell.record_types.len())
+            .map(|column| cell.get_column(column))
+            .collect::<Result<_, _>>()?;
+        return Ok(IndexEntry { key, rowid: 0 });
+    }
+
+    let cell = if is_leaf {
+        parse_index_leaf_cell(page, cell_offset, reader)?
+    } else {
+        parse_index_interior_cell(page, cell_offset, reader)?.1
+    };
+    // The last column of an index record is the rowid.
+    let key = (0..cell.record_types.len().saturating_sub(1))
+        .map(|column| cell.get_column(column))
+        .collect::<Result<_, _>>()?;
+    Ok(IndexEntry {
+        key,
+        rowid: cell.rowid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `t(id INTEGER PRIMARY KEY, name TEXT)` on 512 bytes pages, at page 2, holds the even
+    /// rowids from 2 to 4000. Its index `t_name` at page 3 holds each name twice.
+    fn reader() -> SqliteReader {
+        SqliteReader::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/btrees.db")).unwrap()
+    }
+
+    const TABLE_ROOT: u64 = 2;
+    const INDEX_ROOT: u64 = 3;
+
+    fn rowid(cell: Option<LazyLeafCell>) -> Option<i128> {
+        cell.map(|cell| cell.rowid)
+    }
+
+    fn name(name: &str) -> Vec<Value> {
+        vec![Value::Text(name.to_string())]
+    }
+
+    /// Key and rowid of an entry, to compare with `name` and a rowid.
+    fn key(entry: Option<IndexEntry>) -> Option<(Vec<Value>, i128)> {
+        entry.map(|entry| (entry.key, entry.rowid))
+    }
+
+    #[test]
+    fn table_cursor_reads_every_row_in_rowid_order() {
+        let reader = reader();
+        let rowids = TableCursor::new(&reader, TABLE_ROOT)
+            .map(|cell| cell.unwrap().rowid)
+            .collect::<Vec<_>>();
+        assert_eq!(rowids, (1..=2000).map(|row| 2 * row).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn table_cursor_seeks_the_first_row_from_a_rowid() {
+        let reader = reader();
+        let mut cursor = TableCursor::new(&reader, TABLE_ROOT);
+        assert_eq!(rowid(cursor.seek(i128::from(i64::MIN)).unwrap()), Some(2));
+        assert_eq!(rowid(cursor.seek(1002).unwrap()), Some(1002));
+        assert_eq!(rowid(cursor.seek(1001).unwrap()), Some(1002));
+        assert_eq!(cursor.next().unwrap().unwrap().rowid, 1004);
+        assert_eq!(rowid(cursor.seek(4000).unwrap()), Some(4000));
+        assert_eq!(rowid(cursor.seek(4001).unwrap()), None);
+    }
+
+    #[test]
+    fn table_cursor_walks_back_across_pages() {
+        let reader = reader();
+        let mut cursor = TableCursor::new(&reader, TABLE_ROOT);
+        assert_eq!(rowid(cursor.seek(1001).unwrap()), Some(1002));
+        assert_eq!(rowid(cursor.prev().unwrap()), Some(1000));
+
+        let mut rowids = vec![cursor.seek_last().unwrap().unwrap().rowid];
+        while let Some(cell) = cursor.prev().unwrap() {
+            rowids.push(cell.rowid);
+        }
+        assert_eq!(
+            rowids,
+            (1..=2000).rev().map(|row| 2 * row).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn index_cursor_reads_entries_in_key_then_rowid_order() {
+        let reader = reader();
+        let entries = IndexCursor::new(&reader, INDEX_ROOT)
+            .map(|entry| entry.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2000);
+        assert_eq!((&entries[0].key, entries[0].rowid), (&name("n0000"), 2000));
+        assert!(entries.windows(2).all(|pair| {
+            pair[0].key[0]
+                .compare(&pair[1].key[0])
+                .then(pair[0].rowid.cmp(&pair[1].rowid))
+                .is_lt()
+        }));
+    }
+
+    #[test]
+    fn index_cursor_seeks_keys_and_walks_back() {
+        let reader = reader();
+        let mut cursor = IndexCursor::new(&reader, INDEX_ROOT);
+        assert_eq!(
+            key(cursor.seek(&name("n0500")).unwrap()),
+            Some((name("n0500"), 1000))
+        );
+        assert_eq!(
+            key(cursor.next().transpose().unwrap()),
+            Some((name("n0500"), 3000))
+        );
+        assert_eq!(
+            key(cursor.seek_past(&name("n0500")).unwrap()),
+            Some((name("n0501"), 1002))
+        );
+        assert_eq!(key(cursor.prev().unwrap()), Some((name("n0500"), 3000)));
+        assert_eq!(key(cursor.seek(&name("n0999x")).unwrap()), None);
+
+        let mut rowids = vec![cursor.seek_last().unwrap().unwrap().rowid];
+        while let Some(entry) = cursor.prev().unwrap() {
+            rowids.push(entry.rowid);
+        }
+        assert_eq!(rowids.len(), 2000);
+        assert_eq!(rowids[..2], [3998, 1998]);
+    }
+}
@@ -0,0 +1,881 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    parsing_error::ParsingError,
+    printf::printf,
+    value::{Value, parse_numeric},
+};
+
+/// Milliseconds in a day.
+const DAY: i64 = 86_400_000;
+/// Julian day of the Unix epoch, in milliseconds.
+const UNIX_EPOCH_JD: i64 = 210_866_760_000_000;
+/// Largest valid Julian day in milliseconds, the end of the year 9999.
+const MAX_JD: i64 = 464_269_060_799_999;
+
+/// A point in time, as the Julian day and/or the calendar date and time it was given as,
+/// computed from one another when needed, like SQLite's `DateTime`. Times are all UTC, the
+/// local time zone being UTC too.
+#[derive(Clone, Default)]
+struct DateTime {
+    /// Julian day, in milliseconds.
+    jd: i64,
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: f64,
+    /// Time zone offset in minutes.
+    tz: i64,
+    valid_jd: bool,
+    valid_ymd: bool,
+    valid_hms: bool,
+    valid_tz: bool,
+    /// The value was a bare number, not yet known to be a Julian day or a Unix time.
+    raw: Option<f64>,
+    use_subsec: bool,
+    /// Days past the end of the month after adding months or years, removed by `floor`.
+    floor_days: i64,
+    error: bool,
+}
+
+impl DateTime {
+    fn now() -> Self {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as i64);
+        DateTime {
+            jd: elapsed + UNIX_EPOCH_JD,
+            valid_jd: true,
+            ..DateTime::default()
+        }
+    }
+
+    /// A bare number, a Julian day unless a modifier says it's a Unix time.
+    fn from_number(value: f64) -> Self {
+        let mut date = DateTime {
+            raw: Some(value),
+            ..DateTime::default()
+        };
+        if (0.0..5_373_484.5).contains(&value) {
+            date.jd = (value * DAY as f64 + 0.5) as i64;
+            date.valid_jd = true;
+        }
+        date
+    }
+
+    /// Reads `YYYY-MM-DD [HH:MM[:SS[.SSS]]]`, `HH:MM[:SS[.SSS]]` (optionally followed by a time
+    /// zone), `now` or a number.
+    fn parse(text: &str) -> Option<Self> {
+        let mut date = DateTime::default();
+        if date.parse_date(text) || date.parse_time(text) {
+            return Some(date);
+        }
+        if text.eq_ignore_ascii_case("now") {
+            return Some(DateTime::now());
+        }
+        if let Some(value) = parse_numeric(text) {
+            return Some(DateTime::from_number(value.to_real()));
+        }
+        if text.eq_ignore_ascii_case("subsec") || text.eq_ignore_ascii_case("subsecond") {
+            return Some(DateTime {
+                use_subsec: true,
+                ..DateTime::now()
+            });
+        }
+        None
+    }
+
+    fn parse_date(&mut self, text: &str) -> bool {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text),
+        };
+        let Some([year, month, day]) = digits(text, [(4, 0, 9999), (2, 1, 12), (2, 1, 31)], '-')
+        else {
+            return false;
+        };
+        let time = text[10..].trim_start_matches(|c: char| c.is_ascii_whitespace() || c == 'T');
+        if !self.parse_time(time) {
+            if !time.is_empty() {
+                return false;
+            }
+            self.valid_hms = false;
+        }
+        self.valid_jd = false;
+        self.valid_ymd = true;
+        self.year = if negative { -year } else { year };
+        self.month = month;
+        self.day = day;
+        self.compute_floor();
+        if self.tz != 0 {
+            self.compute_jd();
+        }
+        true
+    }
+
+    fn parse_time(&mut self, text: &str) -> bool {
+        let Some([hour, minute]) = digits(text, [(2, 0, 24), (2, 0, 59)], ':') else {
+            return false;
+        };
+        let mut rest = &text[5..];
+        let mut second = 0.0;
+        if let Some(seconds) = rest.strip_prefix(':') {
+            let Some([whole]) = digits(seconds, [(2, 0, 59)], ':') else {
+                return false;
+            };
+            second = whole as f64;
+            rest = &seconds[2..];
+            if let Some(fraction) = rest.strip_prefix('.')
+                && fraction.starts_with(|c: char| c.is_ascii_digit())
+            {
+                let length = fraction
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(fraction.len());
+                let milliseconds = format!("0.{}", &fraction[..length])
+                    .parse::<f64>()
+                    .unwrap_or(0.0);
+                // Truncated to avoid sub-millisecond rounding issues
+                second += milliseconds.min(0.999);
+                rest = &fraction[length..];
+            }
+        }
+        let Some(tz) = parse_timezone(rest) else {
+            return false;
+        };
+        self.valid_jd = false;
+        self.raw = None;
+        self.valid_hms = true;
+        self.hour = hour;
+        self.minute = minute;
+        self.second = second;
+        self.tz = tz;
+        self.valid_tz = tz != 0;
+        true
+    }
+
+    fn fail(&mut self) {
+        *self = DateTime {
+            error: true,
+            ..DateTime::default()
+        };
+    }
+
+    fn compute_jd(&mut self) {
+        if self.valid_jd {
+            return;
+        }
+        let (mut year, mut month, day) = if self.valid_ymd {
+            (self.year, self.month, self.day)
+        } else {
+            (2000, 1, 1)
+        };
+        if !(-4713..=9999).contains(&year) || self.raw.is_some() {
+            self.fail();
+            return;
+        }
+        if month <= 2 {
+            year -= 1;
+            month += 12;
+        }
+        let a = year / 100;
+        let b = 2 - a + a / 4;
+        let x1 = 36525 * (year + 4716) / 100;
+        let x2 = 306001 * (month + 1) / 10000;
+        self.jd = (((x1 + x2 + day + b) as f64 - 1524.5) * DAY as f64) as i64;
+        self.valid_jd = true;
+        if self.valid_hms {
+            self.jd +=
+                self.hour * 3_600_000 + self.minute * 60_000 + (self.second * 1000.0 + 0.5) as i64;
+            if self.valid_tz {
+                self.jd -= self.tz * 60_000;
+                self.valid_ymd = false;
+                self.valid_hms = false;
+                self.valid_tz = false;
+            }
+        }
+    }
+
+    fn compute_ymd(&mut self) {
+        if self.valid_ymd {
+            return;
+        }
+        if !self.valid_jd {
+            (self.year, self.month, self.day) = (2000, 1, 1);
+        } else if !(0..=MAX_JD).contains(&self.jd) {
+            self.fail();
+            return;
+        } else {
+            let z = (self.jd + DAY / 2) / DAY;
+            let alpha = ((z as f64 + 32044.75) / 36524.25) as i64 - 52;
+            let a = z + 1 + alpha - (alpha + 100) / 4 + 25;
+            let b = a + 1524;
+            let c = ((b as f64 - 122.1) / 365.25) as i64;
+            let d = (36525 * (c & 32767)) / 100;
+            let e = ((b - d) as f64 / 30.6001) as i64;
+            let x1 = (30.6001 * e as f64) as i64;
+            self.day = b - d - x1;
+            self.month = if e < 14 { e - 1 } else { e - 13 };
+            self.year = if self.month > 2 { c - 4716 } else { c - 4715 };
+        }
+        self.valid_ymd = true;
+    }
+
+    fn compute_hms(&mut self) {
+        if self.valid_hms {
+            return;
+        }
+        self.compute_jd();
+        let milliseconds = (self.jd + DAY / 2) % DAY;
+        self.second = (milliseconds % 60_000) as f64 / 1000.0;
+        let minutes = milliseconds / 60_000;
+        self.minute = minutes % 60;
+        self.hour = minutes / 60;
+        self.raw = None;
+        self.valid_hms = true;
+    }
+
+    fn compute_ymd_hms(&mut self) {
+        self.compute_ymd();
+        self.compute_hms();
+    }
+
+    fn clear_ymd_hms_tz(&mut self) {
+        self.valid_ymd = false;
+        self.valid_hms = false;
+        self.valid_tz = false;
+    }
+
+    /// Number of days the date is past the end of its month, for `floor`.
+    fn compute_floor(&mut self) {
+        let leap = self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0);
+        self.floor_days = match self.month {
+            _ if self.day <= 28 => 0,
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 0,
+            2 if leap => self.day - 29,
+            2 => self.day - 28,
+            _ => i64::from(self.day == 31),
+        };
+    }
+
+    /// Adds whole months to the date, normalizing the year.
+    fn add_months(&mut self, months: i64) {
+        self.month += months;
+        let years = if self.month > 0 {
+            (self.month - 1) / 12
+        } else {
+            (self.month - 12) / 12
+        };
+        self.year += years;
+        self.month -= years * 12;
+    }
+
+    /// Applies a modifier such as `+1 day`, `start of month` or `unixepoch`, `first` telling
+    /// whether it directly follows the time value. Returns whether the modifier is valid.
+    fn apply(&mut self, modifier: &str, first: bool) -> bool {
+        let modifier = modifier.to_ascii_lowercase();
+        match modifier.as_str() {
+            "auto" | "julianday" | "unixepoch" if !first => false,
+            "auto" => {
+                // Time values that aren't numbers are left as they are
+                let Some(raw) = self.raw else {
+                    return true;
+                };
+                if self.valid_jd {
+                    self.raw = None;
+                    return true;
+                }
+                let is_unix_time = (-210_866_760_000.0..=253_402_300_799.0).contains(&raw);
+                if is_unix_time {
+                    self.set_unix_time(raw);
+                }
+                is_unix_time
+            }
+            "julianday" => {
+                if self.valid_jd && self.raw.is_some() {
+                    self.raw = None;
+                    return true;
+                }
+                false
+            }
+            "unixepoch" => match self.raw {
+                Some(raw) => {
+                    let jd = raw * 1000.0 + UNIX_EPOCH_JD as f64;
+                    let in_range = (0.0..(MAX_JD + 1) as f64).contains(&jd);
+                    if in_range {
+                        self.set_unix_time(raw);
+                    }
+                    in_range
+                }
+                None => false,
+            },
+            // The local time zone is UTC, converting only normalizes the date
+            "localtime" | "utc" => {
+                self.compute_jd();
+                self.clear_ymd_hms_tz();
+                true
+            }
+            "ceiling" => {
+                self.compute_jd();
+                self.clear_ymd_hms_tz();
+                true
+            }
+            "floor" => {
+                self.compute_jd();
+                self.jd -= self.floor_days * DAY;
+                self.clear_ymd_hms_tz();
+                true
+            }
+            "subsec" | "subsecond" => {
+                self.use_subsec = true;
+                true
+            }
+            "start of day" | "start of month" | "start of year" => {
+                self.compute_ymd();
+                self.valid_hms = true;
+                (self.hour, self.minute, self.second) = (0, 0, 0.0);
+                self.raw = None;
+                self.valid_tz = false;
+                self.valid_jd = false;
+                if modifier != "start of day" {
+                    self.day = 1;
+                }
+                if modifier == "start of year" {
+                    self.month = 1;
+                }
+                true
+            }
+            _ if modifier.starts_with("weekday ") => {
+                match parse_numeric(&modifier[8..]).map(|value| value.to_real()) {
+                    Some(weekday) if (0.0..7.0).contains(&weekday) && weekday.fract() == 0.0 => {
+                        self.compute_ymd_hms();
+                        self.valid_tz = false;
+                        self.valid_jd = false;
+                        self.compute_jd();
+                        let mut current = ((self.jd + 129_600_000) / DAY) % 7;
+                        if current > weekday as i64 {
+                            current -= 7;
+                        }
+                        self.jd += (weekday as i64 - current) * DAY;
+                        self.clear_ymd_hms_tz();
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ if modifier.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') => {
+                self.apply_offset(&modifier)
+            }
+            _ => false,
+        }
+    }
+
+    fn set_unix_time(&mut self, seconds: f64) {
+        self.clear_ymd_hms_tz();
+        self.jd = (seconds * 1000.0 + UNIX_EPOCH_JD as f64 + 0.5) as i64;
+        self.valid_jd = true;
+        self.raw = None;
+    }
+
+    /// `±N unit`, `±HH:MM[:SS[.SSS]]` and `±YYYY-MM-DD [HH:MM[:SS[.SSS]]]`.
+    fn apply_offset(&mut self, modifier: &str) -> bool {
+        let negative = modifier.starts_with('-');
+        let length = modifier
+            .find(|c: char| c == ':' || c.is_ascii_whitespace())
+            .unwrap_or(modifier.len());
+
+        if matches!(modifier.as_bytes().first(), Some(b'+' | b'-'))
+            && modifier.as_bytes().get(5) == Some(&b'-')
+            && length > 5
+        {
+            return self.add_date(&modifier[1..], negative);
+        }
+        if modifier.as_bytes().get(length) == Some(&b':') {
+            let time = modifier.trim_start_matches(['+', '-']);
+            return self.add_time(time, negative);
+        }
+
+        let Some(amount) = parse_numeric(&modifier[..length]).map(|value| value.to_real()) else {
+            return false;
+        };
+        let unit = modifier[length..].trim_start();
+        let unit = unit.strip_suffix('s').unwrap_or(unit);
+        // Largest amount and milliseconds of each unit
+        let (limit, milliseconds) = match unit {
+            "second" => (4.6427e14, 1000.0),
+            "minute" => (7.7379e12, 60_000.0),
+            "hour" => (1.2897e11, 3_600_000.0),
+            "day" => (5_373_485.0, DAY as f64),
+            "month" => (176_546.0, 30.0 * DAY as f64),
+            "year" => (14_713.0, 365.0 * DAY as f64),
+            _ => return false,
+        };
+        if amount.abs() >= limit {
+            return false;
+        }
+
+        self.compute_jd();
+        self.floor_days = 0;
+        let mut amount = amount;
+        if unit == "month" || unit == "year" {
+            self.compute_ymd_hms();
+            if unit == "month" {
+                self.add_months(amount as i64);
+            } else {
+                self.year += amount as i64;
+            }
+            self.compute_floor();
+            self.valid_jd = false;
+            amount = amount.fract();
+        }
+        self.compute_jd();
+        let rounder = if amount < 0.0 { -0.5 } else { 0.5 };
+        self.jd += (amount * milliseconds + rounder) as i64;
+        self.clear_ymd_hms_tz();
+        true
+    }
+
+    /// Adds (or subtracts) `HH:MM[:SS[.SSS]]`.
+    fn add_time(&mut self, time: &str, negative: bool) -> bool {
+        let mut offset = DateTime::default();
+        if !offset.parse_time(time) {
+            return false;
+        }
+        offset.compute_jd();
+        offset.jd -= DAY / 2;
+        offset.jd -= (offset.jd / DAY) * DAY;
+        if negative {
+            offset.jd = -offset.jd;
+        }
+        self.compute_jd();
+        self.clear_ymd_hms_tz();
+        self.jd += offset.jd;
+        true
+    }
+
+    /// Adds (or subtracts) `YYYY-MM-DD [HH:MM[:SS[.SSS]]]`, with 0 to 11 months and 0 to 30
+    /// days.
+    fn add_date(&mut self, text: &str, negative: bool) -> bool {
+        let Some([years, months, days]) = digits(text, [(4, 0, 9999), (2, 0, 11), (2, 0, 30)], '-')
+        else {
+            return false;
+        };
+        let time = &text[10..];
+        if !time.is_empty() && !time.starts_with(|c: char| c.is_ascii_whitespace()) {
+            return false;
+        }
+
+        self.compute_ymd_hms();
+        self.valid_jd = false;
+        let sign = if negative { -1 } else { 1 };
+        self.year += sign * years;
+        self.add_months(sign * months);
+        self.compute_floor();
+        self.compute_jd();
+        self.valid_hms = false;
+        self.valid_ymd = false;
+        self.jd += sign * days * DAY;
+
+        let time = time.trim_start();
+        time.is_empty() || self.add_time(time, negative)
+    }
+
+    /// Computes the date from the arguments of a date function: a time value then modifiers.
+    /// `None` when any of them is invalid or NULL.
+    fn from_arguments(arguments: &[Value]) -> Option<Self> {
+        let Some((value, modifiers)) = arguments.split_first() else {
+            return Some(DateTime::now());
+        };
+        let mut date = match value {
+            Value::Null => return None,
+            Value::Integer(_) | Value::Real(_) => DateTime::from_number(value.to_real()),
+            value => DateTime::parse(&value.to_text())?,
+        };
+        for (position, modifier) in modifiers.iter().enumerate() {
+            if modifier.is_null() || !date.apply(&modifier.to_text(), position == 0) {
+                return None;
+            }
+        }
+        date.compute_jd();
+        if date.error || !(0..=MAX_JD).contains(&date.jd) {
+            return None;
+        }
+        // An out of range day such as 2023-02-31 is normalized to 2023-03-03
+        if modifiers.is_empty() && date.valid_ymd && date.day > 28 {
+            date.valid_ymd = false;
+        }
+        Some(date)
+    }
+
+    fn date(&mut self) -> String {
+        self.compute_ymd();
+        let sign = if self.year < 0 { "-" } else { "" };
+        format!(
+            "{sign}{:04}-{:02}-{:02}",
+            self.year.abs(),
+            self.month,
+            self.day
+        )
+    }
+
+    fn time(&mut self) -> String {
+        self.compute_hms();
+        if self.use_subsec {
+            let milliseconds = (self.second * 1000.0 + 0.5) as i64;
+            format!(
+                "{:02}:{:02}:{:02}.{:03}",
+                self.hour,
+                self.minute,
+                milliseconds / 1000,
+                milliseconds % 1000
+            )
+        } else {
+            format!(
+                "{:02}:{:02}:{:02}",
+                self.hour, self.minute, self.second as i64
+            )
+        }
+    }
+
+    fn days_after_jan_01(&self) -> i64 {
+        let mut start = self.clone();
+        start.valid_jd = false;
+        start.month = 1;
+        start.day = 1;
+        start.compute_jd();
+        (self.jd - start.jd + DAY / 2) / DAY
+    }
+
+    fn days_after_monday(&self) -> i64 {
+        ((self.jd + DAY / 2) / DAY) % 7
+    }
+
+    fn days_after_sunday(&self) -> i64 {
+        ((self.jd + 129_600_000) / DAY) % 7
+    }
+
+    /// The Thursday of the same ISO week, whose year is the ISO year of the date.
+    fn iso_thursday(&self) -> DateTime {
+        let mut thursday = self.clone();
+        thursday.jd += (3 - self.days_after_monday()) * DAY;
+        thursday.valid_ymd = false;
+        thursday.compute_ymd();
+        thursday
+    }
+
+    /// Formats the date with `strftime` conversions, `None` for an unknown one.
+    fn format(&mut self, format: &str) -> Option<String> {
+        self.compute_jd();
+        self.compute_ymd_hms();
+        let twelve_hour = match self.hour {
+            0 => 12,
+            hour if hour > 12 => hour - 12,
+            hour => hour,
+        };
+
+        let mut output = String::new();
+        let mut chars = format.chars();
+        while let Some(character) = chars.next() {
+            if character != '%' {
+                output.push(character);
+                continue;
+            }
+            let converted = match chars.next()? {
+                'd' => format!("{:02}", self.day),
+                'e' => format!("{:2}", self.day),
+                'f' => format!("{:06.3}", self.second.min(59.999)),
+                'F' => format!("{:04}-{:02}-{:02}", self.year, self.month, self.day),
+                'G' => format!("{:04}", self.iso_thursday().year),
+                'g' => format!("{:02}", self.iso_thursday().year % 100),
+                'H' => format!("{:02}", self.hour),
+                'I' => format!("{twelve_hour:02}"),
+                'j' => format!("{:03}", self.days_after_jan_01() + 1),
+                'J' => {
+                    let day = Value::Real(self.jd as f64 / DAY as f64);
+                    printf(&[Value::Text("%.16g".to_string()), day])
+                        .ok()?
+                        .to_text()
+                }
+                'k' => format!("{:2}", self.hour),
+                'l' => format!("{twelve_hour:2}"),
+                'm' => format!("{:02}", self.month),
+                'M' => format!("{:02}", self.minute),
+                'p' => if self.hour >= 12 { "PM" } else { "AM" }.to_string(),
+                'P' => if self.hour >= 12 { "pm" } else { "am" }.to_string(),
+                'R' => format!("{:02}:{:02}", self.hour, self.minute),
+                's' if self.use_subsec => {
+                    format!("{:.3}", (self.jd - UNIX_EPOCH_JD) as f64 / 1000.0)
+                }
+                's' => (self.jd / 1000 - UNIX_EPOCH_JD / 1000).to_string(),
+                'S' => format!("{:02}", self.second as i64),
+                'T' => format!(
+                    "{:02}:{:02}:{:02}",
+                    self.hour, self.minute, self.second as i64
+                ),
+                'u' => (self.days_after_monday() + 1).to_string(),
+                'w' => self.days_after_sunday().to_string(),
+                'U' => format!(
+                    "{:02}",
+                    (self.days_after_jan_01() - self.days_after_sunday() + 7) / 7
+                ),
+                'V' => format!("{:02}", self.iso_thursday().days_after_jan_01() / 7 + 1),
+                'W' => format!(
+                    "{:02}",
+                    (self.days_after_jan_01() - self.days_after_monday() + 7) / 7
+                ),
+                'Y' => format!("{:04}", self.year),
+                '%' => "%".to_string(),
+                _ => return None,
+            };
+            output.push_str(&converted);
+        }
+        Some(output)
+    }
+}
+
+/// Reads fixed-width numbers separated by `separator`, each given as its number of digits
+/// and its range.
+fn digits<const N: usize>(
+    text: &str,
+    fields: [(usize, i64, i64); N],
+    separator: char,
+) -> Option<[i64; N]> {
+    let mut values = [0; N];
+    let mut rest = text;
+    for (index, (width, min, max)) in fields.into_iter().enumerate() {
+        if index > 0 {
+            rest = rest.strip_prefix(separator)?;
+        }
+        let field = rest.get(..width)?;
+        if !field.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        let value = field.parse().ok()?;
+        if !(min..=max).contains(&value) {
+            return None;
+        }
+        values[index] = value;
+        rest = &rest[width..];
+    }
+    Some(values)
+}
+
+/// Reads an optional `Z` or `±HH:MM` time zone ending the text, as an offset in minutes.
+fn parse_timezone(text: &str) -> Option<i64> {
+    let text = text.trim_start();
+    let (offset, rest) = match text.chars().next() {
+        None => return Some(0),
+        Some('Z' | 'z') => (0, &text[1..]),
+        Some(sign @ ('+' | '-')) => {
+            let [hours, minutes] = digits(&text[1..], [(2, 0, 14), (2, 0, 59)], ':')?;
+            let offset = hours * 60 + minutes;
+            (if sign == '-' { -offset } else { offset }, &text[6..])
+        }
+        Some(_) => return None,
+    };
+    rest.trim_start().is_empty().then_some(offset)
+}
+
+/// Runs `output` on the date computed from the arguments, NULL when they're invalid.
+fn with_date(arguments: &[Value], output: impl Fn(&mut DateTime) -> Value) -> Value {
+    DateTime::from_arguments(arguments).map_or(Value::Null, |mut date| output(&mut date))
+}
+
+/// `date(time, modifiers...)`, as `YYYY-MM-DD`.
+pub fn date(arguments: &[Value]) -> Result<Value, ParsingError> {
+    Ok(with_date(arguments, |date| Value::Text(date.date())))
+}
+
+/// `time(time, modifiers...)`, as `HH:MM:SS`.
+pub fn time(arguments: &[Value]) -> Result<Value, ParsingError> {
+    Ok(with_date(arguments, |date| Value::Text(date.time())))
+}
+
+/// `datetime(time, modifiers...)`, as `YYYY-MM-DD HH:MM:SS`.
+pub fn datetime(arguments: &[Value]) -> Result<Value, ParsingError> {
+    Ok(with_date(arguments, |date| {
+        Value::Text(format!("{} {}", date.date(), date.time()))
+    }))
+}
+
+/// `julianday(time, modifiers...)`, as a real number of days.
+pub fn julianday(arguments: &[Value]) -> Result<Value, ParsingError> {
+    Ok(with_date(arguments, |date| {
+        Value::Real(date.jd as f64 / DAY as f64)
+    }))
+}
+
+/// `unixepoch(time, modifiers...)`, in seconds, with a fraction when `subsec` is used.
+pub fn unixepoch(arguments: &[Value]) -> Result<Value, ParsingError> {
+    Ok(with_date(arguments, |date| {
+        if date.use_subsec {
+            Value::Real((date.jd - UNIX_EPOCH_JD) as f64 / 1000.0)
+        } else {
+            Value::Integer(date.jd / 1000 - UNIX_EPOCH_JD / 1000)
+        }
+    }))
+}
+
+/// `strftime(format, time, modifiers...)`
+pub fn strftime(arguments: &[Value]) -> Result<Value, ParsingError> {
+    let format = match arguments.first() {
+        None | Some(Value::Null) => return Ok(Value::Null),
+        Some(format) => format.to_text(),
+    };
+    Ok(with_date(&arguments[1..], |date| {
+        date.format(&format).map_or(Value::Null, Value::Text)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    /// Calls `function` with a time value and text modifiers.
+    fn call(
+        function: fn(&[Value]) -> Result<Value, ParsingError>,
+        time: Value,
+        modifiers: &[&str],
+    ) -> Value {
+        let arguments = std::iter::once(time)
+            .chain(modifiers.iter().map(|modifier| text(modifier)))
+            .collect::<Vec<_>>();
+        function(&arguments).unwrap()
+    }
+
+    #[test]
+    fn month_and_year_arithmetic_overflows_into_the_next_month() {
+        let cases = [
+            ("2024-01-31", "+1 month", "2024-03-02 00:00:00"),
+            ("2024-03-31", "-1 month", "2024-03-02 00:00:00"),
+            ("2024-02-29", "+1 year", "2025-03-01 00:00:00"),
+        ];
+        for (time, modifier, expected) in cases {
+            assert_eq!(
+                call(datetime, text(time), &[modifier]),
+                text(expected),
+                "{modifier}"
+            );
+        }
+        assert_eq!(
+            call(date, text("2024-01-31"), &["+1 month", "floor"]),
+            text("2024-02-29")
+        );
+        assert_eq!(
+            call(date, text("2024-01-31"), &["+1 month", "ceiling"]),
+            text("2024-03-02")
+        );
+    }
+
+    #[test]
+    fn modifiers_apply_in_order() {
+        assert_eq!(
+            call(datetime, text("2024-01-01 10:00"), &["+1.5 hours"]),
+            text("2024-01-01 11:30:00")
+        );
+        assert_eq!(
+            call(
+                datetime,
+                text("2024-01-01"),
+                &["+1 day", "start of day", "-1 second"]
+            ),
+            text("2024-01-01 23:59:59")
+        );
+        assert_eq!(
+            call(date, text("2024-05-15"), &["+2 years", "-3 days"]),
+            text("2026-05-12")
+        );
+        assert_eq!(
+            call(time, text("12:34:56"), &["+10 minutes"]),
+            text("12:44:56")
+        );
+    }
+
+    #[test]
+    fn start_of_and_weekday() {
+        assert_eq!(
+            call(datetime, text("2024-05-15 13:45:10"), &["start of month"]),
+            text("2024-05-01 00:00:00")
+        );
+        assert_eq!(
+            call(date, text("2024-05-15"), &["start of year"]),
+            text("2024-01-01")
+        );
+        assert_eq!(
+            call(date, text("2024-05-15"), &["weekday 0"]),
+            text("2024-05-19")
+        );
+        assert_eq!(
+            call(date, text("2024-05-19"), &["weekday 0"]),
+            text("2024-05-19")
+        );
+    }
+
+    #[test]
+    fn time_offsets() {
+        assert_eq!(
+            call(datetime, text("2024-01-01"), &["+01:30"]),
+            text("2024-01-01 01:30:00")
+        );
+        assert_eq!(
+            call(datetime, text("2024-01-01 12:00"), &["-00:30:15"]),
+            text("2024-01-01 11:29:45")
+        );
+    }
+
+    #[test]
+    fn numeric_time_values() {
+        let unix = Value::Integer(1_700_000_000);
+        assert_eq!(
+            call(datetime, unix.clone(), &["unixepoch"]),
+            text("2023-11-14 22:13:20")
+        );
+        assert_eq!(call(datetime, unix, &["auto"]), text("2023-11-14 22:13:20"));
+        assert_eq!(
+            call(datetime, Value::Real(2_460_000.5), &["auto"]),
+            text("2023-02-25 00:00:00")
+        );
+        assert_eq!(
+            call(datetime, text("2024-01-01"), &["auto"]),
+            text("2024-01-01 00:00:00")
+        );
+    }
+
+    #[test]
+    fn subsec_and_invalid_modifiers() {
+        assert_eq!(
+            call(datetime, text("2024-01-01"), &["subsec"]),
+            text("2024-01-01 00:00:00.000")
+        );
+        assert_eq!(
+            call(datetime, text("2024-01-01 00:00:00.123"), &["subsec"]),
+            text("2024-01-01 00:00:00.123")
+        );
+        assert_eq!(call(datetime, text("2024-01-01"), &["bogus"]), Value::Null);
+    }
+
+    #[test]
+    fn other_outputs() {
+        assert_eq!(
+            call(julianday, text("2000-01-01"), &[]),
+            Value::Real(2_451_544.5)
+        );
+        assert_eq!(
+            call(unixepoch, text("2024-01-01"), &[]),
+            Value::Integer(1_704_067_200)
+        );
+        assert_eq!(
+            strftime(&[
+                text("%Y/%m/%d %H:%M:%f %j %w"),
+                text("2024-03-05 06:07:08.9")
+            ])
+            .unwrap(),
+            text("2024/03/05 06:07:08.900 065 2")
+        );
+    }
+}
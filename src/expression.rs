@@ -0,0 +1,593 @@
+use std::cmp::Ordering;
+
+use crate::{
+    expression_parser::{BinaryOp, UnaryOp},
+    function::ScalarFunction,
+    parsing_error::ParsingError,
+    select_builder::{Op, Row, WhereColumn},
+    value::{Affinity, Collation, Value},
+};
+
+/// Conversions applied to the operands of a comparison and the collation comparing them, see
+/// https://www.sqlite.org/datatype3.html#type_conversions_prior_to_comparison
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Comparator {
+    /// Affinity applied to the left operand before comparing it.
+    pub lhs: Option<Affinity>,
+    /// Affinity applied to the right operand before comparing it.
+    pub rhs: Option<Affinity>,
+    pub collation: Collation,
+}
+
+impl Comparator {
+    /// Comparator for operands of the given affinities, `None` standing for expressions that
+    /// have no affinity, like literals.
+    pub fn new(lhs: Option<Affinity>, rhs: Option<Affinity>, collation: Collation) -> Self {
+        fn conversion(operand: Option<Affinity>, other: Option<Affinity>) -> Option<Affinity> {
+            match (operand, other) {
+                (Some(operand), Some(other)) if !operand.is_numeric() && other.is_numeric() => {
+                    Some(Affinity::Numeric)
+                }
+                (None, Some(other)) if other.is_numeric() => Some(Affinity::Numeric),
+                (None, Some(Affinity::Text)) => Some(Affinity::Text),
+                _ => None,
+            }
+        }
+        Comparator {
+            lhs: conversion(lhs, rhs),
+            rhs: conversion(rhs, lhs),
+            collation,
+        }
+    }
+
+    /// Comparator of `lhs IN (...)`, which converts the values of the list to the affinity of
+    /// `lhs` when it has a TEXT or numeric one.
+    fn for_in(lhs: Option<Affinity>, collation: Collation) -> Self {
+        Comparator {
+            lhs: None,
+            rhs: lhs.filter(|affinity| *affinity != Affinity::Blob),
+            collation,
+        }
+    }
+
+    /// Orders the operands, `None` when either of them is NULL.
+    pub fn compare(&self, lhs: Value, rhs: Value) -> Option<Ordering> {
+        if lhs.is_null() || rhs.is_null() {
+            return None;
+        }
+        let convert = |value: Value, affinity: Option<Affinity>| match affinity {
+            Some(affinity) => value.apply_affinity(affinity),
+            None => value,
+        };
+        let (lhs, rhs) = (convert(lhs, self.lhs), convert(rhs, self.rhs));
+        Some(lhs.compare_with(&rhs, self.collation))
+    }
+
+    fn swap(self) -> Self {
+        Comparator {
+            lhs: self.rhs,
+            rhs: self.lhs,
+            collation: self.collation,
+        }
+    }
+}
+
+/// One `WHEN ... THEN ...` of a CASE expression.
+#[derive(Clone, Debug)]
+pub struct CaseBranch {
+    pub when: Expression,
+    /// Compares the operand of the CASE to `when`, unused without operand.
+    pub comparator: Comparator,
+    pub then: Expression,
+}
+
+/// An expression bound to the columns of the rows it's evaluated on.
+#[derive(Clone, Debug)]
+pub enum Expression {
+    Literal(Value),
+    Column(WhereColumn, Affinity),
+    /// Result of the aggregate at this position among the aggregates of the query, read from
+    /// the row of a group.
+    Aggregate(usize),
+    Unary(UnaryOp, Box<Expression>),
+    /// Any binary operator but the comparisons.
+    Binary(Box<Expression>, BinaryOp, Box<Expression>),
+    Comparison(Box<Expression>, Op, Box<Expression>, Comparator),
+    Is {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        comparator: Comparator,
+        negated: bool,
+    },
+    In {
+        expression: Box<Expression>,
+        list: Vec<Expression>,
+        comparator: Comparator,
+        negated: bool,
+    },
+    Like {
+        expression: Box<Expression>,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+        negated: bool,
+        glob: bool,
+    },
+    Case {
+        operand: Option<Box<Expression>>,
+        branches: Vec<CaseBranch>,
+        otherwise: Option<Box<Expression>>,
+    },
+    Cast(Box<Expression>, Affinity),
+    Function(ScalarFunction, Vec<Expression>),
+}
+
+impl Expression {
+    /// `lhs op rhs`. Literals are converted ahead of time, and moved to the right-hand side,
+    /// so that comparisons of a column to a literal are easy to spot for index scans.
+    pub fn comparison(lhs: Expression, op: Op, rhs: Expression, collation: Collation) -> Self {
+        let comparator = Comparator::new(lhs.affinity(), rhs.affinity(), collation);
+        let (lhs, op, rhs, comparator) = match (lhs, rhs) {
+            (Expression::Literal(lhs), rhs) if !matches!(rhs, Expression::Literal(_)) => {
+                (rhs, op.flip(), Expression::Literal(lhs), comparator.swap())
+            }
+            (lhs, rhs) => (lhs, op, rhs, comparator),
+        };
+        let (rhs, comparator) = match (rhs, comparator.rhs) {
+            (Expression::Literal(value), Some(affinity)) => (
+                Expression::Literal(value.apply_affinity(affinity)),
+                Comparator {
+                    rhs: None,
+                    ..comparator
+                },
+            ),
+            (rhs, _) => (rhs, comparator),
+        };
+        Expression::Comparison(Box::new(lhs), op, Box::new(rhs), comparator)
+    }
+
+    /// `lhs IS [NOT] rhs`
+    pub fn is(lhs: Expression, rhs: Expression, negated: bool, collation: Collation) -> Self {
+        Expression::Is {
+            comparator: Comparator::new(lhs.affinity(), rhs.affinity(), collation),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            negated,
+        }
+    }
+
+    /// `expression [NOT] IN (list)`
+    pub fn in_list(
+        expression: Expression,
+        list: Vec<Expression>,
+        negated: bool,
+        collation: Collation,
+    ) -> Self {
+        Expression::In {
+            comparator: Comparator::for_in(expression.affinity(), collation),
+            expression: Box::new(expression),
+            list,
+            negated,
+        }
+    }
+
+    pub fn and(self, other: Expression) -> Self {
+        Expression::Binary(Box::new(self), BinaryOp::And, Box::new(other))
+    }
+
+    pub fn or(self, other: Expression) -> Self {
+        Expression::Binary(Box::new(self), BinaryOp::Or, Box::new(other))
+    }
+
+    /// Affinity of the expression, only columns and CASTs have one.
+    pub fn affinity(&self) -> Option<Affinity> {
+        match self {
+            Expression::Column(_, affinity) | Expression::Cast(_, affinity) => Some(*affinity),
+            _ => None,
+        }
+    }
+
+    /// Whether the row satisfies the expression used as a condition, NULL meaning it doesn't.
+    pub fn matches<R: Row>(&self, row: &R) -> Result<bool, ParsingError> {
+        Ok(self.evaluate(row)?.truth() == Some(true))
+    }
+
+    pub fn evaluate<R: Row>(&self, row: &R) -> Result<Value, ParsingError> {
+        match self {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Column(column, affinity) => row.read_with_affinity(column, *affinity),
+            Expression::Aggregate(position) => row.aggregate(*position),
+            Expression::Unary(op, operand) => Ok(unary(*op, operand.evaluate(row)?)),
+            Expression::Binary(lhs, BinaryOp::And, rhs) => {
+                let lhs = lhs.evaluate(row)?.truth();
+                if lhs == Some(false) {
+                    return Ok(boolean(Some(false)));
+                }
+                Ok(boolean(match (lhs, rhs.evaluate(row)?.truth()) {
+                    (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }))
+            }
+            Expression::Binary(lhs, BinaryOp::Or, rhs) => {
+                let lhs = lhs.evaluate(row)?.truth();
+                if lhs == Some(true) {
+                    return Ok(boolean(Some(true)));
+                }
+                Ok(boolean(match (lhs, rhs.evaluate(row)?.truth()) {
+                    (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }))
+            }
+            Expression::Binary(lhs, op, rhs) => {
+                Ok(binary(*op, lhs.evaluate(row)?, rhs.evaluate(row)?))
+            }
+            Expression::Comparison(lhs, op, rhs, comparator) => {
+                let ordering = comparator.compare(lhs.evaluate(row)?, rhs.evaluate(row)?);
+                Ok(boolean(ordering.map(|ordering| op.matches(ordering))))
+            }
+            Expression::Is {
+                lhs,
+                rhs,
+                comparator,
+                negated,
+            } => {
+                let (lhs, rhs) = (lhs.evaluate(row)?, rhs.evaluate(row)?);
+                let equal = match (lhs.is_null(), rhs.is_null()) {
+                    (true, true) => true,
+                    (false, false) => comparator.compare(lhs, rhs) == Some(Ordering::Equal),
+                    _ => false,
+                };
+                Ok(boolean(Some(equal != *negated)))
+            }
+            Expression::In {
+                expression,
+                list,
+                comparator,
+                negated,
+            } => {
+                let value = expression.evaluate(row)?;
+                if value.is_null() && !list.is_empty() {
+                    return Ok(Value::Null);
+                }
+                let mut found = Some(false);
+                for element in list {
+                    match comparator.compare(value.clone(), element.evaluate(row)?) {
+                        Some(Ordering::Equal) => {
+                            found = Some(true);
+                            break;
+                        }
+                        None => found = None,
+                        Some(_) => {}
+                    }
+                }
+                Ok(boolean(found.map(|found| found != *negated)))
+            }
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                negated,
+                glob,
+            } => {
+                let (value, pattern) = (expression.evaluate(row)?, pattern.evaluate(row)?);
+                let escape = match escape {
+                    Some(escape) => match escape.evaluate(row)? {
+                        Value::Null => return Ok(Value::Null),
+                        escape => {
+                            let escape = escape.to_text();
+                            let mut chars = escape.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(escape), None) => Some(escape),
+                                _ => {
+                                    return Err(ParsingError::Semantic(
+                                        "ESCAPE expression must be a single character".to_string(),
+                                    ));
+                                }
+                            }
+                        }
+                    },
+                    None => None,
+                };
+                if value.is_null() || pattern.is_null() {
+                    return Ok(Value::Null);
+                }
+
+                let text = value.to_text().chars().collect::<Vec<_>>();
+                let pattern = pattern.to_text().chars().collect::<Vec<_>>();
+                let found = if *glob {
+                    glob_match(&pattern, &text)
+                } else {
+                    like_match(&pattern, &text, escape)
+                };
+                Ok(boolean(Some(found != *negated)))
+            }
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = operand
+                    .as_ref()
+                    .map(|operand| operand.evaluate(row))
+                    .transpose()?;
+                for branch in branches {
+                    let when = branch.when.evaluate(row)?;
+                    let taken = match &operand {
+                        Some(operand) => {
+                            branch.comparator.compare(operand.clone(), when)
+                                == Some(Ordering::Equal)
+                        }
+                        None => when.truth() == Some(true),
+                    };
+                    if taken {
+                        return branch.then.evaluate(row);
+                    }
+                }
+                match otherwise {
+                    Some(otherwise) => otherwise.evaluate(row),
+                    None => Ok(Value::Null),
+                }
+            }
+            Expression::Cast(operand, affinity) => Ok(operand.evaluate(row)?.cast(*affinity)),
+            Expression::Function(function, arguments) => function.call(
+                &arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(row))
+                    .collect::<Result<Vec<_>, ParsingError>>()?,
+            ),
+        }
+    }
+
+    /// Splits the expression into the conditions ANDed together at its top.
+    pub fn into_conjuncts(self, conjuncts: &mut Vec<Expression>) {
+        match self {
+            Expression::Binary(lhs, BinaryOp::And, rhs) => {
+                lhs.into_conjuncts(conjuncts);
+                rhs.into_conjuncts(conjuncts);
+            }
+            expression => conjuncts.push(expression),
+        }
+    }
+
+    /// The conditions ANDed together at the top of the expression.
+    pub fn conjuncts(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Binary(lhs, BinaryOp::And, rhs) => {
+                let mut conjuncts = lhs.conjuncts();
+                conjuncts.extend(rhs.conjuncts());
+                conjuncts
+            }
+            expression => vec![expression],
+        }
+    }
+
+    /// Calls `visit` on each column the expression reads.
+    pub fn visit_columns(&self, visit: &mut dyn FnMut(&WhereColumn)) {
+        match self {
+            Expression::Literal(_) | Expression::Aggregate(_) => {}
+            Expression::Column(column, _) => visit(column),
+            Expression::Unary(_, operand) | Expression::Cast(operand, _) => {
+                operand.visit_columns(visit)
+            }
+            Expression::Binary(lhs, _, rhs)
+            | Expression::Comparison(lhs, _, rhs, _)
+            | Expression::Is { lhs, rhs, .. } => {
+                lhs.visit_columns(visit);
+                rhs.visit_columns(visit);
+            }
+            Expression::In {
+                expression, list, ..
+            } => {
+                expression.visit_columns(visit);
+                list.iter().for_each(|element| element.visit_columns(visit));
+            }
+            Expression::Function(_, arguments) => arguments
+                .iter()
+                .for_each(|argument| argument.visit_columns(visit)),
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                ..
+            } => {
+                expression.visit_columns(visit);
+                pattern.visit_columns(visit);
+                if let Some(escape) = escape {
+                    escape.visit_columns(visit);
+                }
+            }
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                if let Some(operand) = operand {
+                    operand.visit_columns(visit);
+                }
+                for branch in branches {
+                    branch.when.visit_columns(visit);
+                    branch.then.visit_columns(visit);
+                }
+                if let Some(otherwise) = otherwise {
+                    otherwise.visit_columns(visit);
+                }
+            }
+        }
+    }
+}
+
+/// Result of a condition, as the integer SQLite uses for booleans.
+fn boolean(value: Option<bool>) -> Value {
+    match value {
+        Some(value) => Value::Integer(i64::from(value)),
+        None => Value::Null,
+    }
+}
+
+fn unary(op: UnaryOp, value: Value) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+    match op {
+        UnaryOp::Plus => value,
+        UnaryOp::Not => boolean(value.truth().map(|value| !value)),
+        UnaryOp::BitNot => Value::Integer(!value.to_integer()),
+        UnaryOp::Negate => match value.to_numeric() {
+            Value::Integer(value) => value
+                .checked_neg()
+                .map_or(Value::Real(-(value as f64)), Value::Integer),
+            value => Value::Real(-value.to_real()),
+        },
+    }
+}
+
+/// Arithmetic, bitwise and concatenation operators. Integer results that overflow become
+/// reals, and dividing by zero gives NULL.
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+    if lhs.is_null() || rhs.is_null() {
+        return Value::Null;
+    }
+    if op == BinaryOp::Concat {
+        return Value::Text(lhs.to_text() + &rhs.to_text());
+    }
+
+    let (lhs_integer, rhs_integer) = (lhs.to_integer(), rhs.to_integer());
+    match op {
+        BinaryOp::BitAnd => return Value::Integer(lhs_integer & rhs_integer),
+        BinaryOp::BitOr => return Value::Integer(lhs_integer | rhs_integer),
+        BinaryOp::ShiftLeft => return Value::Integer(shift_left(lhs_integer, rhs_integer)),
+        BinaryOp::ShiftRight => {
+            return Value::Integer(shift_left(lhs_integer, rhs_integer.saturating_neg()));
+        }
+        _ => {}
+    }
+
+    let (lhs, rhs) = (lhs.to_numeric(), rhs.to_numeric());
+    let result = match (op, &lhs, &rhs) {
+        (BinaryOp::Divide | BinaryOp::Remainder, _, rhs) if rhs.to_real() == 0.0 => Value::Null,
+        (BinaryOp::Remainder, lhs, rhs) => {
+            // Remainders are computed on integers, reals only change the type of the result
+            let (dividend, divisor) = (lhs.to_integer(), rhs.to_integer());
+            if divisor == 0 {
+                return Value::Null;
+            }
+            let remainder = dividend.checked_rem(divisor).unwrap_or(0);
+            match (&lhs, &rhs) {
+                (Value::Integer(_), Value::Integer(_)) => Value::Integer(remainder),
+                _ => Value::Real(remainder as f64),
+            }
+        }
+        (op, Value::Integer(lhs), Value::Integer(rhs)) => {
+            let result = match op {
+                BinaryOp::Add => lhs.checked_add(*rhs),
+                BinaryOp::Subtract => lhs.checked_sub(*rhs),
+                BinaryOp::Multiply => lhs.checked_mul(*rhs),
+                _ => lhs.checked_div(*rhs),
+            };
+            match result {
+                Some(result) => Value::Integer(result),
+                None => Value::Real(real_arithmetic(op, *lhs as f64, *rhs as f64)),
+            }
+        }
+        (op, lhs, rhs) => Value::Real(real_arithmetic(op, lhs.to_real(), rhs.to_real())),
+    };
+    match result {
+        Value::Real(value) if value.is_nan() => Value::Null,
+        result => result,
+    }
+}
+
+fn real_arithmetic(op: BinaryOp, lhs: f64, rhs: f64) -> f64 {
+    match op {
+        BinaryOp::Add => lhs + rhs,
+        BinaryOp::Subtract => lhs - rhs,
+        BinaryOp::Multiply => lhs * rhs,
+        _ => lhs / rhs,
+    }
+}
+
+/// `value << amount`, shifting right for negative amounts. Shifting everything out gives 0,
+/// or -1 when shifting a negative value right.
+fn shift_left(value: i64, amount: i64) -> i64 {
+    match amount {
+        64.. => 0,
+        0..64 => value << amount,
+        ..=-64 => {
+            if value < 0 {
+                -1
+            } else {
+                0
+            }
+        }
+        _ => value >> -amount,
+    }
+}
+
+/// LIKE matching: `%` matches any sequence, `_` any character, and letters match regardless
+/// of their (ASCII) case.
+pub(crate) fn like_match(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    let Some((&first, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+    if Some(first) == escape {
+        return match (rest.split_first(), text.split_first()) {
+            (Some((&literal, rest)), Some((&character, text))) => {
+                literal.eq_ignore_ascii_case(&character) && like_match(rest, text, escape)
+            }
+            _ => false,
+        };
+    }
+    match first {
+        '%' => (0..=text.len()).any(|skipped| like_match(rest, &text[skipped..], escape)),
+        '_' => !text.is_empty() && like_match(rest, &text[1..], escape),
+        first => text.split_first().is_some_and(|(character, text)| {
+            first.eq_ignore_ascii_case(character) && like_match(rest, text, escape)
+        }),
+    }
+}
+
+/// GLOB matching: `*` matches any sequence, `?` any character and `[...]` (or `[^...]`) a
+/// character of (or not of) a set of characters and ranges, case-sensitively.
+pub(crate) fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let Some((&first, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+    match first {
+        '*' => (0..=text.len()).any(|skipped| glob_match(rest, &text[skipped..])),
+        '?' => !text.is_empty() && glob_match(rest, &text[1..]),
+        '[' => {
+            let Some((&character, text)) = text.split_first() else {
+                return false;
+            };
+            let negated = rest.first() == Some(&'^');
+            let set = if negated { &rest[1..] } else { rest };
+            // A `]` right after the opening bracket is part of the set
+            let Some(end) = set
+                .iter()
+                .skip(1)
+                .position(|&character| character == ']')
+                .map(|end| end + 1)
+            else {
+                return false;
+            };
+            let (set, rest) = (&set[..end], &set[end + 1..]);
+
+            let mut found = false;
+            let mut position = 0;
+            while position < set.len() {
+                if set.get(position + 1) == Some(&'-') && position + 2 < set.len() {
+                    found |= (set[position]..=set[position + 2]).contains(&character);
+                    position += 3;
+                } else {
+                    found |= set[position] == character;
+                    position += 1;
+                }
+            }
+            found != negated && glob_match(rest, text)
+        }
+        first => text
+            .split_first()
+            .is_some_and(|(&character, text)| first == character && glob_match(rest, text)),
+    }
+}
@@ -0,0 +1,805 @@
+use std::fmt::Display;
+
+use crate::{
+    aggregate::AggregateFunction,
+    lexer::TokenKind,
+    parser::{Parser, is_reserved},
+    parsing_error::ParsingError,
+    select_builder::Op,
+    value::Value,
+};
+
+const OR_KEYWORD: &str = "OR";
+const AND_KEYWORD: &str = "AND";
+const NOT_KEYWORD: &str = "NOT";
+const NULL_KEYWORD: &str = "NULL";
+const DISTINCT_KEYWORD: &str = "DISTINCT";
+
+/// Column of one of the tables of the FROM clause, optionally qualified with the name or alias
+/// of its table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnName {
+    pub table: Option<String>,
+    pub name: String,
+}
+
+impl Display for ColumnName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(table) = &self.table {
+            f.write_fmt(format_args!("{table}."))?;
+        }
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Plus,
+    Not,
+    BitNot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Comparison(Op),
+    BitAnd,
+    BitOr,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Concat,
+}
+
+impl BinaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Or => OR_KEYWORD,
+            BinaryOp::And => AND_KEYWORD,
+            BinaryOp::Comparison(op) => op.as_str(),
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Remainder => "%",
+            BinaryOp::Concat => "||",
+        }
+    }
+
+    /// Binding strength of the operator, see https://www.sqlite.org/lang_expr.html#operators
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 0,
+            BinaryOp::And => 1,
+            BinaryOp::Comparison(Op::Eq | Op::NotEq) => 3,
+            BinaryOp::Comparison(_) => 4,
+            BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 5,
+            BinaryOp::Add | BinaryOp::Subtract => 6,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => 7,
+            BinaryOp::Concat => 8,
+        }
+    }
+}
+
+/// Call of an aggregate function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedAggregate {
+    pub function: AggregateFunction,
+    /// Expression the aggregate runs over, `None` for the `*` of `COUNT(*)`.
+    pub argument: Option<Box<ParsedExpression>>,
+    pub distinct: bool,
+    /// Second argument of GROUP_CONCAT.
+    pub separator: Option<Value>,
+}
+
+impl Display for ParsedAggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}(", self.function))?;
+        if self.distinct {
+            f.write_str("DISTINCT ")?;
+        }
+        match &self.argument {
+            Some(argument) => argument.fmt(f)?,
+            None => f.write_str("*")?,
+        }
+        if let Some(separator) = &self.separator {
+            f.write_fmt(format_args!(", {}", separator.sql_literal()))?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// An SQL expression, as written in result columns and in the WHERE, ON, GROUP BY, HAVING and
+/// ORDER BY clauses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedExpression {
+    Literal(Value),
+    Column(ColumnName),
+    /// A double quoted name, which is read as a string when no column has that name, like
+    /// SQLite does.
+    QuotedName(String),
+    Unary(UnaryOp, Box<ParsedExpression>),
+    Binary(Box<ParsedExpression>, BinaryOp, Box<ParsedExpression>),
+    /// `lhs IS [NOT] rhs`, `ISNULL` and `NOTNULL` included.
+    Is {
+        lhs: Box<ParsedExpression>,
+        rhs: Box<ParsedExpression>,
+        negated: bool,
+    },
+    Between {
+        expression: Box<ParsedExpression>,
+        low: Box<ParsedExpression>,
+        high: Box<ParsedExpression>,
+        negated: bool,
+    },
+    In {
+        expression: Box<ParsedExpression>,
+        list: Vec<ParsedExpression>,
+        negated: bool,
+    },
+    /// `LIKE`, or `GLOB` when `glob` is set.
+    Like {
+        expression: Box<ParsedExpression>,
+        pattern: Box<ParsedExpression>,
+        escape: Option<Box<ParsedExpression>>,
+        negated: bool,
+        glob: bool,
+    },
+    Case {
+        operand: Option<Box<ParsedExpression>>,
+        /// `WHEN` and `THEN` expressions of each branch.
+        branches: Vec<(ParsedExpression, ParsedExpression)>,
+        otherwise: Option<Box<ParsedExpression>>,
+    },
+    /// `CAST(expression AS type)`
+    Cast(Box<ParsedExpression>, String),
+    Collate(Box<ParsedExpression>, String),
+    Aggregate(ParsedAggregate),
+    /// Call of a scalar function, looked up by name when binding.
+    Function {
+        name: String,
+        arguments: Vec<ParsedExpression>,
+    },
+}
+
+impl ParsedExpression {
+    /// Whether an aggregate function is called anywhere in the expression.
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            ParsedExpression::Aggregate(_) => true,
+            ParsedExpression::Literal(_)
+            | ParsedExpression::Column(_)
+            | ParsedExpression::QuotedName(_) => false,
+            ParsedExpression::Unary(_, operand)
+            | ParsedExpression::Cast(operand, _)
+            | ParsedExpression::Collate(operand, _) => operand.contains_aggregate(),
+            ParsedExpression::Binary(lhs, _, rhs) | ParsedExpression::Is { lhs, rhs, .. } => {
+                lhs.contains_aggregate() || rhs.contains_aggregate()
+            }
+            ParsedExpression::Between {
+                expression,
+                low,
+                high,
+                ..
+            } => [expression, low, high]
+                .iter()
+                .any(|operand| operand.contains_aggregate()),
+            ParsedExpression::In {
+                expression, list, ..
+            } => expression.contains_aggregate() || list.iter().any(Self::contains_aggregate),
+            ParsedExpression::Function { arguments, .. } => {
+                arguments.iter().any(Self::contains_aggregate)
+            }
+            ParsedExpression::Like {
+                expression,
+                pattern,
+                escape,
+                ..
+            } => {
+                expression.contains_aggregate()
+                    || pattern.contains_aggregate()
+                    || escape
+                        .as_ref()
+                        .is_some_and(|escape| escape.contains_aggregate())
+            }
+            ParsedExpression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                operand
+                    .as_ref()
+                    .is_some_and(|operand| operand.contains_aggregate())
+                    || branches
+                        .iter()
+                        .any(|(when, then)| when.contains_aggregate() || then.contains_aggregate())
+                    || otherwise
+                        .as_ref()
+                        .is_some_and(|otherwise| otherwise.contains_aggregate())
+            }
+        }
+    }
+
+    /// Binding strength used to decide where parentheses are needed when printing.
+    fn precedence(&self) -> u8 {
+        match self {
+            ParsedExpression::Binary(_, op, _) => op.precedence(),
+            ParsedExpression::Unary(UnaryOp::Not, _) => 2,
+            ParsedExpression::Is { .. }
+            | ParsedExpression::Between { .. }
+            | ParsedExpression::In { .. }
+            | ParsedExpression::Like { .. } => 3,
+            ParsedExpression::Unary(..) | ParsedExpression::Collate(..) => 9,
+            _ => 10,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut std::fmt::Formatter<'_>, precedence: u8) -> std::fmt::Result {
+        if self.precedence() < precedence {
+            f.write_fmt(format_args!("({self})"))
+        } else {
+            f.write_fmt(format_args!("{self}"))
+        }
+    }
+}
+
+impl Display for ParsedExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let precedence = self.precedence();
+        let not = |negated: bool| if negated { "NOT " } else { "" };
+        match self {
+            ParsedExpression::Literal(value) => f.write_str(&value.sql_literal()),
+            ParsedExpression::Column(column) => column.fmt(f),
+            ParsedExpression::QuotedName(name) => {
+                f.write_fmt(format_args!("\"{}\"", name.replace('"', "\"\"")))
+            }
+            ParsedExpression::Unary(op, operand) => {
+                f.write_str(match op {
+                    UnaryOp::Negate => "-",
+                    UnaryOp::Plus => "+",
+                    UnaryOp::Not => "NOT ",
+                    UnaryOp::BitNot => "~",
+                })?;
+                // Nested signs are parenthesized so that `- -x` can't print as a comment
+                operand.fmt_operand(f, precedence + 1)
+            }
+            ParsedExpression::Binary(lhs, op, rhs) => {
+                lhs.fmt_operand(f, precedence)?;
+                f.write_fmt(format_args!(" {} ", op.as_str()))?;
+                rhs.fmt_operand(f, precedence + 1)
+            }
+            ParsedExpression::Is { lhs, rhs, negated } => {
+                lhs.fmt_operand(f, precedence)?;
+                f.write_fmt(format_args!(" IS {}", not(*negated)))?;
+                rhs.fmt_operand(f, precedence + 1)
+            }
+            ParsedExpression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                expression.fmt_operand(f, precedence + 1)?;
+                f.write_fmt(format_args!(" {}BETWEEN ", not(*negated)))?;
+                low.fmt_operand(f, precedence + 1)?;
+                f.write_str(" AND ")?;
+                high.fmt_operand(f, precedence + 1)
+            }
+            ParsedExpression::In {
+                expression,
+                list,
+                negated,
+            } => {
+                expression.fmt_operand(f, precedence + 1)?;
+                let list = list
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                f.write_fmt(format_args!(" {}IN ({list})", not(*negated)))
+            }
+            ParsedExpression::Like {
+                expression,
+                pattern,
+                escape,
+                negated,
+                glob,
+            } => {
+                expression.fmt_operand(f, precedence + 1)?;
+                let keyword = if *glob { "GLOB" } else { "LIKE" };
+                f.write_fmt(format_args!(" {}{keyword} ", not(*negated)))?;
+                pattern.fmt_operand(f, precedence + 1)?;
+                if let Some(escape) = escape {
+                    f.write_str(" ESCAPE ")?;
+                    escape.fmt_operand(f, precedence + 1)?;
+                }
+                Ok(())
+            }
+            ParsedExpression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                f.write_str("CASE")?;
+                if let Some(operand) = operand {
+                    f.write_fmt(format_args!(" {operand}"))?;
+                }
+                for (when, then) in branches {
+                    f.write_fmt(format_args!(" WHEN {when} THEN {then}"))?;
+                }
+                if let Some(otherwise) = otherwise {
+                    f.write_fmt(format_args!(" ELSE {otherwise}"))?;
+                }
+                f.write_str(" END")
+            }
+            ParsedExpression::Cast(operand, type_name) => {
+                f.write_fmt(format_args!("CAST({operand} AS {type_name})"))
+            }
+            ParsedExpression::Collate(operand, collation) => {
+                operand.fmt_operand(f, precedence)?;
+                f.write_fmt(format_args!(" COLLATE {collation}"))
+            }
+            ParsedExpression::Aggregate(aggregate) => aggregate.fmt(f),
+            ParsedExpression::Function { name, arguments } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                f.write_fmt(format_args!("{name}({arguments})"))
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    /// `expr := or_expr`, the operators being parsed by increasing binding strength.
+    pub fn parse_expression(&mut self) -> Result<ParsedExpression, ParsingError> {
+        self.parse_or()
+    }
+
+    /// `or_expr := and_expr [OR and_expr ...]`
+    fn parse_or(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_and()?;
+        while self.consume_keyword(OR_KEYWORD) {
+            lhs = binary(lhs, BinaryOp::Or, self.parse_and()?);
+        }
+        Ok(lhs)
+    }
+
+    /// `and_expr := not_expr [AND not_expr ...]`
+    fn parse_and(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_not()?;
+        while self.consume_keyword(AND_KEYWORD) {
+            lhs = binary(lhs, BinaryOp::And, self.parse_not()?);
+        }
+        Ok(lhs)
+    }
+
+    /// `not_expr := NOT not_expr | equality`
+    fn parse_not(&mut self) -> Result<ParsedExpression, ParsingError> {
+        if self.consume_keyword(NOT_KEYWORD) {
+            return Ok(ParsedExpression::Unary(
+                UnaryOp::Not,
+                Box::new(self.parse_not()?),
+            ));
+        }
+        self.parse_equality()
+    }
+
+    /// `equality := comparison [(= | != | IS [NOT] | [NOT] IN | [NOT] LIKE | [NOT] GLOB |
+    /// [NOT] BETWEEN | ISNULL | NOTNULL | NOT NULL) ...]`
+    fn parse_equality(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_comparison()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Eq => Some(Op::Eq),
+                TokenKind::NotEq => Some(Op::NotEq),
+                _ => None,
+            };
+            if let Some(op) = op {
+                self.advance();
+                lhs = binary(lhs, BinaryOp::Comparison(op), self.parse_comparison()?);
+                continue;
+            }
+            if self.consume_keyword("IS") {
+                let negated = self.consume_keyword(NOT_KEYWORD);
+                lhs = ParsedExpression::Is {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(self.parse_comparison()?),
+                    negated,
+                };
+                continue;
+            }
+            let is_null = self.consume_keyword("ISNULL");
+            if is_null
+                || self.consume_keyword("NOTNULL")
+                || self.consume_keywords(&[NOT_KEYWORD, NULL_KEYWORD])
+            {
+                lhs = ParsedExpression::Is {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(ParsedExpression::Literal(Value::Null)),
+                    negated: !is_null,
+                };
+                continue;
+            }
+
+            let negated = ["IN", "LIKE", "GLOB", "BETWEEN"]
+                .iter()
+                .any(|keyword| self.check_keyword_at(1, keyword))
+                && self.consume_keyword(NOT_KEYWORD);
+            if self.consume_keyword("IN") {
+                lhs = ParsedExpression::In {
+                    expression: Box::new(lhs),
+                    list: self.parse_in_list()?,
+                    negated,
+                };
+            } else if self.check_keyword("LIKE") || self.check_keyword("GLOB") {
+                let glob = self.check_keyword("GLOB");
+                self.advance();
+                let pattern = self.parse_comparison()?;
+                let escape = if self.consume_keyword("ESCAPE") {
+                    Some(Box::new(self.parse_comparison()?))
+                } else {
+                    None
+                };
+                lhs = ParsedExpression::Like {
+                    expression: Box::new(lhs),
+                    pattern: Box::new(pattern),
+                    escape,
+                    negated,
+                    glob,
+                };
+            } else if self.consume_keyword("BETWEEN") {
+                let low = self.parse_comparison()?;
+                self.expect_keyword(AND_KEYWORD)?;
+                let high = self.parse_comparison()?;
+                lhs = ParsedExpression::Between {
+                    expression: Box::new(lhs),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                };
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    /// `( [expr [, expr ...]] )` after IN.
+    fn parse_in_list(&mut self) -> Result<Vec<ParsedExpression>, ParsingError> {
+        self.expect(TokenKind::LeftParen)?;
+        if self.check_keyword("SELECT") {
+            return Err(self.error("subqueries are not supported"));
+        }
+        let mut list = vec![];
+        if !self.consume(&TokenKind::RightParen) {
+            list.push(self.parse_expression()?);
+            while self.consume(&TokenKind::Comma) {
+                list.push(self.parse_expression()?);
+            }
+            self.expect(TokenKind::RightParen)?;
+        }
+        Ok(list)
+    }
+
+    /// `comparison := bitwise [(< | <= | > | >=) bitwise ...]`
+    fn parse_comparison(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_bitwise()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Lt => Op::Lt,
+                TokenKind::LtEq => Op::LtEq,
+                TokenKind::Gt => Op::Gt,
+                TokenKind::GtEq => Op::GtEq,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = binary(lhs, BinaryOp::Comparison(op), self.parse_bitwise()?);
+        }
+    }
+
+    /// `bitwise := additive [(& | "|" | << | >>) additive ...]`
+    fn parse_bitwise(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_additive()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Ampersand => BinaryOp::BitAnd,
+                TokenKind::Pipe => BinaryOp::BitOr,
+                TokenKind::ShiftLeft => BinaryOp::ShiftLeft,
+                TokenKind::ShiftRight => BinaryOp::ShiftRight,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = binary(lhs, op, self.parse_additive()?);
+        }
+    }
+
+    /// `additive := multiplicative [(+ | -) multiplicative ...]`
+    fn parse_additive(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => BinaryOp::Add,
+                TokenKind::Minus => BinaryOp::Subtract,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = binary(lhs, op, self.parse_multiplicative()?);
+        }
+    }
+
+    /// `multiplicative := concat [(* | / | %) concat ...]`
+    fn parse_multiplicative(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_concat()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => BinaryOp::Multiply,
+                TokenKind::Slash => BinaryOp::Divide,
+                TokenKind::Percent => BinaryOp::Remainder,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = binary(lhs, op, self.parse_concat()?);
+        }
+    }
+
+    /// `concat := unary [|| unary ...]`
+    fn parse_concat(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let mut lhs = self.parse_unary()?;
+        while self.consume(&TokenKind::Concat) {
+            lhs = binary(lhs, BinaryOp::Concat, self.parse_unary()?);
+        }
+        Ok(lhs)
+    }
+
+    /// `unary := (- | + | ~) unary | primary [COLLATE name ...]`. A sign in front of a number
+    /// is folded into the literal.
+    fn parse_unary(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let op = match self.peek().kind {
+            TokenKind::Minus => UnaryOp::Negate,
+            TokenKind::Plus => UnaryOp::Plus,
+            TokenKind::Tilde => UnaryOp::BitNot,
+            _ => {
+                let mut expression = self.parse_primary()?;
+                while self.consume_keyword("COLLATE") {
+                    expression =
+                        ParsedExpression::Collate(Box::new(expression), self.parse_identifier()?);
+                }
+                return Ok(expression);
+            }
+        };
+        self.advance();
+        let begin = self.mark();
+        let operand = self.parse_unary()?;
+        Ok(match (op, operand) {
+            // The digits of the smallest integer are too big for an integer on their own
+            (UnaryOp::Negate, ParsedExpression::Literal(Value::Real(_)))
+                if is_integer_min_digits(self.source_since(begin)) =>
+            {
+                ParsedExpression::Literal(Value::Integer(i64::MIN))
+            }
+            (UnaryOp::Negate, ParsedExpression::Literal(Value::Integer(value))) => {
+                match value.checked_neg() {
+                    Some(value) => ParsedExpression::Literal(Value::Integer(value)),
+                    None => ParsedExpression::Literal(Value::Real(-(value as f64))),
+                }
+            }
+            (UnaryOp::Negate, ParsedExpression::Literal(Value::Real(value))) => {
+                ParsedExpression::Literal(Value::Real(-value))
+            }
+            (op, operand) => ParsedExpression::Unary(op, Box::new(operand)),
+        })
+    }
+
+    /// A literal, a column name, a parenthesized expression, `CASE`, `CAST` or a function call.
+    fn parse_primary(&mut self) -> Result<ParsedExpression, ParsingError> {
+        match &self.peek().kind {
+            TokenKind::LeftParen => {
+                self.advance();
+                if self.check_keyword("SELECT") {
+                    return Err(self.error("subqueries are not supported"));
+                }
+                let expression = self.parse_expression()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(expression)
+            }
+            TokenKind::QuotedIdentifier(name) => {
+                let name = name.clone();
+                let begin = self.mark();
+                self.advance();
+                if self.consume(&TokenKind::Dot) {
+                    return Ok(ParsedExpression::Column(ColumnName {
+                        table: Some(name),
+                        name: self.parse_identifier()?,
+                    }));
+                }
+                // Only names in double quotes are strings when they aren't columns, `[name]` and
+                // `` `name` `` are always names
+                if self.source_since(begin).starts_with('"') {
+                    Ok(ParsedExpression::QuotedName(name))
+                } else {
+                    Ok(ParsedExpression::Column(ColumnName { table: None, name }))
+                }
+            }
+            TokenKind::Identifier(word) => {
+                let word = word.to_uppercase();
+                match word.as_str() {
+                    NULL_KEYWORD => self.parse_literal().map(ParsedExpression::Literal),
+                    "TRUE" | "FALSE" => {
+                        self.advance();
+                        Ok(ParsedExpression::Literal(Value::Integer(i64::from(
+                            word == "TRUE",
+                        ))))
+                    }
+                    "CASE" => self.parse_case(),
+                    "CAST" if self.peek_nth(1).kind == TokenKind::LeftParen => self.parse_cast(),
+                    "EXISTS" | "SELECT" => Err(self.error("subqueries are not supported")),
+                    _ if is_reserved(&word) => Err(self.unexpected("an expression")),
+                    _ if self.peek_nth(1).kind == TokenKind::LeftParen => self.parse_function(),
+                    _ => self.parse_column_name().map(ParsedExpression::Column),
+                }
+            }
+            _ => self.parse_literal().map(ParsedExpression::Literal),
+        }
+    }
+
+    /// `CASE [operand] WHEN expr THEN expr [WHEN ...] [ELSE expr] END`
+    fn parse_case(&mut self) -> Result<ParsedExpression, ParsingError> {
+        self.expect_keyword("CASE")?;
+        let operand = if self.check_keyword("WHEN") {
+            None
+        } else {
+            Some(Box::new(self.parse_expression()?))
+        };
+
+        let mut branches = vec![];
+        while self.consume_keyword("WHEN") {
+            let when = self.parse_expression()?;
+            self.expect_keyword("THEN")?;
+            branches.push((when, self.parse_expression()?));
+        }
+        if branches.is_empty() {
+            return Err(self.unexpected("WHEN"));
+        }
+
+        let otherwise = if self.consume_keyword("ELSE") {
+            Some(Box::new(self.parse_expression()?))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+
+        Ok(ParsedExpression::Case {
+            operand,
+            branches,
+            otherwise,
+        })
+    }
+
+    /// `CAST ( expr AS type_name )`, where the type name is a few words with optional sizes.
+    fn parse_cast(&mut self) -> Result<ParsedExpression, ParsingError> {
+        self.expect_keyword("CAST")?;
+        self.expect(TokenKind::LeftParen)?;
+        let operand = self.parse_expression()?;
+        self.expect_keyword("AS")?;
+
+        let mark = self.mark();
+        while let TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_) = self.peek().kind {
+            self.advance();
+        }
+        if self.consume(&TokenKind::LeftParen) {
+            self.skip_parenthesized()?;
+        }
+        let type_name = self.source_since(mark).to_string();
+        self.expect(TokenKind::RightParen)?;
+
+        Ok(ParsedExpression::Cast(Box::new(operand), type_name))
+    }
+
+    /// `name ( [DISTINCT] expr, ... )` or `COUNT(*)`. Aggregate functions are recognized
+    /// here, MIN and MAX only when called with a single argument, other names are scalar
+    /// functions resolved when binding.
+    fn parse_function(&mut self) -> Result<ParsedExpression, ParsingError> {
+        let name_token = self.peek().clone();
+        let name = self.parse_identifier()?;
+        let paren = self.expect(TokenKind::LeftParen)?;
+        let aggregate = AggregateFunction::from_name(&name);
+
+        if aggregate == Some(AggregateFunction::Count) && self.consume(&TokenKind::Star) {
+            self.expect(TokenKind::RightParen)?;
+            return Ok(ParsedExpression::Aggregate(ParsedAggregate {
+                function: AggregateFunction::Count,
+                argument: None,
+                distinct: false,
+                separator: None,
+            }));
+        }
+
+        let distinct = self.consume_keyword(DISTINCT_KEYWORD);
+        let mut arguments = vec![];
+        if !self.check(&TokenKind::RightParen) {
+            arguments.push(self.parse_expression()?);
+            while self.consume(&TokenKind::Comma) {
+                arguments.push(self.parse_expression()?);
+            }
+        }
+        self.expect(TokenKind::RightParen)?;
+
+        let function = match aggregate {
+            Some(AggregateFunction::Min | AggregateFunction::Max) if arguments.len() > 1 => None,
+            aggregate => aggregate,
+        };
+        let Some(function) = function else {
+            return Ok(ParsedExpression::Function { name, arguments });
+        };
+
+        let mut arguments = arguments.into_iter();
+        let (argument, separator) = (arguments.next(), arguments.next());
+        let valid = match function {
+            AggregateFunction::Count => separator.is_none(),
+            AggregateFunction::GroupConcat => argument.is_some(),
+            _ => argument.is_some() && separator.is_none(),
+        };
+        if !valid || arguments.next().is_some() {
+            return Err(self.error_at(
+                &name_token,
+                format!("wrong number of arguments to function {name}()"),
+            ));
+        }
+        if distinct && separator.is_some() {
+            return Err(self.error_at(&paren, "DISTINCT aggregates must have exactly one argument"));
+        }
+        let separator = match separator {
+            Some(ParsedExpression::Literal(separator)) => Some(separator),
+            Some(_) => {
+                return Err(self.error_at(
+                    &paren,
+                    format!("the separator of {name}() must be a literal"),
+                ));
+            }
+            None => None,
+        };
+
+        Ok(ParsedExpression::Aggregate(ParsedAggregate {
+            function,
+            // `COUNT()` counts rows like `COUNT(*)`
+            argument: argument.map(Box::new),
+            distinct,
+            separator,
+        }))
+    }
+
+    /// `[table.]column`
+    pub fn parse_column_name(&mut self) -> Result<ColumnName, ParsingError> {
+        let name = self.parse_identifier()?;
+        if self.consume(&TokenKind::Dot) {
+            return Ok(ColumnName {
+                table: Some(name),
+                name: self.parse_identifier()?,
+            });
+        }
+        Ok(ColumnName { table: None, name })
+    }
+}
+
+/// Whether `text` is the integer literal 9223372036854775808, possibly parenthesized.
+fn is_integer_min_digits(text: &str) -> bool {
+    text.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace())
+        .trim_start_matches('0')
+        == "9223372036854775808"
+}
+
+fn binary(lhs: ParsedExpression, op: BinaryOp, rhs: ParsedExpression) -> ParsedExpression {
+    ParsedExpression::Binary(Box::new(lhs), op, Box::new(rhs))
+}
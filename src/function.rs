@@ -0,0 +1,722 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, hash_map::RandomState},
+    f64::consts::PI,
+    fmt::Debug,
+    hash::{BuildHasher, Hasher},
+    ops::{Bound, RangeBounds},
+    rc::Rc,
+};
+
+use crate::{
+    datetime::{date, datetime, julianday, strftime, time, unixepoch},
+    expression::{glob_match, like_match},
+    parsing_error::ParsingError,
+    printf::{Decimal, exponent_suffix, printf},
+    value::{Value, from_hex, parse_numeric, to_hex},
+};
+
+/// Body of a scalar function, called with the values of its arguments.
+pub type Implementation = dyn Fn(&[Value]) -> Result<Value, ParsingError>;
+
+/// A math function of a single real.
+type RealFunction = fn(f64) -> f64;
+
+/// A scalar function and the number of arguments it accepts.
+#[derive(Clone)]
+pub struct ScalarFunction {
+    name: String,
+    min_arguments: usize,
+    max_arguments: Option<usize>,
+    implementation: Rc<Implementation>,
+}
+
+impl ScalarFunction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn accepts(&self, arguments: usize) -> bool {
+        arguments >= self.min_arguments && self.max_arguments.is_none_or(|max| arguments <= max)
+    }
+
+    pub fn call(&self, arguments: &[Value]) -> Result<Value, ParsingError> {
+        (self.implementation)(arguments)
+    }
+}
+
+impl Debug for ScalarFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}()", self.name))
+    }
+}
+
+/// Scalar functions by (case-insensitive) name. The default registry has SQLite's core, math
+/// and date functions, and more can be registered before binding a query.
+#[derive(Clone, Debug)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, ScalarFunction>,
+}
+
+impl FunctionRegistry {
+    /// A registry without any function.
+    pub fn empty() -> Self {
+        FunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Registers `implementation` as the function `name` taking a number of arguments in
+    /// `arguments`, replacing any function with the same name.
+    pub fn register<F>(&mut self, name: &str, arguments: impl RangeBounds<usize>, implementation: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, ParsingError> + 'static,
+    {
+        let min_arguments = match arguments.start_bound() {
+            Bound::Included(&min) => min,
+            Bound::Excluded(&min) => min + 1,
+            Bound::Unbounded => 0,
+        };
+        let max_arguments = match arguments.end_bound() {
+            Bound::Included(&max) => Some(max),
+            Bound::Excluded(&max) => Some(max.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+        self.functions.insert(
+            name.to_lowercase(),
+            ScalarFunction {
+                name: name.to_lowercase(),
+                min_arguments,
+                max_arguments,
+                implementation: Rc::new(implementation),
+            },
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ScalarFunction> {
+        self.functions.get(&name.to_lowercase())
+    }
+
+    /// Looks up the function `name` for a call with `arguments` arguments.
+    pub fn resolve(&self, name: &str, arguments: usize) -> Result<ScalarFunction, ParsingError> {
+        match self.get(name) {
+            Some(function) if function.accepts(arguments) => Ok(function.clone()),
+            Some(_) => Err(ParsingError::Semantic(format!(
+                "wrong number of arguments to function {name}()"
+            ))),
+            None => Err(ParsingError::Semantic(format!("no such function: {name}"))),
+        }
+    }
+
+    fn register_core_functions(&mut self) {
+        self.register("abs", 1..=1, |arguments| abs(&arguments[0]));
+        self.register("changes", 0..=0, |_| Ok(Value::Integer(0)));
+        self.register("char", .., |arguments| {
+            Ok(Value::Text(
+                arguments
+                    .iter()
+                    .map(|argument| {
+                        u32::try_from(argument.to_integer())
+                            .ok()
+                            .and_then(char::from_u32)
+                            .unwrap_or(char::REPLACEMENT_CHARACTER)
+                    })
+                    .collect(),
+            ))
+        });
+        self.register("coalesce", 2.., |arguments| Ok(coalesce(arguments)));
+        self.register("concat", 1.., |arguments| {
+            Ok(Value::Text(arguments.iter().map(Value::to_text).collect()))
+        });
+        self.register("concat_ws", 2.., |arguments| {
+            if arguments[0].is_null() {
+                return Ok(Value::Null);
+            }
+            let texts = arguments[1..]
+                .iter()
+                .filter(|argument| !argument.is_null())
+                .map(Value::to_text)
+                .collect::<Vec<_>>();
+            Ok(Value::Text(texts.join(&arguments[0].to_text())))
+        });
+        self.register("format", 0.., printf);
+        self.register("glob", 2..=2, |arguments| {
+            pattern_match(&arguments[0], &arguments[1], None, true)
+        });
+        self.register("hex", 1..=1, |arguments| {
+            Ok(Value::Text(match &arguments[0] {
+                Value::Blob(bytes) => to_hex(bytes),
+                value => to_hex(value.to_text().as_bytes()),
+            }))
+        });
+        self.register("ifnull", 2..=2, |arguments| Ok(coalesce(arguments)));
+        for name in ["iif", "if"] {
+            self.register(name, 2..=3, |arguments| {
+                Ok(match arguments[0].truth() {
+                    Some(true) => arguments[1].clone(),
+                    _ => arguments.get(2).cloned().unwrap_or(Value::Null),
+                })
+            });
+        }
+        self.register("instr", 2..=2, |arguments| {
+            Ok(instr(&arguments[0], &arguments[1]))
+        });
+        self.register("last_insert_rowid", 0..=0, |_| Ok(Value::Integer(0)));
+        self.register("length", 1..=1, |arguments| {
+            Ok(match &arguments[0] {
+                Value::Null => Value::Null,
+                Value::Blob(bytes) => Value::Integer(bytes.len() as i64),
+                // Text ends at its first NUL character, like in SQLite
+                value => Value::Integer(
+                    value
+                        .to_text()
+                        .chars()
+                        .take_while(|&character| character != '\0')
+                        .count() as i64,
+                ),
+            })
+        });
+        self.register("like", 2..=3, |arguments| {
+            pattern_match(&arguments[0], &arguments[1], arguments.get(2), false)
+        });
+        for name in ["likely", "unlikely"] {
+            self.register(name, 1..=1, |arguments| Ok(arguments[0].clone()));
+        }
+        self.register("likelihood", 2..=2, |arguments| Ok(arguments[0].clone()));
+        self.register("lower", 1..=1, |arguments| {
+            Ok(map_text(&arguments[0], |text| text.to_ascii_lowercase()))
+        });
+        self.register("ltrim", 1..=2, |arguments| Ok(trim(arguments, true, false)));
+        self.register("max", 2.., |arguments| {
+            Ok(extreme(arguments, Ordering::Greater))
+        });
+        self.register("min", 2.., |arguments| {
+            Ok(extreme(arguments, Ordering::Less))
+        });
+        self.register("nullif", 2..=2, |arguments| {
+            Ok(if arguments[0] == arguments[1] {
+                Value::Null
+            } else {
+                arguments[0].clone()
+            })
+        });
+        self.register("octet_length", 1..=1, |arguments| {
+            Ok(match &arguments[0] {
+                Value::Null => Value::Null,
+                Value::Blob(bytes) => Value::Integer(bytes.len() as i64),
+                value => Value::Integer(value.to_text().len() as i64),
+            })
+        });
+        self.register("printf", 0.., printf);
+        self.register("quote", 1..=1, |arguments| {
+            Ok(Value::Text(match &arguments[0] {
+                Value::Real(value) => quote_real(*value),
+                value => value.sql_literal(),
+            }))
+        });
+        self.register("random", 0..=0, |_| Ok(Value::Integer(random() as i64)));
+        self.register("randomblob", 1..=1, |arguments| {
+            let length = arguments[0].to_integer().max(1) as usize;
+            Ok(Value::Blob(
+                (0..length.div_ceil(8))
+                    .flat_map(|_| random().to_le_bytes())
+                    .take(length)
+                    .collect(),
+            ))
+        });
+        self.register("replace", 3..=3, |arguments| Ok(replace(arguments)));
+        self.register("round", 1..=2, |arguments| Ok(round(arguments)));
+        self.register("rtrim", 1..=2, |arguments| Ok(trim(arguments, false, true)));
+        self.register("sign", 1..=1, |arguments| {
+            Ok(match numeric_argument(&arguments[0]) {
+                Some(value) => Value::Integer(match value.to_real() {
+                    value if value > 0.0 => 1,
+                    value if value < 0.0 => -1,
+                    _ => 0,
+                }),
+                None => Value::Null,
+            })
+        });
+        for name in ["substr", "substring"] {
+            self.register(name, 2..=3, |arguments| Ok(substr(arguments)));
+        }
+        self.register("total_changes", 0..=0, |_| Ok(Value::Integer(0)));
+        self.register("trim", 1..=2, |arguments| Ok(trim(arguments, true, true)));
+        self.register("typeof", 1..=1, |arguments| {
+            Ok(Value::Text(arguments[0].type_name().to_string()))
+        });
+        self.register("unhex", 1..=2, |arguments| Ok(unhex(arguments)));
+        self.register("unicode", 1..=1, |arguments| {
+            Ok(match &arguments[0] {
+                Value::Null => Value::Null,
+                value => value
+                    .to_text()
+                    .chars()
+                    .next()
+                    .map_or(Value::Null, |character| {
+                        Value::Integer(i64::from(u32::from(character)))
+                    }),
+            })
+        });
+        self.register("upper", 1..=1, |arguments| {
+            Ok(map_text(&arguments[0], |text| text.to_ascii_uppercase()))
+        });
+        self.register("zeroblob", 1..=1, |arguments| {
+            Ok(Value::Blob(vec![
+                0;
+                arguments[0].to_integer().max(0) as usize
+            ]))
+        });
+    }
+
+    fn register_math_functions(&mut self) {
+        let functions: [(&str, RealFunction); 16] = [
+            ("acos", f64::acos),
+            ("acosh", f64::acosh),
+            ("asin", f64::asin),
+            ("asinh", f64::asinh),
+            ("atan", f64::atan),
+            ("atanh", f64::atanh),
+            ("cos", f64::cos),
+            ("cosh", f64::cosh),
+            ("degrees", f64::to_degrees),
+            ("exp", f64::exp),
+            ("radians", f64::to_radians),
+            ("sin", f64::sin),
+            ("sinh", f64::sinh),
+            ("sqrt", f64::sqrt),
+            ("tan", f64::tan),
+            ("tanh", f64::tanh),
+        ];
+        for (name, function) in functions {
+            self.register(name, 1..=1, move |arguments| {
+                Ok(math(arguments, |operands| function(operands[0])))
+            });
+        }
+
+        // Logarithms are only defined for positive values (and bases other than 1)
+        let functions: [(&str, RealFunction); 3] =
+            [("ln", f64::ln), ("log10", f64::log10), ("log2", f64::log2)];
+        for (name, function) in functions {
+            self.register(name, 1..=1, move |arguments| {
+                Ok(math(arguments, |operands| logarithm(operands[0], function)))
+            });
+        }
+        self.register("log", 1..=2, |arguments| {
+            Ok(math(arguments, |operands| match operands {
+                [value] => logarithm(*value, f64::log10),
+                [base, _] if *base == 1.0 => f64::NAN,
+                [base, value] => logarithm(*value, f64::ln) / logarithm(*base, f64::ln),
+                _ => unreachable!("log takes one or two arguments"),
+            }))
+        });
+        for name in ["pow", "power"] {
+            self.register(name, 2..=2, |arguments| {
+                Ok(math(arguments, |operands| operands[0].powf(operands[1])))
+            });
+        }
+        self.register("atan2", 2..=2, |arguments| {
+            Ok(math(arguments, |operands| operands[0].atan2(operands[1])))
+        });
+        self.register("mod", 2..=2, |arguments| {
+            Ok(math(arguments, |operands| operands[0] % operands[1]))
+        });
+        self.register("pi", 0..=0, |_| Ok(Value::Real(PI)));
+
+        // Rounding functions keep integers as they are
+        let functions: [(&str, RealFunction); 4] = [
+            ("ceil", f64::ceil),
+            ("ceiling", f64::ceil),
+            ("floor", f64::floor),
+            ("trunc", f64::trunc),
+        ];
+        for (name, function) in functions {
+            self.register(name, 1..=1, move |arguments| {
+                Ok(match numeric_argument(&arguments[0]) {
+                    Some(Value::Real(value)) => Value::Real(function(value)),
+                    Some(value) => value,
+                    None => Value::Null,
+                })
+            });
+        }
+    }
+
+    fn register_date_functions(&mut self) {
+        self.register("date", .., date);
+        self.register("time", .., time);
+        self.register("datetime", .., datetime);
+        self.register("julianday", .., julianday);
+        self.register("unixepoch", .., unixepoch);
+        self.register("strftime", 1.., strftime);
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        let mut registry = FunctionRegistry::empty();
+        registry.register_core_functions();
+        registry.register_math_functions();
+        registry.register_date_functions();
+        registry
+    }
+}
+
+/// The value of a numeric argument of a math function: numbers, and text that reads as a
+/// number, `None` otherwise.
+fn numeric_argument(value: &Value) -> Option<Value> {
+    match value {
+        Value::Integer(_) | Value::Real(_) => Some(value.clone()),
+        Value::Text(text) => parse_numeric(text),
+        Value::Null | Value::Blob(_) => None,
+    }
+}
+
+/// Applies a math function to the arguments as reals. It's NULL when an argument isn't a
+/// number, or when the result isn't one (e.g. `sqrt(-1)`).
+fn math(arguments: &[Value], function: impl Fn(&[f64]) -> f64) -> Value {
+    let operands = arguments
+        .iter()
+        .map(|argument| numeric_argument(argument).map(|value| value.to_real()))
+        .collect::<Option<Vec<_>>>();
+    match operands.map(|operands| function(&operands)) {
+        Some(result) if !result.is_nan() => Value::Real(result),
+        _ => Value::Null,
+    }
+}
+
+fn logarithm(value: f64, function: RealFunction) -> f64 {
+    if value > 0.0 {
+        function(value)
+    } else {
+        f64::NAN
+    }
+}
+
+fn abs(value: &Value) -> Result<Value, ParsingError> {
+    Ok(match value {
+        Value::Null => Value::Null,
+        Value::Integer(value) => match value.checked_abs() {
+            Some(value) => Value::Integer(value),
+            None => return Err(ParsingError::IntegerOverflow),
+        },
+        value => Value::Real(value.to_real().abs()),
+    })
+}
+
+fn coalesce(arguments: &[Value]) -> Value {
+    arguments
+        .iter()
+        .find(|argument| !argument.is_null())
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+/// Multi-argument `min` and `max`: NULL when any argument is NULL, else the argument that's
+/// the furthest towards `direction`. Like in SQLite, the last of equal arguments is the
+/// minimum but the first is the maximum.
+fn extreme(arguments: &[Value], direction: std::cmp::Ordering) -> Value {
+    if arguments.iter().any(Value::is_null) {
+        return Value::Null;
+    }
+    arguments
+        .iter()
+        .reduce(|extreme, argument| match argument.compare(extreme) {
+            Ordering::Equal if direction == Ordering::Less => argument,
+            ordering if ordering == direction => argument,
+            _ => extreme,
+        })
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+fn map_text(value: &Value, map: impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        value => Value::Text(map(&value.to_text())),
+    }
+}
+
+/// `like(pattern, text [, escape])` and `glob(pattern, text)`, the arguments being in the
+/// reverse order of the operators.
+fn pattern_match(
+    pattern: &Value,
+    text: &Value,
+    escape: Option<&Value>,
+    glob: bool,
+) -> Result<Value, ParsingError> {
+    let escape = match escape {
+        Some(Value::Null) => return Ok(Value::Null),
+        Some(escape) => {
+            let escape = escape.to_text();
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(escape), None) => Some(escape),
+                _ => {
+                    return Err(ParsingError::Semantic(
+                        "ESCAPE expression must be a single character".to_string(),
+                    ));
+                }
+            }
+        }
+        None => None,
+    };
+    if pattern.is_null() || text.is_null() {
+        return Ok(Value::Null);
+    }
+    let pattern = pattern.to_text().chars().collect::<Vec<_>>();
+    let text = text.to_text().chars().collect::<Vec<_>>();
+    let found = if glob {
+        glob_match(&pattern, &text)
+    } else {
+        like_match(&pattern, &text, escape)
+    };
+    Ok(Value::Integer(i64::from(found)))
+}
+
+/// 1-based position of the first occurrence of `needle` in `haystack`, in bytes for blobs and
+/// in characters otherwise, 0 when it doesn't occur.
+fn instr(haystack: &Value, needle: &Value) -> Value {
+    match (haystack, needle) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (Value::Blob(haystack), Value::Blob(needle)) => Value::Integer(
+            (0..=haystack.len().saturating_sub(needle.len()))
+                .find(|&start| haystack[start..].starts_with(needle))
+                .map_or(0, |start| start as i64 + 1),
+        ),
+        (haystack, needle) => {
+            let haystack = haystack.to_text();
+            Value::Integer(
+                haystack
+                    .find(&needle.to_text())
+                    .map_or(0, |start| haystack[..start].chars().count() as i64 + 1),
+            )
+        }
+    }
+}
+
+fn replace(arguments: &[Value]) -> Value {
+    let [text, pattern, replacement] = arguments else {
+        unreachable!("replace takes three arguments");
+    };
+    match (text, pattern, replacement) {
+        (Value::Null, _, _) | (_, Value::Null, _) => Value::Null,
+        (text, pattern, _) if pattern.to_text().is_empty() => text.clone(),
+        (_, _, Value::Null) => Value::Null,
+        (text, pattern, replacement) => Value::Text(
+            text.to_text()
+                .replace(&pattern.to_text(), &replacement.to_text()),
+        ),
+    }
+}
+
+/// `round(value [, digits])`, rounding halfway cases away from zero on the (17 significant)
+/// decimal digits of the value, like SQLite does.
+fn round(arguments: &[Value]) -> Value {
+    let digits = match arguments.get(1) {
+        Some(Value::Null) => return Value::Null,
+        Some(digits) => digits.to_integer().clamp(0, 30),
+        None => 0,
+    };
+    let value = match &arguments[0] {
+        Value::Null => return Value::Null,
+        value => value.to_real(),
+    };
+    if digits == 0 {
+        return Value::Real(if value.abs() < 4503599627370496.0 {
+            (value + value.signum() * 0.5).trunc()
+        } else {
+            value
+        });
+    }
+
+    let decimal = Decimal::with_digits(value.abs(), 17);
+    let exponent = i64::from(decimal.exponent);
+    // Number of digits of the mantissa that are kept
+    let kept = exponent + 1 + digits;
+    if kept >= decimal.digits.len() as i64 {
+        return Value::Real(value);
+    }
+    if kept < 0 {
+        return Value::Real(0.0);
+    }
+
+    let kept = kept as usize;
+    let mut rounded = decimal.digits[..kept].iter().fold(0u64, |rounded, digit| {
+        rounded * 10 + u64::from(digit - b'0')
+    });
+    if decimal.digits[kept] >= b'5' {
+        rounded += 1;
+    }
+    let rounded = format!("{rounded}e{}", exponent + 1 - kept as i64)
+        .parse::<f64>()
+        .expect("rounded digits are a number");
+    Value::Real(rounded.copysign(value))
+}
+
+/// SQLite's `substr(text, start [, length])`: positions are 1-based, a negative start counts
+/// from the end and a negative length takes the characters before the start. Blobs are
+/// sliced by bytes.
+fn substr(arguments: &[Value]) -> Value {
+    if arguments.iter().any(Value::is_null) {
+        return Value::Null;
+    }
+
+    let length = match &arguments[0] {
+        Value::Blob(bytes) => bytes.len(),
+        value => value.to_text().chars().count(),
+    } as i64;
+    let mut start = arguments[1].to_integer();
+    let (mut count, negative_count) = match arguments.get(2) {
+        Some(count) => {
+            let count = count.to_integer();
+            (count.saturating_abs(), count < 0)
+        }
+        None => (i64::MAX, false),
+    };
+
+    if start < 0 {
+        start += length;
+        if start < 0 {
+            count = (count + start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if count > 0 {
+        count -= 1;
+    }
+    if negative_count {
+        start -= count;
+        if start < 0 {
+            count += start;
+            start = 0;
+        }
+    }
+    let start = start.min(length) as usize;
+    let count = count.clamp(0, length - start as i64) as usize;
+
+    match &arguments[0] {
+        Value::Blob(bytes) => Value::Blob(bytes[start..start + count].to_vec()),
+        value => Value::Text(value.to_text().chars().skip(start).take(count).collect()),
+    }
+}
+
+/// `trim`, `ltrim` and `rtrim`: removes the characters of the second argument (spaces by
+/// default) from the chosen ends of the text.
+fn trim(arguments: &[Value], left: bool, right: bool) -> Value {
+    let characters = match arguments.get(1) {
+        Some(Value::Null) => return Value::Null,
+        Some(characters) => characters.to_text().chars().collect::<Vec<_>>(),
+        None => vec![' '],
+    };
+    map_text(&arguments[0], |text| {
+        let mut text = text;
+        if left {
+            text = text.trim_start_matches(characters.as_slice());
+        }
+        if right {
+            text = text.trim_end_matches(characters.as_slice());
+        }
+        text.to_string()
+    })
+}
+
+/// `unhex(text [, ignored])`: the blob whose bytes are written in hexadecimal in the text,
+/// which may have characters of `ignored` between the bytes. NULL when the text isn't valid.
+fn unhex(arguments: &[Value]) -> Value {
+    let ignored = match arguments.get(1) {
+        Some(Value::Null) => return Value::Null,
+        Some(ignored) => ignored.to_text(),
+        None => String::new(),
+    };
+    let text = match &arguments[0] {
+        Value::Null => return Value::Null,
+        value => value.to_text(),
+    };
+
+    let mut digits = String::new();
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        if ignored.contains(character) {
+            continue;
+        }
+        match chars.next() {
+            Some(low) if character.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                digits.push(character);
+                digits.push(low);
+            }
+            _ => return Value::Null,
+        }
+    }
+    from_hex(&digits).map_or(Value::Null, Value::Blob)
+}
+
+/// Reals in `quote()` use up to 15 significant digits when that reads back as the same
+/// value, and more digits otherwise. Infinities are written as a literal too big to be
+/// anything else.
+fn quote_real(value: f64) -> String {
+    let sign = if value < 0.0 { "-" } else { "" };
+    if value.is_infinite() {
+        return format!("{sign}9.0e+999");
+    }
+    let short = Value::Real(value).to_text();
+    if short.parse::<f64>().ok() == Some(value) || value.is_nan() {
+        return short;
+    }
+    let decimal = Decimal::with_digits(value.abs(), 19);
+    format!(
+        "{sign}{}{}",
+        decimal.mantissa(),
+        exponent_suffix(decimal.exponent)
+    )
+}
+
+/// A random 64 bits number, from the random keys of the standard library's hash maps.
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, arguments: &[Value]) -> Result<Value, ParsingError> {
+        FunctionRegistry::default()
+            .resolve(name, arguments.len())?
+            .call(arguments)
+    }
+
+    #[test]
+    fn abs_of_the_smallest_integer_overflows() {
+        assert!(matches!(
+            call("abs", &[Value::Integer(i64::MIN)]),
+            Err(ParsingError::IntegerOverflow)
+        ));
+        assert_eq!(
+            call("abs", &[Value::Integer(-i64::MAX)]).unwrap(),
+            Value::Integer(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn quoted_reals_read_back_as_the_same_value() {
+        let quote = |value: f64| call("quote", &[Value::Real(value)]).unwrap().to_text();
+        assert_eq!(quote(0.5), "0.5");
+        assert_eq!(quote(-2.0 / 3.0), "-6.666666666666666297e-01");
+        assert_eq!(quote(f64::INFINITY), "9.0e+999");
+        assert_eq!(quote(f64::NEG_INFINITY), "-9.0e+999");
+    }
+
+    #[test]
+    fn round_goes_away_from_zero_on_the_decimal_digits() {
+        let round = |value: f64, digits: i64| {
+            call("round", &[Value::Real(value), Value::Integer(digits)]).unwrap()
+        };
+        assert_eq!(round(2.5, 0), Value::Real(3.0));
+        assert_eq!(round(-2.675, 2), Value::Real(-2.67));
+        assert_eq!(round(123456.789, 1), Value::Real(123456.8));
+        assert_eq!(round(1e-20, 5), Value::Real(0.0));
+    }
+}
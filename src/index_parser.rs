@@ -1,52 +1,120 @@
-use crate::{parsing_error::ParsingError, parsing_utils::find_keyword};
+use crate::{
+    lexer::TokenKind, parser::Parser, parsing_error::ParsingError, select_parser::SortOrder,
+    statistics::Statistics,
+};
 
-#[derive(Debug)]
+/// A key column of an index, in the order it is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedColumn {
+    pub name: String,
+    pub collation: Option<String>,
+    pub order: SortOrder,
+}
+
+impl IndexedColumn {
+    /// Whether the key is the table column of that name, ignoring case like SQLite does.
+    pub fn is_column(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct IndexData {
     pub index_name: String,
     pub table_name: String,
-    pub columns: Vec<String>,
+    pub columns: Vec<IndexedColumn>,
+    /// Whether the index only has entries for the rows satisfying its WHERE clause.
+    pub partial: bool,
     pub root_page: u64,
+    pub statistics: Statistics,
 }
 
 const INDEX_KEYWORD: &str = "INDEX";
 const CREATE_KEYWORD: &str = "CREATE";
 const ON_KEYWORD: &str = "ON";
 
-pub fn parse_index(root_page: u64, sql: &str) -> Result<IndexData, ParsingError> {
-    let sql = sql.trim();
-    let create = find_keyword(sql, CREATE_KEYWORD).ok_or(ParsingError::InvalidStatement)?;
-    let index = find_keyword(sql, INDEX_KEYWORD).ok_or(ParsingError::InvalidStatement)?;
-    let on = find_keyword(sql, ON_KEYWORD).ok_or(ParsingError::InvalidStatement)?;
+impl Parser<'_> {
+    /// `CREATE [UNIQUE] INDEX [IF NOT EXISTS] name ON table (indexed_column [, ...]) [WHERE expr]`
+    pub fn parse_create_index(&mut self) -> Result<IndexData, ParsingError> {
+        self.expect_keyword(CREATE_KEYWORD)?;
+        self.consume_keyword("UNIQUE");
+        self.expect_keyword(INDEX_KEYWORD)?;
+        self.consume_keywords(&["IF", "NOT", "EXISTS"]);
+        let index_name = self.parse_qualified_name()?;
+        self.expect_keyword(ON_KEYWORD)?;
+        let table_name = self.parse_identifier()?;
+
+        self.expect(TokenKind::LeftParen)?;
+        let mut columns = vec![self.parse_indexed_column()?];
+        while self.consume(&TokenKind::Comma) {
+            columns.push(self.parse_indexed_column()?);
+        }
+        self.expect(TokenKind::RightParen)?;
 
-    let table_name_end = sql.find("(").ok_or(ParsingError::InvalidStatement)?;
-    let columns_end = sql.rfind(")").ok_or(ParsingError::InvalidStatement)?;
+        // The condition of partial indexes is not needed to read them.
+        let partial = self.consume_keyword("WHERE");
+        if partial {
+            while !self.check(&TokenKind::Eof) && !self.check(&TokenKind::Semicolon) {
+                self.advance();
+            }
+        }
 
-    if create >= index || index >= on || on >= table_name_end || table_name_end >= columns_end {
-        return Err(ParsingError::InvalidStatement);
+        Ok(IndexData {
+            index_name,
+            table_name,
+            columns,
+            partial,
+            root_page: 0,
+            statistics: Statistics::default(),
+        })
     }
 
-    let index_name_begin = index + INDEX_KEYWORD.len();
-    let table_name_begin = on + ON_KEYWORD.len();
-    let index_name = sql[index_name_begin..on].trim();
-    let table_name = sql[table_name_begin..table_name_end].trim();
-    let columns = sql[(table_name_end + 1)..columns_end]
-        .split(",")
-        .map(|v| v.trim().to_string())
-        .collect();
-
-    return Ok(IndexData {
-        index_name: index_name.to_string(),
-        table_name: table_name.to_string(),
-        columns,
-        root_page: root_page,
-    });
+    /// A column name, or the source text of an indexed expression, with its collation and order.
+    pub(crate) fn parse_indexed_column(&mut self) -> Result<IndexedColumn, ParsingError> {
+        let begin = self.mark();
+        let is_name = matches!(
+            self.peek().kind,
+            TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_)
+        ) && matches!(
+            self.peek_nth(1).kind,
+            TokenKind::Comma | TokenKind::RightParen | TokenKind::Identifier(_)
+        );
+        let name = if is_name {
+            self.parse_identifier()?
+        } else {
+            while !matches!(
+                self.peek().kind,
+                TokenKind::Comma | TokenKind::RightParen | TokenKind::Eof
+            ) {
+                if self.advance().kind == TokenKind::LeftParen {
+                    self.skip_parenthesized()?;
+                }
+            }
+            self.source_since(begin).to_string()
+        };
+
+        let collation = if self.consume_keyword("COLLATE") {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        let order = if self.consume_keyword("DESC") {
+            SortOrder::Desc
+        } else {
+            self.consume_keyword("ASC");
+            SortOrder::Asc
+        };
+        Ok(IndexedColumn {
+            name,
+            collation,
+            order,
+        })
+    }
 }
 
-pub fn get_table_index_for_column_in(
-    index: Vec<IndexData>,
-    columns: Vec<String>,
-) -> Option<IndexData> {
-    index
-        .into_iter()
-        .find(|index| columns.contains(&index.columns[0].to_lowercase()))
+pub fn parse_index(root_page: u64, sql: &str) -> Result<IndexData, ParsingError> {
+    let mut parser = Parser::new(sql)?;
+    let index = parser.parse_create_index()?;
+    parser.expect_end()?;
+    Ok(IndexData { root_page, ..index })
 }
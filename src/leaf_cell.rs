@@ -1,4 +1,7 @@
-use crate::parsing_error::ParsingError;
+use crate::{
+    parsing_error::ParsingError,
+    value::{Affinity, Value},
+};
 use std::rc::Rc;
 
 #[derive(Clone, Copy, Debug)]
@@ -101,46 +104,25 @@ impl SerialType {
         
     }
 
-    pub fn parse_value(&self, bytes: &[u8]) -> Result<String, ParsingError> {
+    pub fn parse_value(&self, bytes: &[u8]) -> Result<Value, ParsingError> {
         match self {
-            SerialType::Null => Ok("NULL".to_string()),
-            SerialType::I8 => Ok(format!("{}", i8::from_be_bytes(bytes[0..1].try_into()?))),
-            SerialType::I16 => Ok(format!("{}", i16::from_be_bytes(bytes[0..2].try_into()?))),
-            SerialType::I24 => Ok(format!("{}", parse_i24_big_endian(&bytes[0..3])?)),
-            SerialType::I32 => Ok(format!("{}", i32::from_be_bytes(bytes[0..4].try_into()?))),
-            SerialType::I48 => Ok(format!("{}", parse_i48_big_endian(&bytes[0..6])?)),
-            SerialType::I64 => Ok(format!("{}", i64::from_be_bytes(bytes[0..8].try_into()?))),
-            SerialType::Double => Ok(format!("{}", f64::from_be_bytes(bytes[0..8].try_into()?))),
-            SerialType::False => Ok("false".to_string()),
-            SerialType::True => Ok("true".to_string()),
+            SerialType::Null => Ok(Value::Null),
+            SerialType::I8 => Ok(Value::Integer(i8::from_be_bytes(bytes[0..1].try_into()?) as i64)),
+            SerialType::I16 => Ok(Value::Integer(i16::from_be_bytes(bytes[0..2].try_into()?) as i64)),
+            SerialType::I24 => Ok(Value::Integer(parse_i24_big_endian(&bytes[0..3])? as i64)),
+            SerialType::I32 => Ok(Value::Integer(i32::from_be_bytes(bytes[0..4].try_into()?) as i64)),
+            SerialType::I48 => Ok(Value::Integer(parse_i48_big_endian(&bytes[0..6])?)),
+            SerialType::I64 => Ok(Value::Integer(i64::from_be_bytes(bytes[0..8].try_into()?))),
+            SerialType::Double => Ok(Value::Real(f64::from_be_bytes(bytes[0..8].try_into()?))),
+            SerialType::False => Ok(Value::Integer(0)),
+            SerialType::True => Ok(Value::Integer(1)),
             SerialType::Unused => unreachable!(),
-            SerialType::Blob(_) => unimplemented!(),
-            SerialType::String(size) => Ok(format!(
-                "{}",
-                String::from_utf8_lossy(&bytes[0..*size]).to_string()
+            SerialType::Blob(size) => Ok(Value::Blob(bytes[0..*size].to_vec())),
+            SerialType::String(size) => Ok(Value::Text(
+                String::from_utf8_lossy(&bytes[0..*size]).to_string(),
             )),
         }
     }
-
-    pub fn parse_value_cmp(&self, bytes: &[u8]) -> Result<String, ParsingError> {
-        match self {
-            SerialType::Null => Ok("NULL".to_string()),
-            SerialType::I8 => Ok(format!("{}", i8::from_be_bytes(bytes[0..1].try_into()?))),
-            SerialType::I16 => Ok(format!("{}", i16::from_be_bytes(bytes[0..2].try_into()?))),
-            SerialType::I24 => Ok(format!("{}", format!("{}", "unimplemented i24"))),
-            SerialType::I32 => Ok(format!("{}", i32::from_be_bytes(bytes[0..4].try_into()?))),
-            SerialType::I48 => Ok(format!("{}", format!("{}", "unimplemented i48"))),
-            SerialType::I64 => Ok(format!("{}", i64::from_be_bytes(bytes[0..8].try_into()?))),
-            SerialType::Double => Ok(format!("{}", f64::from_be_bytes(bytes[0..8].try_into()?))),
-            SerialType::False => Ok("false".to_string()),
-            SerialType::True => Ok("true".to_string()),
-            SerialType::Unused => unreachable!(),
-            SerialType::Blob(_) => unimplemented!(),
-            SerialType::String(size) => {
-                Ok(format!("\"{}\"", String::from_utf8_lossy(&bytes[0..*size])))
-            }
-        }
-    }
 }
 
 #[derive(Clone)]
@@ -150,26 +132,32 @@ pub struct LazyLeafCell {
     pub records_begin: usize,
     pub record_types: Vec<SerialType>,
     pub page_data: Rc<[u8]>,
+    /// Values of the columns missing from records written before they were added with
+    /// `ALTER TABLE ADD COLUMN`, by position. Columns past them read as NULL.
+    pub defaults: Rc<[Value]>,
 }
 
 impl LazyLeafCell {
     pub fn get_column_offset(&self, column: usize) -> usize {
-        return self.record_types[0..column]
+        self.record_types[0..column]
             .iter()
             .map(|value| value.size())
             .sum::<usize>()
-            + self.records_begin;
+            + self.records_begin
     }
 
     pub fn get_column_size(&self, column: usize) -> usize {
-        return self.record_types[column].size();
+        self.record_types[column].size()
     }
 
     pub fn get_column_type(&self, column: usize) -> SerialType {
-        return self.record_types[column];
+        self.record_types[column]
     }
 
-    pub fn get_column(&self, column: usize) -> Result<String, ParsingError> {
+    pub fn get_column(&self, column: usize) -> Result<Value, ParsingError> {
+        if column >= self.record_types.len() {
+            return Ok(self.defaults.get(column).cloned().unwrap_or(Value::Null));
+        }
         let column_offset = self.get_column_offset(column);
         let column_size = self.get_column_size(column);
         let column_type = self.get_column_type(column);
@@ -178,15 +166,16 @@ impl LazyLeafCell {
         column_type.parse_value(&self.page_data[begin_index..end_index])
     }
 
-    pub fn get_column_cmp(
+    /// Reads a column the way SQLite hands it out for a column of the given affinity: REAL
+    /// columns may store integral values as integers, which are converted back to reals.
+    pub fn get_column_with_affinity(
         &self,
         column: usize,
-    ) -> Result<String, ParsingError> {
-        let column_offset = self.get_column_offset(column);
-        let column_size = self.get_column_size(column);
-        let column_type = self.get_column_type(column);
-        let begin_index = column_offset;
-        let end_index = begin_index + column_size;
-        column_type.parse_value_cmp(&self.page_data[begin_index..end_index])
+        affinity: Affinity,
+    ) -> Result<Value, ParsingError> {
+        match (affinity, self.get_column(column)?) {
+            (Affinity::Real, Value::Integer(value)) => Ok(Value::Real(value as f64)),
+            (_, value) => Ok(value),
+        }
     }
 }
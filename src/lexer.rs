@@ -0,0 +1,467 @@
+use std::ops::Range;
+
+use crate::{parsing_error::ParsingError, value::from_hex};
+
+/// Line and column (both starting at 1) of a token in the SQL source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    /// A bare word, keywords included. Keywords are recognized by the parser.
+    Identifier(String),
+    /// An identifier written as `"name"`, `[name]` or `` `name` ``.
+    QuotedIdentifier(String),
+    String(String),
+    Blob(Vec<u8>),
+    Integer(i64),
+    Real(f64),
+    LeftParen,
+    RightParen,
+    Comma,
+    Semicolon,
+    Dot,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Concat,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Ampersand,
+    Pipe,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    Eof,
+}
+
+impl TokenKind {
+    /// Human readable form of the token used in syntax error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Identifier(name) => format!("\"{name}\""),
+            TokenKind::QuotedIdentifier(name) => format!("identifier \"{name}\""),
+            TokenKind::String(value) => format!("string '{value}'"),
+            TokenKind::Blob(_) => "blob literal".to_string(),
+            TokenKind::Integer(value) => format!("number {value}"),
+            TokenKind::Real(value) => format!("number {value}"),
+            TokenKind::Eof => "end of input".to_string(),
+            punctuation => format!("\"{}\"", punctuation.symbol()),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Dot => ".",
+            TokenKind::Star => "*",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Concat => "||",
+            TokenKind::Eq => "=",
+            TokenKind::NotEq => "!=",
+            TokenKind::Lt => "<",
+            TokenKind::LtEq => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::GtEq => ">=",
+            TokenKind::Ampersand => "&",
+            TokenKind::Pipe => "|",
+            TokenKind::Tilde => "~",
+            TokenKind::ShiftLeft => "<<",
+            TokenKind::ShiftRight => ">>",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub position: Position,
+    /// Byte range of the token in the source, used to recover the original text.
+    pub span: Range<usize>,
+}
+
+pub struct Lexer<'a> {
+    sql: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(sql: &'a str) -> Self {
+        Lexer {
+            sql,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    pub fn tokenize(mut self) -> Result<Vec<Token>, ParsingError> {
+        let mut tokens = vec![];
+        loop {
+            let token = self.next_token()?;
+            let is_eof = token.kind == TokenKind::Eof;
+            tokens.push(token);
+            if is_eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, position: Position, message: impl Into<String>) -> ParsingError {
+        ParsingError::SyntaxError {
+            message: message.into(),
+            line: position.line,
+            column: position.column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.sql[self.offset..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.sql[self.offset..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), ParsingError> {
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('-'), Some('-')) => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                // Like SQLite, a comment left open runs to the end of the input
+                (Some('/'), Some('*')) => {
+                    self.bump();
+                    self.bump();
+                    while self.peek().is_some() {
+                        if self.peek() == Some('*') && self.peek_second() == Some('/') {
+                            self.bump();
+                            self.bump();
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Reads characters up to the closing `quote`, a doubled quote standing for itself.
+    fn read_quoted(&mut self, start: Position, quote: char) -> Result<String, ParsingError> {
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => {
+                    if self.peek() == Some(quote) && quote != ']' {
+                        self.bump();
+                        value.push(quote);
+                    } else {
+                        return Ok(value);
+                    }
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error(start, format!("unterminated {quote} quote"))),
+            }
+        }
+    }
+
+    fn read_number(&mut self, start: Position) -> Result<TokenKind, ParsingError> {
+        let begin = self.offset;
+        if self.peek() == Some('0') && matches!(self.peek_second(), Some('x' | 'X')) {
+            self.bump();
+            self.bump();
+            let digits_begin = self.offset;
+            while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                self.bump();
+            }
+            return u64::from_str_radix(&self.sql[digits_begin..self.offset], 16)
+                .map(|value| TokenKind::Integer(value as i64))
+                .map_err(|_| self.error(start, "invalid hexadecimal literal"));
+        }
+
+        let mut is_real = false;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        if self.peek() == Some('.') {
+            is_real = true;
+            self.bump();
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            is_real = true;
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(self.error(start, "invalid number literal"));
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+            }
+        }
+        if self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return Err(self.error(start, "invalid number literal"));
+        }
+
+        let text = &self.sql[begin..self.offset];
+        if !is_real && let Ok(value) = text.parse::<i64>() {
+            return Ok(TokenKind::Integer(value));
+        }
+        // Integers too large for 64 bits are read as reals, like SQLite does.
+        text.parse::<f64>()
+            .map(TokenKind::Real)
+            .map_err(|_| self.error(start, "invalid number literal"))
+    }
+
+    fn next_token(&mut self) -> Result<Token, ParsingError> {
+        self.skip_whitespace_and_comments()?;
+        let start = self.position();
+        let begin = self.offset;
+
+        let Some(c) = self.peek() else {
+            return Ok(Token {
+                kind: TokenKind::Eof,
+                position: start,
+                span: begin..begin,
+            });
+        };
+
+        let kind = match c {
+            '\'' => {
+                self.bump();
+                TokenKind::String(self.read_quoted(start, '\'')?)
+            }
+            '"' | '`' => {
+                self.bump();
+                TokenKind::QuotedIdentifier(self.read_quoted(start, c)?)
+            }
+            '[' => {
+                self.bump();
+                TokenKind::QuotedIdentifier(self.read_quoted(start, ']')?)
+            }
+            'x' | 'X' if self.peek_second() == Some('\'') => {
+                self.bump();
+                self.bump();
+                let digits = self.read_quoted(start, '\'')?;
+                TokenKind::Blob(
+                    from_hex(&digits).ok_or_else(|| self.error(start, "malformed blob literal"))?,
+                )
+            }
+            c if c.is_ascii_digit() => self.read_number(start)?,
+            '.' if self.peek_second().is_some_and(|c| c.is_ascii_digit()) => {
+                self.read_number(start)?
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+                {
+                    self.bump();
+                }
+                TokenKind::Identifier(self.sql[begin..self.offset].to_string())
+            }
+            _ => {
+                self.bump();
+                let next = self.peek();
+                let mut two_chars = |kind: TokenKind| {
+                    self.bump();
+                    kind
+                };
+                match (c, next) {
+                    ('|', Some('|')) => two_chars(TokenKind::Concat),
+                    ('=', Some('=')) => two_chars(TokenKind::Eq),
+                    ('!', Some('=')) => two_chars(TokenKind::NotEq),
+                    ('<', Some('>')) => two_chars(TokenKind::NotEq),
+                    ('<', Some('=')) => two_chars(TokenKind::LtEq),
+                    ('<', Some('<')) => two_chars(TokenKind::ShiftLeft),
+                    ('>', Some('=')) => two_chars(TokenKind::GtEq),
+                    ('>', Some('>')) => two_chars(TokenKind::ShiftRight),
+                    ('(', _) => TokenKind::LeftParen,
+                    (')', _) => TokenKind::RightParen,
+                    (',', _) => TokenKind::Comma,
+                    (';', _) => TokenKind::Semicolon,
+                    ('.', _) => TokenKind::Dot,
+                    ('*', _) => TokenKind::Star,
+                    ('+', _) => TokenKind::Plus,
+                    ('-', _) => TokenKind::Minus,
+                    ('/', _) => TokenKind::Slash,
+                    ('%', _) => TokenKind::Percent,
+                    ('=', _) => TokenKind::Eq,
+                    ('<', _) => TokenKind::Lt,
+                    ('>', _) => TokenKind::Gt,
+                    ('&', _) => TokenKind::Ampersand,
+                    ('|', _) => TokenKind::Pipe,
+                    ('~', _) => TokenKind::Tilde,
+                    (c, _) => return Err(self.error(start, format!("unrecognized token \"{c}\""))),
+                }
+            }
+        };
+
+        Ok(Token {
+            kind,
+            position: start,
+            span: begin..self.offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<TokenKind> {
+        Lexer::new(sql)
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn identifier(name: &str) -> TokenKind {
+        TokenKind::Identifier(name.to_string())
+    }
+
+    fn quoted(name: &str) -> TokenKind {
+        TokenKind::QuotedIdentifier(name.to_string())
+    }
+
+    /// Line and column of the syntax error reading `sql` fails with.
+    fn error_position(sql: &str) -> (usize, usize) {
+        match Lexer::new(sql).tokenize() {
+            Err(ParsingError::SyntaxError { line, column, .. }) => (line, column),
+            other => panic!("expected a syntax error for {sql}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(
+            kinds("'it''s' \"a\"\"b\" [c d] `e`"),
+            [
+                TokenKind::String("it's".to_string()),
+                quoted("a\"b"),
+                quoted("c d"),
+                quoted("e"),
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(
+            kinds("x'4142'"),
+            [TokenKind::Blob(b"AB".to_vec()), TokenKind::Eof]
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            kinds("0x10 1e3 .5 9223372036854775808 7"),
+            [
+                TokenKind::Integer(16),
+                TokenKind::Real(1000.0),
+                TokenKind::Real(0.5),
+                TokenKind::Real(9_223_372_036_854_775_808.0),
+                TokenKind::Integer(7),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            kinds("<> == != << || <="),
+            [
+                TokenKind::NotEq,
+                TokenKind::Eq,
+                TokenKind::NotEq,
+                TokenKind::ShiftLeft,
+                TokenKind::Concat,
+                TokenKind::LtEq,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            kinds("a /* b */ c -- d\ne /* never closed"),
+            [
+                identifier("a"),
+                identifier("c"),
+                identifier("e"),
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let tokens = Lexer::new("select a,\n  /* x */ bé, c").tokenize().unwrap();
+        let positions = tokens
+            .iter()
+            .map(|token| (token.position.line, token.position.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [(1, 1), (1, 8), (1, 9), (2, 11), (2, 13), (2, 15), (2, 16)]
+        );
+        assert_eq!(tokens[3].span, 20..23);
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error_position("select 12abc"), (1, 8));
+        assert_eq!(error_position("select x'4g'"), (1, 8));
+        assert_eq!(error_position("select\n  'open"), (2, 3));
+        assert_eq!(error_position("select [a]]"), (1, 11));
+    }
+}
@@ -1,17 +1,33 @@
+pub mod aggregate;
+pub mod ast;
+pub mod binder;
 pub mod cell;
+pub mod cursor;
+pub mod datetime;
+pub mod expression;
+pub mod expression_parser;
+pub mod function;
 pub mod index_parser;
 pub mod interior_cell;
 pub mod leaf_cell;
+pub mod lexer;
 pub mod page;
 pub mod page_header;
+pub mod parser;
 pub mod parsing_error;
+pub mod planner;
+pub mod printf;
 pub mod reader;
+pub mod schema;
 pub mod select_builder;
 pub mod select_parser;
 pub mod sqlite_header;
+pub mod statistics;
 pub mod table_parser;
+pub mod trigger_parser;
+pub mod value;
 pub mod varint;
-pub mod parsing_utils;
+pub mod view_parser;
 pub mod prelude {
     pub use crate::reader::*;
 }
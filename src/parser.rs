@@ -0,0 +1,317 @@
+use crate::{
+    ast::Statement,
+    lexer::{Lexer, Token, TokenKind},
+    parsing_error::ParsingError,
+    value::Value,
+};
+
+/// Keywords SQLite only reads as names when they're quoted.
+const RESERVED_KEYWORDS: [&str; 58] = [
+    "ADD",
+    "ALL",
+    "ALTER",
+    "AND",
+    "AS",
+    "AUTOINCREMENT",
+    "BETWEEN",
+    "CASE",
+    "CHECK",
+    "COLLATE",
+    "COMMIT",
+    "CONSTRAINT",
+    "CREATE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DELETE",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "ESCAPE",
+    "EXCEPT",
+    "EXISTS",
+    "FOREIGN",
+    "FROM",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INDEX",
+    "INSERT",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "LIMIT",
+    "NOT",
+    "NOTHING",
+    "NOTNULL",
+    "NULL",
+    "ON",
+    "OR",
+    "ORDER",
+    "PRIMARY",
+    "REFERENCES",
+    "RETURNING",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "THEN",
+    "TO",
+    "TRANSACTION",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+];
+
+/// Whether `word` is a keyword that can't be an unquoted name.
+pub fn is_reserved(word: &str) -> bool {
+    RESERVED_KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+/// Recursive-descent parser over the tokens of a single SQL statement. The grammar of each
+/// statement lives next to the type it produces (`select_parser`, `table_parser`,
+/// `index_parser`), this type only provides the shared building blocks.
+pub struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(sql: &'a str) -> Result<Self, ParsingError> {
+        Ok(Parser {
+            sql,
+            tokens: Lexer::new(sql).tokenize()?,
+            current: 0,
+        })
+    }
+
+    pub fn peek(&self) -> &Token {
+        self.peek_nth(0)
+    }
+
+    /// Looks `n` tokens ahead, the end of input token is returned past the end.
+    pub fn peek_nth(&self, n: usize) -> &Token {
+        let index = (self.current + n).min(self.tokens.len() - 1);
+        &self.tokens[index]
+    }
+
+    pub fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.current += 1;
+        }
+        token
+    }
+
+    /// Index of the next token, to be used with `source_since`.
+    pub fn mark(&self) -> usize {
+        self.current
+    }
+
+    /// Original SQL text of the tokens consumed since `mark`.
+    pub fn source_since(&self, mark: usize) -> &'a str {
+        if mark >= self.current {
+            return "";
+        }
+        let begin = self.tokens[mark].span.start;
+        let end = self.tokens[self.current - 1].span.end;
+        &self.sql[begin..end]
+    }
+
+    pub fn error_at(&self, token: &Token, message: impl Into<String>) -> ParsingError {
+        ParsingError::SyntaxError {
+            message: message.into(),
+            line: token.position.line,
+            column: token.position.column,
+        }
+    }
+
+    /// Error located at the next token.
+    pub fn error(&self, message: impl Into<String>) -> ParsingError {
+        self.error_at(self.peek(), message)
+    }
+
+    /// Error for an unexpected next token, `expected` describes what the grammar wanted.
+    pub fn unexpected(&self, expected: &str) -> ParsingError {
+        self.error(format!(
+            "expected {expected} but found {}",
+            self.peek().kind.describe()
+        ))
+    }
+
+    pub fn check(&self, kind: &TokenKind) -> bool {
+        &self.peek().kind == kind
+    }
+
+    pub fn consume(&mut self, kind: &TokenKind) -> bool {
+        if self.check(kind) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    pub fn expect(&mut self, kind: TokenKind) -> Result<Token, ParsingError> {
+        if self.check(&kind) {
+            return Ok(self.advance());
+        }
+        Err(self.unexpected(&kind.describe()))
+    }
+
+    /// Whether the next token is the (case-insensitive) keyword.
+    pub fn check_keyword(&self, keyword: &str) -> bool {
+        self.check_keyword_at(0, keyword)
+    }
+
+    pub fn check_keyword_at(&self, n: usize, keyword: &str) -> bool {
+        matches!(&self.peek_nth(n).kind, TokenKind::Identifier(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    pub fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.check_keyword(keyword) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParsingError> {
+        if self.consume_keyword(keyword) {
+            return Ok(());
+        }
+        Err(self.unexpected(keyword))
+    }
+
+    /// Consumes a sequence of keywords such as `IF NOT EXISTS`, or nothing at all.
+    pub fn consume_keywords(&mut self, keywords: &[&str]) -> bool {
+        let matches = keywords
+            .iter()
+            .enumerate()
+            .all(|(n, keyword)| self.check_keyword_at(n, keyword));
+        if matches {
+            self.current += keywords.len();
+        }
+        matches
+    }
+
+    /// Parses a plain or quoted identifier and returns its unquoted name.
+    pub fn parse_identifier(&mut self) -> Result<String, ParsingError> {
+        match &self.peek().kind {
+            TokenKind::Identifier(name) | TokenKind::QuotedIdentifier(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            // SQLite accepts string literals where a name is expected
+            TokenKind::String(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    /// Parses `[schema.]name` and returns the name, the schema is dropped since only the
+    /// main database is supported.
+    pub fn parse_qualified_name(&mut self) -> Result<String, ParsingError> {
+        let name = self.parse_identifier()?;
+        if self.consume(&TokenKind::Dot) {
+            return self.parse_identifier();
+        }
+        Ok(name)
+    }
+
+    /// Parses a literal value, with an optional sign in front of numbers.
+    pub fn parse_literal(&mut self) -> Result<Value, ParsingError> {
+        let negate = if self.consume(&TokenKind::Minus) {
+            true
+        } else {
+            self.consume(&TokenKind::Plus);
+            false
+        };
+
+        let value = match &self.peek().kind {
+            TokenKind::Integer(value) if negate => Value::Integer(value.wrapping_neg()),
+            TokenKind::Integer(value) => Value::Integer(*value),
+            TokenKind::Real(value) if negate => Value::Real(-value),
+            TokenKind::Real(value) => Value::Real(*value),
+            TokenKind::String(value) if !negate => Value::Text(value.clone()),
+            TokenKind::Blob(value) if !negate => Value::Blob(value.clone()),
+            TokenKind::Identifier(word) if !negate && word.eq_ignore_ascii_case("NULL") => {
+                Value::Null
+            }
+            _ => return Err(self.unexpected("a literal value")),
+        };
+        self.advance();
+        Ok(value)
+    }
+
+    /// Skips tokens up to the matching closing parenthesis of an already consumed `(`.
+    pub fn skip_parenthesized(&mut self) -> Result<(), ParsingError> {
+        let mut depth = 1;
+        loop {
+            match self.advance().kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                TokenKind::Eof => return Err(self.unexpected("\")\"")),
+                _ => {}
+            }
+        }
+    }
+
+    /// Accepts an optional trailing `;` and requires the end of the input.
+    pub fn expect_end(&mut self) -> Result<(), ParsingError> {
+        self.consume(&TokenKind::Semicolon);
+        if !self.check(&TokenKind::Eof) {
+            return Err(self.unexpected("end of statement"));
+        }
+        Ok(())
+    }
+
+    pub fn parse_statement(&mut self) -> Result<Statement, ParsingError> {
+        let statement = if self.check_keyword("SELECT") {
+            Statement::Select(self.parse_select()?)
+        } else if self.check_keyword("CREATE") {
+            let mut n = 1;
+            while ["TEMP", "TEMPORARY", "UNIQUE"]
+                .iter()
+                .any(|keyword| self.check_keyword_at(n, keyword))
+            {
+                n += 1;
+            }
+            if self.check_keyword_at(n, "TABLE") {
+                Statement::CreateTable(self.parse_create_table()?)
+            } else if self.check_keyword_at(n, "INDEX") {
+                Statement::CreateIndex(self.parse_create_index()?)
+            } else if self.check_keyword_at(n, "VIEW") {
+                Statement::CreateView(self.parse_create_view()?)
+            } else if self.check_keyword_at(n, "TRIGGER") {
+                Statement::CreateTrigger(self.parse_create_trigger()?)
+            } else {
+                return Err(self.error_at(self.peek_nth(n), "unsupported CREATE statement"));
+            }
+        } else {
+            return Err(self.unexpected("SELECT or CREATE"));
+        };
+        self.expect_end()?;
+        Ok(statement)
+    }
+}
+
+/// Parses any supported SQL statement.
+pub fn parse_statement(sql: &str) -> Result<Statement, ParsingError> {
+    Parser::new(sql)?.parse_statement()
+}
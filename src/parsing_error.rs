@@ -8,6 +8,15 @@ pub enum ParsingError {
     InvalidPageType,
     InvalidVarint,
     InvalidStatement,
+    InvalidOverflowChain,
+    IntegerOverflow,
+    SyntaxError {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    /// A statement that parses but can't be run, such as one naming a missing table.
+    Semantic(String),
 }
 
 impl std::error::Error for ParsingError {
@@ -18,7 +27,11 @@ impl std::error::Error for ParsingError {
             ParsingError::InvalidHeaderString => None,
             ParsingError::InvalidPageType => None,
             ParsingError::InvalidVarint => None,
-            ParsingError::InvalidStatement => None
+            ParsingError::InvalidStatement => None,
+            ParsingError::InvalidOverflowChain => None,
+            ParsingError::IntegerOverflow => None,
+            ParsingError::SyntaxError { .. } => None,
+            ParsingError::Semantic(_) => None,
         }
     }
 
@@ -36,7 +49,11 @@ impl Display for ParsingError {
             ParsingError::InvalidHeaderString => f.write_str("Invalid header string for sqlite file"),
             ParsingError::InvalidPageType => f.write_str("Invalid page type"),
             ParsingError::InvalidVarint => f.write_str("Error while parsing a varint"),
-            ParsingError::InvalidStatement => f.write_str("Invalid statement while parsing SQL")
+            ParsingError::InvalidStatement => f.write_str("Invalid statement while parsing SQL"),
+            ParsingError::InvalidOverflowChain => f.write_str("Overflow page chain ended before the end of the payload"),
+            ParsingError::IntegerOverflow => f.write_str("integer overflow"),
+            ParsingError::SyntaxError { message, line, column } => f.write_fmt(format_args!("Syntax error at line {line}, column {column}: {message}")),
+            ParsingError::Semantic(message) => f.write_str(message),
             
         }
     }
@@ -44,12 +61,12 @@ impl Display for ParsingError {
 
 impl From<io::Error> for ParsingError {
     fn from(value: io::Error) -> Self {
-        return ParsingError::IoError(value)
+        ParsingError::IoError(value)
     }
 }
 
 impl From<TryFromSliceError> for ParsingError {
     fn from(value: TryFromSliceError) -> Self {
-        return ParsingError::SliceConversionError(value)
+        ParsingError::SliceConversionError(value)
     }
 }
@@ -0,0 +1,571 @@
+use crate::{
+    expression::{Comparator, Expression},
+    expression_parser::{BinaryOp, ParsedExpression},
+    index_parser::{IndexData, IndexedColumn},
+    select_builder::{KeyRange, Op, RowidRange, SourceTable, WhereColumn},
+    select_parser::{OrderingTerm, SortOrder},
+    table_parser::{Table, TableColumn},
+    value::{Affinity, Collation, Value},
+};
+
+/// How the rows of the first table are read.
+pub(crate) enum AccessPath {
+    /// Through the table b-tree, like the other paths with the direction it's read in being set
+    /// when it yields the rows in the order of the query.
+    FullScan(Option<SortOrder>),
+    Rowids(RowidRange, Option<SortOrder>),
+    /// Through the index at this position.
+    Index(usize, KeyRange, Option<SortOrder>),
+}
+
+/// Compares the ways of reading the rows of `source` that satisfy `comp` in `scan_order`: the
+/// full scan, the rowids they can have and each index, and picks the one reading the fewest
+/// rows, sorting them included. The sizes of the b-trees come from sqlite_stat1 after an
+/// ANALYZE, or else are estimated from their first pages. Partial indexes lack rows, so they
+/// aren't used.
+pub(crate) fn plan_access_path(
+    comp: Option<&Expression>,
+    source: &SourceTable,
+    scan_order: &[OrderingTerm],
+) -> AccessPath {
+    let table = &source.table;
+    let terms = comp.map(search_terms).unwrap_or_default();
+    let table_rows = source.statistics.rows.max(1.0);
+    let sort_cost = |rows: f64| {
+        if scan_order.is_empty() {
+            0.0
+        } else {
+            rows * rows.max(2.0).log2()
+        }
+    };
+
+    // The table b-tree yields the rows by rowid
+    let rowid_order = rowid_order_for(table, scan_order);
+    let table_sort_cost = |rows: f64| {
+        if rowid_order.is_some() {
+            0.0
+        } else {
+            sort_cost(rows)
+        }
+    };
+
+    let mut best = (
+        AccessPath::FullScan(rowid_order),
+        table_rows + table_sort_cost(table_rows),
+    );
+    let mut consider = |path: AccessPath, cost: f64| {
+        if cost < best.1 {
+            best = (path, cost);
+        }
+    };
+
+    if !table.without_rowid
+        && let Some(rowids) = rowid_range(&terms)
+    {
+        let rows = if rowids.start == rowids.end {
+            1.0
+        } else {
+            let bounds = i32::from(rowids.start != i64::MIN.into())
+                + i32::from(rowids.end != i64::MAX.into());
+            (table_rows / 4f64.powi(bounds)).max(1.0)
+        };
+        let cost = f64::from(source.statistics.depth) + rows + table_sort_cost(rows);
+        consider(AccessPath::Rowids(rowids, rowid_order), cost);
+    }
+
+    for (position, index) in source.indexes.iter().enumerate() {
+        if index.partial {
+            continue;
+        }
+        let (range, index_order) = match index_range(&terms, table, index) {
+            Some(range) => {
+                let index_order = index_order_for(index, range.prefix.len(), table, scan_order);
+                (range, index_order)
+            }
+            None => match index_order_for(index, 0, table, scan_order) {
+                Some(index_order) => (KeyRange::default(), Some(index_order)),
+                None => continue,
+            },
+        };
+        let bounds = usize::from(range.lower.is_some()) + usize::from(range.upper.is_some());
+        let rows = index_entries(index, range.prefix.len(), bounds, table_rows);
+        // Rows are looked up by rowid, except in the index of a WITHOUT ROWID table
+        let lookup_cost = if index.root_page == source.root_page {
+            0.0
+        } else {
+            f64::from(source.statistics.depth)
+        };
+        let mut cost = f64::from(index.statistics.depth) + rows * (1.0 + lookup_cost);
+        if index_order.is_none() {
+            cost += sort_cost(rows);
+        }
+        consider(AccessPath::Index(position, range, index_order), cost);
+    }
+    best.0
+}
+
+/// Estimated number of entries of `index` with given values for its first `prefix` key columns
+/// and `bounds` bounds on the next one: the average number sharing a key prefix measured by
+/// ANALYZE, or else a tenth of the rows for each key column, and a quarter of them for each
+/// bound.
+fn index_entries(index: &IndexData, prefix: usize, bounds: usize, table_rows: f64) -> f64 {
+    let entries = match prefix.checked_sub(1) {
+        Some(last) => index
+            .statistics
+            .equal_rows
+            .get(last)
+            .copied()
+            .unwrap_or_else(|| table_rows / 10f64.powi(prefix as i32)),
+        None => table_rows,
+    };
+    (entries / 4f64.powi(bounds as i32)).max(1.0)
+}
+
+/// A comparison of a column to a literal that a b-tree can be searched with.
+struct SearchTerm<'a> {
+    column: &'a WhereColumn,
+    op: Op,
+    value: &'a Value,
+}
+
+/// The comparisons of a column to a literal with the BINARY collation ANDed at the top of
+/// `comp`, which every row it holds satisfies.
+fn search_terms(comp: &Expression) -> Vec<SearchTerm<'_>> {
+    comp.conjuncts()
+        .into_iter()
+        .filter_map(|conjunct| match conjunct {
+            Expression::Comparison(column, op, value, comparator)
+                if *op != Op::NotEq
+                    && *comparator == Comparator::new(None, None, Collation::Binary) =>
+            {
+                match (column.as_ref(), value.as_ref()) {
+                    (Expression::Column(column, _), Expression::Literal(value)) => {
+                        Some(SearchTerm {
+                            column,
+                            op: *op,
+                            value,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rowids of the rows satisfying the comparisons of the rowid to integers among `terms`, when
+/// there are any.
+fn rowid_range(terms: &[SearchTerm]) -> Option<RowidRange> {
+    let mut rowids = RowidRange::default();
+    let mut is_bounded = false;
+    for term in terms {
+        let (WhereColumn::RowId, Value::Integer(value)) = (term.column, term.value) else {
+            continue;
+        };
+        let value = i128::from(*value);
+        match term.op {
+            Op::Eq => {
+                rowids.start = rowids.start.max(value);
+                rowids.end = rowids.end.min(value);
+            }
+            Op::Gt => rowids.start = rowids.start.max(value + 1),
+            Op::GtEq => rowids.start = rowids.start.max(value),
+            Op::Lt => rowids.end = rowids.end.min(value - 1),
+            Op::LtEq => rowids.end = rowids.end.min(value),
+            Op::NotEq => continue,
+        }
+        is_bounded = true;
+    }
+    is_bounded.then_some(rowids)
+}
+
+/// Range of the entries of `index` that hold the rows satisfying `terms`: equalities on the
+/// first key columns, then bounds on the next one when it's ascending. The keys have to use
+/// the BINARY collation like the comparisons.
+fn index_range(terms: &[SearchTerm], table: &Table, index: &IndexData) -> Option<KeyRange> {
+    let terms = terms
+        .iter()
+        .filter_map(|term| {
+            let table_column = match term.column {
+                WhereColumn::RowId => table.columns.iter().find(|column| column.is_rowid()),
+                WhereColumn::Column(index) => table.get_record_column(*index),
+            }?;
+            Some((table_column.name(), term.op, term.value))
+        })
+        .collect::<Vec<_>>();
+
+    let mut range = KeyRange::default();
+    for key in &index.columns {
+        if key_collation(key, table) != Some(Collation::Binary) {
+            break;
+        }
+        let term = |ops: &[Op]| {
+            terms
+                .iter()
+                .find(|(name, op, _)| key.is_column(name) && ops.contains(op))
+                .map(|(_, op, value)| (*op, (*value).clone()))
+        };
+        if let Some((_, value)) = term(&[Op::Eq]) {
+            range.prefix.push(value);
+            continue;
+        }
+        if key.order == SortOrder::Asc {
+            range.lower = term(&[Op::Gt, Op::GtEq]);
+            range.upper = term(&[Op::Lt, Op::LtEq]);
+        }
+        break;
+    }
+    (!range.is_everything()).then_some(range)
+}
+
+/// How a joined table gets looked up, before its index is moved into the `JoinLookup`.
+pub(crate) enum PlannedLookup {
+    RowId(usize),
+    Index(usize, usize, Option<Affinity>),
+}
+
+/// An equality between two columns of a joined row: the position of each column and the
+/// affinity it's converted to before comparing them.
+type Equality = (usize, Option<Affinity>, usize, Option<Affinity>);
+
+/// Appends the equalities between two columns that are ANDed together at the top of the
+/// expression and compare their values with the BINARY collation.
+pub(crate) fn collect_equalities(expression: &Expression, equalities: &mut Vec<Equality>) {
+    match expression {
+        Expression::Comparison(lhs, Op::Eq, rhs, comparator)
+            if comparator.collation == Collation::Binary =>
+        {
+            if let (
+                Expression::Column(WhereColumn::Column(lhs), _),
+                Expression::Column(WhereColumn::Column(rhs), _),
+            ) = (lhs.as_ref(), rhs.as_ref())
+            {
+                equalities.push((*lhs, comparator.lhs, *rhs, comparator.rhs));
+            }
+        }
+        Expression::Binary(lhs, BinaryOp::And, rhs) => {
+            collect_equalities(lhs, equalities);
+            collect_equalities(rhs, equalities);
+        }
+        _ => {}
+    }
+}
+
+/// Last position of a row the expression reads.
+pub(crate) fn last_column(expression: &Expression) -> usize {
+    let mut last = 0;
+    expression.visit_columns(&mut |column| last = last.max(column.position()));
+    last
+}
+
+/// Finds among `equalities` one between a column of the joined table, whose rowid is at
+/// `offset` in joined rows, and a column of the tables before it that its rowid or an index
+/// can be searched for.
+pub(crate) fn join_lookup(
+    equalities: &[Equality],
+    offset: usize,
+    source: &SourceTable,
+) -> Option<PlannedLookup> {
+    let width = source.table.record_affinities().len() + 1;
+    let is_inner = |position: usize| (offset..offset + width).contains(&position);
+    equalities
+        .iter()
+        .find_map(|&(lhs, lhs_affinity, rhs, rhs_affinity)| {
+            let (inner, inner_affinity, outer, outer_affinity) = if is_inner(lhs) && rhs < offset {
+                (lhs, lhs_affinity, rhs, rhs_affinity)
+            } else if is_inner(rhs) && lhs < offset {
+                (rhs, rhs_affinity, lhs, lhs_affinity)
+            } else {
+                return None;
+            };
+            if inner == offset {
+                return Some(PlannedLookup::RowId(outer));
+            }
+
+            let column = source.table.get_record_column(inner - offset - 1)?;
+            // The index holds the values of the column as they are, which only works when the
+            // comparison doesn't convert them.
+            let keeps_values = inner_affinity.is_none() || column.affinity().is_numeric();
+            if !keeps_values {
+                return None;
+            }
+            // Among the indexes, the one with the fewest entries for a value
+            let table_rows = source.statistics.rows.max(1.0);
+            source
+                .indexes
+                .iter()
+                .enumerate()
+                .filter(|(_, index)| {
+                    !index.partial
+                        && index.columns.first().is_some_and(|key| {
+                            key.is_column(column.name())
+                                && key_collation(key, &source.table) == Some(Collation::Binary)
+                        })
+                })
+                .min_by(|(_, lhs), (_, rhs)| {
+                    let lhs = index_entries(lhs, 1, 0, table_rows);
+                    lhs.total_cmp(&index_entries(rhs, 1, 0, table_rows))
+                })
+                .map(|(position, _)| PlannedLookup::Index(position, outer, outer_affinity))
+        })
+}
+
+/// Collation of an index key, which defaults to the one of the table column.
+fn key_collation(key: &IndexedColumn, table: &Table) -> Option<Collation> {
+    match &key.collation {
+        Some(name) => Collation::from_name(name),
+        None => column_collation(table.get_column_by_name(&key.name)),
+    }
+}
+
+/// Declared collation of a table column, BINARY for expressions.
+fn column_collation(column: Option<&TableColumn>) -> Option<Collation> {
+    match column.and_then(|column| column.collation.as_ref()) {
+        Some(name) => Collation::from_name(name),
+        None => Some(Collation::Binary),
+    }
+}
+
+/// Returns the direction to read the b-tree of `table` in so that its rows, stored by rowid,
+/// come out in the order asked by `order_by`, or `None` when they have to be sorted.
+fn rowid_order_for(table: &Table, order_by: &[OrderingTerm]) -> Option<SortOrder> {
+    let term = order_by.first()?;
+    let name = match &term.expression {
+        ParsedExpression::Column(column) => &column.name,
+        ParsedExpression::QuotedName(name) => name,
+        _ => return None,
+    };
+    // Rowids are unique and never NULL, so the terms after it and the placement of NULLs
+    // don't matter
+    table
+        .get_column_by_name(name)
+        .filter(|column| column.is_rowid())
+        .map(|_| term.order)
+}
+
+/// Returns the direction to read `index` in so that its entries come out in the order asked
+/// by `order_by`, or `None` when the rows have to be sorted. The first `fixed_columns` key
+/// columns are constrained by equalities, and therefore don't affect the order.
+fn index_order_for(
+    index: &IndexData,
+    fixed_columns: usize,
+    table: &Table,
+    order_by: &[OrderingTerm],
+) -> Option<SortOrder> {
+    if order_by.is_empty() {
+        return None;
+    }
+
+    let mut direction = None;
+    let mut next_column = 0;
+    for term in order_by {
+        let name = match &term.expression {
+            ParsedExpression::Column(column) => &column.name,
+            ParsedExpression::QuotedName(name) => name,
+            _ => return None,
+        };
+        let table_column = table.get_column_by_name(name)?;
+        let term_collation = match &term.collation {
+            Some(name) => Collation::from_name(name),
+            None => column_collation(Some(table_column)),
+        };
+        // NULL is the smallest value, reading the index either way only gives the default
+        // placement of NULLs for the direction.
+        if term.nulls_first() != (term.order == SortOrder::Asc) {
+            return None;
+        }
+
+        let is_fixed = index.columns[..fixed_columns]
+            .iter()
+            .any(|key| key.is_column(table_column.name()));
+        if !table_column.is_rowid() && is_fixed {
+            continue;
+        }
+        next_column = next_column.max(fixed_columns);
+
+        let term_direction = match (table_column, index.columns.get(next_column)) {
+            (table_column, Some(index_column))
+                if !table_column.is_rowid()
+                    && index_column.is_column(table_column.name())
+                    && key_collation(index_column, table) == term_collation =>
+            {
+                match (term.order, index_column.order) {
+                    (order, SortOrder::Asc) => order,
+                    (SortOrder::Asc, SortOrder::Desc) => SortOrder::Desc,
+                    (SortOrder::Desc, SortOrder::Desc) => SortOrder::Asc,
+                }
+            }
+            // Entries with equal keys are ordered by rowid
+            (table_column, None) if table_column.is_rowid() => term.order,
+            _ => return None,
+        };
+        next_column += 1;
+
+        if direction.is_some_and(|direction| direction != term_direction) {
+            return None;
+        }
+        direction = Some(term_direction);
+    }
+
+    Some(direction.unwrap_or(SortOrder::Asc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binder::Binder, function::FunctionRegistry, index_parser::parse_index,
+        select_parser::parse_select, statistics::Statistics, table_parser::parse_table,
+    };
+
+    fn source_table(indexes: &[&str]) -> SourceTable {
+        SourceTable {
+            root_page: 2,
+            table: parse_table(
+                "CREATE TABLE t(id INTEGER PRIMARY KEY, a INT, b TEXT, c TEXT COLLATE NOCASE)",
+            )
+            .unwrap(),
+            indexes: (3..)
+                .zip(indexes)
+                .map(|(root_page, index)| parse_index(root_page, index).unwrap())
+                .collect(),
+            statistics: Statistics::default(),
+            view: None,
+        }
+    }
+
+    /// The WHERE clause of `select` bound to `source`, and its ORDER BY terms.
+    fn clauses(source: &SourceTable, select: &str) -> (Option<Expression>, Vec<OrderingTerm>) {
+        let select = parse_select(select).unwrap();
+        let functions = FunctionRegistry::default();
+        let mut binder = Binder::new(
+            std::slice::from_ref(source),
+            select.tables().collect(),
+            &select.columns,
+            &functions,
+        );
+        let comp = select
+            .where_comp
+            .as_ref()
+            .map(|comp| binder.bind(comp, false).unwrap());
+        (comp, select.order_by)
+    }
+
+    /// Range of the first index of `source` for the WHERE clause of `select`.
+    fn key_range(source: &SourceTable, select: &str) -> Option<KeyRange> {
+        let comp = clauses(source, select).0.unwrap();
+        index_range(&search_terms(&comp), &source.table, &source.indexes[0])
+    }
+
+    /// Direction to read the first index of `source` in for the ORDER BY clause of `select`.
+    fn order(source: &SourceTable, fixed_columns: usize, select: &str) -> Option<SortOrder> {
+        let order_by = parse_select(select).unwrap().order_by;
+        index_order_for(&source.indexes[0], fixed_columns, &source.table, &order_by)
+    }
+
+    #[test]
+    fn index_ranges_take_equalities_then_bounds_on_the_next_key() {
+        let source = source_table(&["CREATE INDEX t_a_b ON t(a, b)"]);
+        let range = key_range(
+            &source,
+            "SELECT id FROM t WHERE b > 'k' AND a = 1 AND b <= 'p'",
+        );
+        let range = range.unwrap();
+        assert_eq!(range.prefix, vec![Value::Integer(1)]);
+        assert_eq!(range.lower, Some((Op::Gt, Value::Text("k".to_string()))));
+        assert_eq!(range.upper, Some((Op::LtEq, Value::Text("p".to_string()))));
+
+        // The first key column has to be constrained
+        assert!(key_range(&source, "SELECT id FROM t WHERE b = 'k'").is_none());
+    }
+
+    #[test]
+    fn index_ranges_stop_at_descending_or_other_collation_keys() {
+        let source = source_table(&["CREATE INDEX t_a_b ON t(a, b DESC)"]);
+        let range = key_range(&source, "SELECT id FROM t WHERE a = 1 AND b > 'k'").unwrap();
+        assert_eq!(range.prefix, vec![Value::Integer(1)]);
+        assert!(range.lower.is_none());
+
+        let source = source_table(&["CREATE INDEX t_c_a ON t(c, a)"]);
+        assert!(key_range(&source, "SELECT id FROM t WHERE c = 'k' AND a = 1").is_none());
+    }
+
+    #[test]
+    fn index_order_follows_the_keys_in_either_direction() {
+        let source = source_table(&["CREATE INDEX t_a_b ON t(a, b)"]);
+        let cases = [
+            (0, "ORDER BY a, b", Some(SortOrder::Asc)),
+            (0, "ORDER BY a DESC, b DESC", Some(SortOrder::Desc)),
+            (0, "ORDER BY a, b DESC", None),
+            (0, "ORDER BY a NULLS LAST", None),
+            (0, "ORDER BY b", None),
+            (1, "ORDER BY b DESC", Some(SortOrder::Desc)),
+            (1, "ORDER BY a, b", Some(SortOrder::Asc)),
+            // Entries with equal keys are ordered by rowid
+            (0, "ORDER BY a, b, id", Some(SortOrder::Asc)),
+            (0, "ORDER BY a, id", None),
+        ];
+        for (fixed_columns, order_by, expected) in cases {
+            let select = format!("SELECT id FROM t {order_by}");
+            assert_eq!(
+                order(&source, fixed_columns, &select),
+                expected,
+                "{order_by}"
+            );
+        }
+    }
+
+    #[test]
+    fn index_order_needs_the_collation_of_the_key() {
+        let source = source_table(&["CREATE INDEX t_c ON t(c)"]);
+        let select = "SELECT id FROM t ORDER BY c";
+        assert_eq!(order(&source, 0, select), Some(SortOrder::Asc));
+        let select = "SELECT id FROM t ORDER BY c COLLATE BINARY";
+        assert_eq!(order(&source, 0, select), None);
+    }
+
+    /// What `plan_access_path` reads the rows of `select` through, indexes by name.
+    fn plan(source: &SourceTable, select: &str) -> String {
+        let (comp, order_by) = clauses(source, select);
+        match plan_access_path(comp.as_ref(), source, &order_by) {
+            AccessPath::FullScan(_) => "full scan".to_string(),
+            AccessPath::Rowids(..) => "rowids".to_string(),
+            AccessPath::Index(position, ..) => source.indexes[position].index_name.clone(),
+        }
+    }
+
+    #[test]
+    fn access_paths_read_the_fewest_rows() {
+        let mut source = source_table(&[
+            "CREATE INDEX t_a ON t(a)",
+            "CREATE INDEX t_b ON t(b)",
+            "CREATE INDEX t_c ON t(c)",
+        ]);
+        source.statistics.rows = 10_000.0;
+        source.statistics.depth = 2;
+        source.indexes[0].statistics.equal_rows = vec![2_000.0];
+        source.indexes[1].statistics.equal_rows = vec![2.0];
+
+        let cases = [
+            ("", "full scan"),
+            ("WHERE id = 5", "rowids"),
+            ("WHERE id > 5 AND a = 1", "rowids"),
+            ("WHERE a = 1", "t_a"),
+            ("WHERE a = 1 AND b = 'k'", "t_b"),
+            // The index holds the names in their NOCASE collation
+            ("WHERE c = 'k'", "full scan"),
+            ("ORDER BY a", "t_a"),
+            ("ORDER BY id DESC", "full scan"),
+        ];
+        for (clauses, expected) in cases {
+            let select = format!("SELECT id FROM t {clauses}");
+            assert_eq!(plan(&source, &select), expected, "{clauses}");
+        }
+
+        // Partial indexes miss rows
+        let mut source = source_table(&["CREATE INDEX t_a ON t(a) WHERE a > 0"]);
+        source.statistics.rows = 10_000.0;
+        assert_eq!(plan(&source, "SELECT id FROM t WHERE a = 1"), "full scan");
+    }
+}
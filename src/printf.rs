@@ -0,0 +1,470 @@
+use crate::{parsing_error::ParsingError, value::Value};
+
+/// Reals are printed with at most this many significant digits, like SQLite does.
+const MAX_SIGNIFICANT_DIGITS: i32 = 16;
+
+/// `printf(format, ...)`, formatting the arguments like SQLite's printf: `%d`, `%i`, `%u`,
+/// `%x`, `%X`, `%o`, `%f`, `%e`, `%E`, `%g`, `%G`, `%s`, `%z`, `%c`, `%q`, `%Q`, `%w` and `%%`,
+/// with the `-`, `+`, space, `0`, `#` and `,` flags, a width and a precision. Missing
+/// arguments read as NULL, and an unknown conversion ends the output.
+pub fn printf(arguments: &[Value]) -> Result<Value, ParsingError> {
+    let Some(format) = arguments.first() else {
+        return Ok(Value::Null);
+    };
+    if format.is_null() {
+        return Ok(Value::Null);
+    }
+    let format = format.to_text().chars().collect::<Vec<_>>();
+    let mut arguments = arguments[1..].iter();
+    let mut next_argument = || arguments.next().cloned().unwrap_or(Value::Null);
+
+    let mut output = String::new();
+    let mut position = 0;
+    while position < format.len() {
+        if format[position] != '%' {
+            output.push(format[position]);
+            position += 1;
+            continue;
+        }
+        position += 1;
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = format.get(position) {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                '0' => spec.zero_pad = true,
+                ',' => spec.thousands = true,
+                '!' => {}
+                _ => break,
+            }
+            position += 1;
+        }
+        if format.get(position) == Some(&'*') {
+            let width = next_argument().to_integer();
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs() as usize;
+            position += 1;
+        } else {
+            spec.width = read_number(&format, &mut position);
+        }
+        if format.get(position) == Some(&'.') {
+            position += 1;
+            if format.get(position) == Some(&'*') {
+                spec.precision = usize::try_from(next_argument().to_integer()).ok();
+                position += 1;
+            } else {
+                spec.precision = Some(read_number(&format, &mut position));
+            }
+        }
+        while format.get(position) == Some(&'l') {
+            position += 1;
+        }
+
+        let Some(&conversion) = format.get(position) else {
+            break;
+        };
+        position += 1;
+        let converted = match conversion {
+            '%' => "%".to_string(),
+            'd' | 'i' => spec.signed(next_argument().to_integer()),
+            'u' => spec.unsigned(next_argument().to_integer() as u64, 10, ""),
+            'x' => spec.unsigned(next_argument().to_integer() as u64, 16, "0x"),
+            'X' => spec
+                .unsigned(next_argument().to_integer() as u64, 16, "0X")
+                .to_uppercase(),
+            'o' => spec.unsigned(next_argument().to_integer() as u64, 8, "0"),
+            'f' | 'e' | 'E' | 'g' | 'G' => spec.real(next_argument().to_real(), conversion),
+            's' | 'z' => {
+                let text = next_argument().to_text();
+                match spec.precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                }
+            }
+            'c' => {
+                let character = next_argument().to_text().chars().next();
+                character
+                    .map(|character| {
+                        character
+                            .to_string()
+                            .repeat(spec.precision.unwrap_or(1).max(1))
+                    })
+                    .unwrap_or_default()
+            }
+            'q' => match next_argument() {
+                Value::Null => "(NULL)".to_string(),
+                value => value.to_text().replace('\'', "''"),
+            },
+            'Q' => next_argument().sql_literal(),
+            'w' => next_argument().to_text().replace('"', "\"\""),
+            _ => break,
+        };
+        output.push_str(&spec.pad(converted));
+    }
+    Ok(Value::Text(output))
+}
+
+fn read_number(format: &[char], position: &mut usize) -> usize {
+    let mut number = 0usize;
+    while let Some(digit) = format.get(*position).and_then(|c| c.to_digit(10)) {
+        number = number.saturating_mul(10).saturating_add(digit as usize);
+        *position += 1;
+    }
+    number
+}
+
+/// Flags, width and precision of a conversion.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero_pad: bool,
+    thousands: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn sign(&self, negative: bool) -> &'static str {
+        match (negative, self.plus, self.space) {
+            (true, _, _) => "-",
+            (false, true, _) => "+",
+            (false, false, true) => " ",
+            _ => "",
+        }
+    }
+
+    fn signed(&self, value: i64) -> String {
+        let mut digits = value.unsigned_abs().to_string();
+        if let Some(precision) = self.precision {
+            digits = format!("{digits:0>precision$}");
+        }
+        if self.thousands {
+            digits = group_thousands(&digits);
+        }
+        self.zero_padded(self.sign(value < 0), &digits)
+    }
+
+    fn unsigned(&self, value: u64, radix: u32, prefix: &str) -> String {
+        let mut digits = match radix {
+            16 => format!("{value:x}"),
+            8 => format!("{value:o}"),
+            _ => value.to_string(),
+        };
+        if let Some(precision) = self.precision {
+            digits = format!("{digits:0>precision$}");
+        }
+        let prefix = if self.alternate && value != 0 {
+            prefix
+        } else {
+            ""
+        };
+        self.zero_padded(prefix, &digits)
+    }
+
+    fn real(&self, value: f64, conversion: char) -> String {
+        let sign = self.sign(value.is_sign_negative() && value != 0.0);
+        if value.is_nan() {
+            return "NaN".to_string();
+        }
+        if value.is_infinite() {
+            return format!("{sign}Inf");
+        }
+
+        let precision = self.precision.unwrap_or(6) as i32;
+        let decimal = Decimal::new(value.abs());
+        let formatted = match conversion {
+            'f' => decimal.fixed(precision, self.alternate),
+            'e' => decimal.scientific(precision, self.alternate),
+            'E' => decimal.scientific(precision, self.alternate).to_uppercase(),
+            _ => {
+                let precision = precision.max(1);
+                let exponent = decimal.round(precision).exponent;
+                let formatted = if exponent < -4 || exponent >= precision {
+                    decimal.scientific(precision - 1, self.alternate)
+                } else {
+                    decimal.fixed(precision - 1 - exponent, self.alternate)
+                };
+                let formatted = if self.alternate {
+                    formatted
+                } else {
+                    trim_trailing_zeros(&formatted)
+                };
+                if conversion == 'G' {
+                    formatted.to_uppercase()
+                } else {
+                    formatted
+                }
+            }
+        };
+        self.zero_padded(sign, &formatted)
+    }
+
+    /// `prefix` then `digits`, with zeros between them up to the width when zero padding.
+    fn zero_padded(&self, prefix: &str, digits: &str) -> String {
+        let length = prefix.chars().count() + digits.chars().count();
+        if self.zero_pad && !self.left && length < self.width {
+            format!("{prefix}{}{digits}", "0".repeat(self.width - length))
+        } else {
+            format!("{prefix}{digits}")
+        }
+    }
+
+    fn pad(&self, converted: String) -> String {
+        let length = converted.chars().count();
+        if length >= self.width {
+            return converted;
+        }
+        let padding = " ".repeat(self.width - length);
+        if self.left {
+            converted + &padding
+        } else {
+            padding + &converted
+        }
+    }
+}
+
+/// Inserts a comma between each group of three digits.
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn trim_trailing_zeros(formatted: &str) -> String {
+    let (mantissa, exponent) = match formatted.find('e') {
+        Some(index) => formatted.split_at(index),
+        None => (formatted, ""),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{mantissa}{exponent}")
+}
+
+/// The decimal digits of a non-negative real, the first one being at `10^exponent`.
+pub(crate) struct Decimal {
+    pub(crate) digits: Vec<u8>,
+    pub(crate) exponent: i32,
+}
+
+impl Decimal {
+    fn new(value: f64) -> Self {
+        Decimal::with_digits(value, 20)
+    }
+
+    /// The `significant` first digits of a finite real, the last one correctly rounded.
+    pub(crate) fn with_digits(value: f64, significant: usize) -> Self {
+        let scientific = format!("{value:.*e}", significant.saturating_sub(1));
+        let (mantissa, exponent) = scientific
+            .split_once('e')
+            .expect("scientific format always has an exponent");
+        Decimal {
+            digits: mantissa.bytes().filter(u8::is_ascii_digit).collect(),
+            exponent: exponent.parse().expect("exponent is an integer"),
+        }
+    }
+
+    /// All the digits with a point after the first one, e.g. `1.250`.
+    pub(crate) fn mantissa(&self) -> String {
+        let mut mantissa = String::from_utf8_lossy(&self.digits).into_owned();
+        if mantissa.len() > 1 {
+            mantissa.insert(1, '.');
+        }
+        mantissa
+    }
+
+    /// Rounds halfway cases away from zero to keep `significant` digits, at most
+    /// `MAX_SIGNIFICANT_DIGITS`.
+    fn round(&self, significant: i32) -> Decimal {
+        let significant = significant.min(MAX_SIGNIFICANT_DIGITS);
+        if significant < 0 {
+            return Decimal {
+                digits: vec![],
+                exponent: self.exponent,
+            };
+        }
+        let kept = significant as usize;
+        let mut digits = self.digits[..kept].to_vec();
+        let mut exponent = self.exponent;
+        if self.digits[kept] >= b'5' {
+            let carried = digits.iter_mut().rev().all(|digit| {
+                if *digit == b'9' {
+                    *digit = b'0';
+                    true
+                } else {
+                    *digit += 1;
+                    false
+                }
+            });
+            if carried {
+                digits.insert(0, b'1');
+                exponent += 1;
+                digits.truncate(kept.max(1));
+            }
+        }
+        Decimal { digits, exponent }
+    }
+
+    /// Digit at `10^place`.
+    fn digit(&self, place: i32) -> char {
+        let index = self.exponent - place;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.digits.get(index))
+            .map_or('0', |&digit| digit as char)
+    }
+
+    /// `%f`, with `precision` decimals.
+    fn fixed(&self, precision: i32, alternate: bool) -> String {
+        let rounded = self.round(self.exponent + 1 + precision);
+        let mut formatted = (0..=rounded.exponent.max(0))
+            .rev()
+            .map(|place| rounded.digit(place))
+            .collect::<String>();
+        if precision > 0 || alternate {
+            formatted.push('.');
+        }
+        formatted.extend((1..=precision).map(|place| rounded.digit(-place)));
+        formatted
+    }
+
+    /// `%e`, with `precision` decimals in the mantissa.
+    fn scientific(&self, precision: i32, alternate: bool) -> String {
+        let rounded = if self.digits.iter().all(|&digit| digit == b'0') {
+            Decimal {
+                digits: vec![b'0'],
+                exponent: 0,
+            }
+        } else {
+            self.round(precision + 1)
+        };
+        let mut formatted = rounded.digit(rounded.exponent).to_string();
+        if precision > 0 || alternate {
+            formatted.push('.');
+        }
+        formatted.extend((1..=precision).map(|offset| rounded.digit(rounded.exponent - offset)));
+        format!("{formatted}{}", exponent_suffix(rounded.exponent))
+    }
+}
+
+/// The exponent of the scientific notation, with a sign and at least two digits: `e+05`.
+pub(crate) fn exponent_suffix(exponent: i32) -> String {
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("e{sign}{:02}", exponent.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    fn format(format: &str, arguments: &[Value]) -> Value {
+        let arguments = std::iter::once(text(format))
+            .chain(arguments.iter().cloned())
+            .collect::<Vec<_>>();
+        printf(&arguments).unwrap()
+    }
+
+    #[test]
+    fn integer_flags() {
+        let cases = [
+            ("[%5d]", "[   42]"),
+            ("[%-5d]", "[42   ]"),
+            ("%05d", "00042"),
+            ("%+d", "+42"),
+            ("% d", " 42"),
+            ("%lld", "42"),
+            ("%d%%", "42%"),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(
+                format(spec, &[Value::Integer(42)]),
+                text(expected),
+                "{spec}"
+            );
+        }
+        assert_eq!(
+            format("%,d", &[Value::Integer(1_234_567)]),
+            text("1,234,567")
+        );
+        assert_eq!(
+            format(
+                "%x %X %#x %o %#o",
+                &[255, 255, 255, 8, 8].map(Value::Integer)
+            ),
+            text("ff FF 0xff 10 010")
+        );
+    }
+
+    #[test]
+    fn real_conversions() {
+        let cases = [
+            ("%.2f", 1.23856, "1.24"),
+            ("%10.3f", 2.5, "     2.500"),
+            ("%-10.1f", 2.25, "2.3       "),
+            ("%+.0f", 2.5, "+3"),
+            ("%.1f", 0.05, "0.1"),
+            ("%.0f", 0.5, "1"),
+            ("%.0f", 1.5, "2"),
+            ("%e", 12345.678, "1.234568e+04"),
+            ("%.3E", 0.000123, "1.230E-04"),
+            ("%g", 0.0001, "0.0001"),
+            ("%g", 1e20, "1e+20"),
+            ("%#g", 1.5, "1.50000"),
+        ];
+        for (spec, value, expected) in cases {
+            assert_eq!(
+                format(spec, &[Value::Real(value)]),
+                text(expected),
+                "{spec}"
+            );
+        }
+    }
+
+    #[test]
+    fn text_conversions() {
+        assert_eq!(format("%.3s", &[text("abcdef")]), text("abc"));
+        assert_eq!(format("[%-6s]", &[text("ab")]), text("[ab    ]"));
+        assert_eq!(format("[%6s]", &[text("ab")]), text("[    ab]"));
+        assert_eq!(format("%z", &[text("x")]), text("x"));
+        assert_eq!(format("%c%c", &[text("xyz"), text("q")]), text("xq"));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(format("%q", &[text("it's")]), text("it''s"));
+        assert_eq!(format("%Q", &[text("it's")]), text("'it''s'"));
+        assert_eq!(format("%Q", &[Value::Null]), text("NULL"));
+        assert_eq!(format("%w", &[text("a\"b")]), text("a\"\"b"));
+    }
+
+    #[test]
+    fn widths_from_arguments_and_missing_arguments() {
+        let arguments = [4, 7, 4, 7, 2].map(Value::Integer);
+        assert_eq!(
+            format(
+                "%*d|%-*d|%.*f",
+                &[&arguments[..], &[Value::Real(1.23456)]].concat()
+            ),
+            text("   7|7   |1.23")
+        );
+        assert_eq!(format("%d %s", &[Value::Integer(1)]), text("1 "));
+        assert_eq!(format("%y ok", &[Value::Integer(1)]), text(""));
+    }
+}
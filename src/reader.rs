@@ -1,19 +1,25 @@
-use std::io::{Read, Seek, SeekFrom};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    rc::Rc,
+};
 
 use num_traits::FromBytes;
 
 use crate::{
+    cursor::{IndexCursor, TableCursor},
+    index_parser::IndexData,
     page::Page,
-    page_header::read_page_header,
+    page_header::{BtreePageType, read_page_header},
     parsing_error::ParsingError,
     sqlite_header::{SqliteHeader, read_sqlite_header},
+    value::Collation,
 };
 
 pub(crate) fn offset_range<'a>(buffer: &'a [u8], offset: &mut usize, size: usize) -> &'a [u8] {
     let begin_index = *offset;
     let end_index = *offset + size;
     *offset += size;
-    return &buffer[begin_index..end_index];
+    &buffer[begin_index..end_index]
 }
 
 pub(crate) fn get_num_from_be<'a, T>(offset: &mut usize, bytes: &'a [u8]) -> Result<T, ParsingError>
@@ -30,9 +36,10 @@ where
         .map_err(|err: <<T as FromBytes>::Bytes as TryFrom<&'a [u8]>>::Error| err.into())
 }
 
+/// Reads pages of a database file. Reading only needs a shared reference, so any number of
+/// cursors can be open on the same reader at once.
 pub struct SqliteReader {
     file: std::fs::File,
-    buffer: Vec<u8>,
     pub header: SqliteHeader,
 }
 
@@ -42,26 +49,134 @@ impl SqliteReader {
 
         let header = read_sqlite_header(&mut file)?;
 
-        return Ok(SqliteReader {
-            file: file,
-            buffer: vec![0; header.page_size as usize],
-            header: header,
-        });
+        Ok(SqliteReader { file, header })
+    }
+
+    /// Bytes of each page available to b-tree content (page size minus the reserved region).
+    pub fn usable_size(&self) -> usize {
+        self.header.page_size_in_bytes() - self.header.reserved_space as usize
+    }
+
+    /// Largest payload that is stored entirely on a b-tree page of the given type.
+    pub fn max_local(&self, page_type: BtreePageType) -> usize {
+        let usable_size = self.usable_size();
+        match page_type {
+            BtreePageType::LeafTablePage => usable_size - 35,
+            BtreePageType::InteriorTablePage
+            | BtreePageType::InteriorIndexPage
+            | BtreePageType::LeafIndexPage => {
+                (usable_size - 12) * self.header.max_payload_fraction as usize / 255 - 23
+            }
+        }
+    }
+
+    /// Minimum amount of an overflowing payload that is kept on the b-tree page.
+    pub fn min_local(&self) -> usize {
+        (self.usable_size() - 12) * self.header.min_payload_fraction as usize / 255 - 23
+    }
+
+    /// Number of payload bytes stored on the b-tree page itself, see
+    /// https://www.sqlite.org/fileformat2.html#b_tree_pages
+    pub fn local_payload_size(&self, page_type: BtreePageType, payload_size: usize) -> usize {
+        let max_local = self.max_local(page_type);
+        if payload_size <= max_local {
+            return payload_size;
+        }
+
+        let min_local = self.min_local();
+        let local = min_local + (payload_size - min_local) % (self.usable_size() - 4);
+        if local <= max_local { local } else { min_local }
     }
 
-    pub fn read_page(&mut self, page: u64) -> Result<Page, ParsingError> {
-        self.file
-            .seek(SeekFrom::Start((page - 1) * self.header.page_size as u64))?;
-        self.file.read_exact(&mut self.buffer)?;
+    /// Returns the buffer holding a cell payload of `payload_size` bytes starting at `offset`
+    /// in `page_data`, along with the offset of the payload inside that buffer.
+    ///
+    /// When the payload fits on the page the page buffer itself is returned, otherwise the
+    /// overflow page chain is followed and the payload is assembled into a new buffer.
+    pub fn read_payload(
+        &self,
+        page_data: &Rc<[u8]>,
+        offset: usize,
+        payload_size: usize,
+        page_type: BtreePageType,
+    ) -> Result<(Rc<[u8]>, usize), ParsingError> {
+        let local_size = self.local_payload_size(page_type, payload_size);
+        if local_size == payload_size {
+            return Ok((Rc::clone(page_data), offset));
+        }
+
+        let mut payload = Vec::with_capacity(payload_size);
+        payload.extend_from_slice(&page_data[offset..offset + local_size]);
+
+        let mut pointer_offset = offset + local_size;
+        let mut overflow_page: u32 = get_num_from_be(&mut pointer_offset, page_data)?;
+        let overflow_content_size = self.usable_size() - 4;
+        while payload.len() < payload_size {
+            if overflow_page == 0 {
+                return Err(ParsingError::InvalidOverflowChain);
+            }
+            let buffer = self.read_raw_page(overflow_page as u64)?;
+            let mut offset = 0;
+            overflow_page = get_num_from_be(&mut offset, &buffer)?;
+            let size = overflow_content_size.min(payload_size - payload.len());
+            payload.extend_from_slice(offset_range(&buffer, &mut offset, size));
+        }
+
+        Ok((payload.into(), 0))
+    }
+
+    fn read_raw_page(&self, page: u64) -> Result<Vec<u8>, ParsingError> {
+        let page_size = self.header.page_size_in_bytes();
+        let mut buffer = vec![0; page_size];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start((page - 1) * page_size as u64))?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reads the database header again from the file, which another connection may have changed
+    /// since the reader was opened.
+    pub fn read_header(&self) -> Result<SqliteHeader, ParsingError> {
+        let mut buffer = [0; 100];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut buffer)?;
+        SqliteHeader::from_bytes(&buffer)
+    }
+
+    pub fn read_page(&self, page: u64) -> Result<Page, ParsingError> {
+        let buffer = self.read_raw_page(page)?;
         let page_offset: usize = if page == 1 { 100 } else { 0 };
         let mut offset: usize = page_offset;
-        let page_header = read_page_header(&mut offset, &self.buffer)?;
+        let page_header = read_page_header(&mut offset, &buffer)?;
 
-        return Ok(Page {
-            page_header: page_header,
-            page: self.buffer[..].into(),
+        Ok(Page {
+            page_header,
+            page: buffer.into(),
             page_start: offset,
             page_offset,
-        });
+        })
+    }
+
+    /// Cursor over the rows of the table b-tree rooted at `root_page`.
+    pub fn table_cursor(&self, root_page: u64) -> TableCursor<'_> {
+        TableCursor::new(self, root_page)
+    }
+
+    /// Cursor over the entries of `index`, in the key order the index declares.
+    pub fn index_cursor(&self, index: &IndexData) -> IndexCursor<'_> {
+        let key_order = index
+            .columns
+            .iter()
+            .map(|column| {
+                let collation = column
+                    .collation
+                    .as_deref()
+                    .and_then(Collation::from_name)
+                    .unwrap_or_default();
+                (collation, column.order)
+            })
+            .collect();
+        IndexCursor::new(self, index.root_page).with_key_order(key_order)
     }
 }
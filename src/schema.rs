@@ -0,0 +1,299 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::Statement,
+    function::FunctionRegistry,
+    index_parser::IndexData,
+    parser::parse_statement,
+    parsing_error::ParsingError,
+    reader::SqliteReader,
+    select_builder::{SelectBuilder, SourceTable},
+    statistics::Statistics,
+    table_parser::Table,
+    trigger_parser::Trigger,
+    value::Value,
+    view_parser::View,
+};
+
+/// Page of the sqlite_schema table.
+const SCHEMA_PAGE_NUMBER: u64 = 1;
+
+const SCHEMA_TYPE_COLUMN: usize = 0;
+const SCHEMA_NAME_COLUMN: usize = 1;
+const SCHEMA_ROOT_PAGE_COLUMN: usize = 3;
+const SCHEMA_SQL_COLUMN: usize = 4;
+
+const STAT1_TABLE_NAME: &str = "sqlite_stat1";
+const STAT1_TABLE_COLUMN: usize = 0;
+const STAT1_INDEX_COLUMN: usize = 1;
+const STAT1_STAT_COLUMN: usize = 2;
+
+/// A table of the database, with its parsed `CREATE TABLE`.
+#[derive(Debug)]
+pub struct SchemaTable {
+    pub root_page: u64,
+    pub table: Table,
+    pub statistics: Statistics,
+}
+
+/// The objects of the database, read from sqlite_schema.
+#[derive(Debug)]
+pub struct Schema {
+    /// Schema cookie of the database header when the schema was read, which SQLite changes
+    /// whenever the schema does.
+    pub cookie: u32,
+    pub tables: Vec<SchemaTable>,
+    /// Indexes with a `CREATE INDEX`, the ones SQLite creates for UNIQUE and PRIMARY KEY
+    /// constraints having none.
+    pub indexes: Vec<IndexData>,
+    pub views: Vec<View>,
+    pub triggers: Vec<Trigger>,
+    /// Tables, indexes, views and triggers whose SQL this reader can't parse, by name, with
+    /// the error that only the queries reading them fail with.
+    pub unreadable: Vec<(String, ParsingError)>,
+}
+
+impl Schema {
+    pub fn load(reader: &SqliteReader) -> Result<Self, ParsingError> {
+        let mut schema = Schema {
+            cookie: reader.read_header()?.schema_cookie,
+            tables: vec![],
+            indexes: vec![],
+            views: vec![],
+            triggers: vec![],
+            unreadable: vec![],
+        };
+
+        for cell in reader.table_cursor(SCHEMA_PAGE_NUMBER) {
+            let cell = cell?;
+            let text = |column| -> Result<String, ParsingError> {
+                Ok(cell
+                    .get_column(column)?
+                    .as_text()
+                    .unwrap_or_default()
+                    .to_string())
+            };
+            let object_type = text(SCHEMA_TYPE_COLUMN)?;
+            let sql = cell.get_column(SCHEMA_SQL_COLUMN)?;
+            let Value::Text(sql) = sql else {
+                // Automatic indexes have no SQL
+                continue;
+            };
+            let root_page = cell
+                .get_column(SCHEMA_ROOT_PAGE_COLUMN)?
+                .as_integer()
+                .unwrap_or(0) as u64;
+
+            match object_type.as_str() {
+                // Virtual tables have no b-tree and are created by modules this reader doesn't have
+                "table" if root_page == 0 => {}
+                "table" | "index" | "view" | "trigger" => match parse_statement(&sql) {
+                    Ok(Statement::CreateTable(table)) => schema.tables.push(SchemaTable {
+                        root_page,
+                        table,
+                        statistics: Statistics::estimate(reader, root_page)?,
+                    }),
+                    Ok(Statement::CreateIndex(index)) => schema.indexes.push(IndexData {
+                        root_page,
+                        statistics: Statistics::estimate(reader, root_page)?,
+                        ..index
+                    }),
+                    Ok(Statement::CreateView(view)) => schema.views.push(view),
+                    Ok(Statement::CreateTrigger(trigger)) => schema.triggers.push(trigger),
+                    Ok(Statement::Select(_)) => return Err(ParsingError::InvalidStatement),
+                    Err(error) => schema.unreadable.push((text(SCHEMA_NAME_COLUMN)?, error)),
+                },
+                _ => {}
+            }
+        }
+        schema.read_stat1(reader)?;
+        Ok(schema)
+    }
+
+    /// Replaces the estimated sizes of the tables and indexes with the ones ANALYZE measured
+    /// and kept in sqlite_stat1. The rows of the indexes without a `CREATE INDEX` go to their
+    /// table, which for a WITHOUT ROWID table is the index on its primary key.
+    fn read_stat1(&mut self, reader: &SqliteReader) -> Result<(), ParsingError> {
+        let Some(stat1) = self.table(STAT1_TABLE_NAME) else {
+            return Ok(());
+        };
+        let stats = reader
+            .table_cursor(stat1.root_page)
+            .map(|cell| {
+                let cell = cell?;
+                Ok((
+                    cell.get_column(STAT1_TABLE_COLUMN)?,
+                    cell.get_column(STAT1_INDEX_COLUMN)?,
+                    cell.get_column(STAT1_STAT_COLUMN)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, ParsingError>>()?;
+
+        for (table_name, index_name, stat) in stats {
+            let (Some(table_name), Some(stat)) = (table_name.as_text(), stat.as_text()) else {
+                continue;
+            };
+            let index = index_name.as_text().and_then(|index_name| {
+                self.indexes
+                    .iter_mut()
+                    .find(|index| index.index_name.eq_ignore_ascii_case(index_name))
+            });
+            let table = self
+                .tables
+                .iter_mut()
+                .find(|table| table.table.name.eq_ignore_ascii_case(table_name));
+            match (index, table) {
+                (Some(index), Some(table)) => {
+                    index.statistics.apply_stat1(stat);
+                    table.statistics.rows = index.statistics.rows;
+                }
+                (Some(index), None) => index.statistics.apply_stat1(stat),
+                (None, Some(table)) => table.statistics.apply_stat1(stat),
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up a table by name, ignoring case like SQLite does.
+    pub fn table(&self, name: &str) -> Option<&SchemaTable> {
+        self.tables
+            .iter()
+            .find(|table| table.table.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn index(&self, name: &str) -> Option<&IndexData> {
+        self.indexes
+            .iter()
+            .find(|index| index.index_name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn view(&self, name: &str) -> Option<&View> {
+        self.views
+            .iter()
+            .find(|view| view.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn trigger(&self, name: &str) -> Option<&Trigger> {
+        self.triggers
+            .iter()
+            .find(|trigger| trigger.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Indexes on the table of the given name.
+    pub fn indexes_on<'a>(&'a self, table_name: &'a str) -> impl Iterator<Item = &'a IndexData> {
+        self.indexes
+            .iter()
+            .filter(move |index| index.table_name.eq_ignore_ascii_case(table_name))
+    }
+
+    /// A table or view to read in a query, with the indexes that can be used to read it. Views
+    /// are expanded into the query of their SELECT, bound to the tables it reads.
+    pub fn source_table(
+        &self,
+        name: &str,
+        functions: &FunctionRegistry,
+    ) -> Result<Option<SourceTable>, ParsingError> {
+        self.expand_source_table(name, functions, &mut vec![])
+    }
+
+    /// `source_table`, with the names of the views being expanded to detect views that read
+    /// themselves.
+    fn expand_source_table(
+        &self,
+        name: &str,
+        functions: &FunctionRegistry,
+        expanding: &mut Vec<String>,
+    ) -> Result<Option<SourceTable>, ParsingError> {
+        if let Some(SchemaTable {
+            root_page,
+            table,
+            statistics,
+        }) = self.table(name)
+        {
+            // The rows of a WITHOUT ROWID table are the entries of an index on its primary key,
+            // and its other indexes lead to the primary key instead of a rowid, so they aren't
+            // used.
+            let indexes = if table.without_rowid {
+                vec![IndexData {
+                    index_name: format!("sqlite_autoindex_{}_1", table.name),
+                    table_name: table.name.clone(),
+                    columns: table.primary_key(),
+                    partial: false,
+                    root_page: *root_page,
+                    statistics: statistics.clone(),
+                }]
+            } else {
+                self.indexes_on(&table.name).cloned().collect()
+            };
+            return Ok(Some(SourceTable {
+                root_page: *root_page,
+                table: table.clone(),
+                indexes,
+                statistics: statistics.clone(),
+                view: None,
+            }));
+        }
+
+        if let Some((_, error)) = self
+            .unreadable
+            .iter()
+            .find(|(unreadable, _)| unreadable.eq_ignore_ascii_case(name))
+        {
+            return Err(ParsingError::Semantic(format!(
+                "cannot read {name}: {error}"
+            )));
+        }
+        let Some(view) = self.view(name) else {
+            return Ok(None);
+        };
+        if expanding
+            .iter()
+            .any(|expanded| expanded.eq_ignore_ascii_case(&view.name))
+        {
+            return Err(ParsingError::Semantic(format!(
+                "view {} is circularly defined",
+                view.name
+            )));
+        }
+        expanding.push(view.name.clone());
+        let tables = view
+            .select
+            .tables()
+            .map(
+                |table| match self.expand_source_table(&table.name, functions, expanding)? {
+                    Some(source) => Ok(source),
+                    None => Err(ParsingError::Semantic(format!(
+                        "no such table: {}",
+                        table.name
+                    ))),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+        expanding.pop();
+
+        let select = SelectBuilder::from_select(view.select.clone(), tables, functions)?;
+        SourceTable::view(&view.name, &view.columns, select).map(Some)
+    }
+}
+
+/// Keeps the schema between statements, and reads it again once the schema cookie shows that
+/// it changed.
+#[derive(Default)]
+pub struct SchemaCache {
+    schema: Option<Rc<Schema>>,
+}
+
+impl SchemaCache {
+    pub fn get(&mut self, reader: &SqliteReader) -> Result<Rc<Schema>, ParsingError> {
+        let cookie = reader.read_header()?.schema_cookie;
+        match &self.schema {
+            Some(schema) if schema.cookie == cookie => Ok(Rc::clone(schema)),
+            _ => {
+                let schema = Rc::new(Schema::load(reader)?);
+                self.schema = Some(Rc::clone(&schema));
+                Ok(schema)
+            }
+        }
+    }
+}
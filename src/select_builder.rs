@@ -1,39 +1,204 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+    rc::Rc,
+};
+
 use crate::{
-    cell::{binary_search_cells_lazy, get_cells_lazy, index_search},
+    aggregate::{Accumulator, AggregateFunction, group_key},
+    binder::{Binder, collation},
+    cursor::{IndexCursor, IndexEntry},
+    expression::Expression,
+    expression_parser::{ColumnName, ParsedExpression},
+    function::FunctionRegistry,
     index_parser::IndexData,
     leaf_cell::LazyLeafCell,
     parsing_error::ParsingError,
+    planner::{
+        AccessPath, PlannedLookup, collect_equalities, join_lookup, last_column, plan_access_path,
+    },
     reader::SqliteReader,
-    select_parser::{ParsedCombinator, ParsedSelect, ParsedWhere},
-    table_parser::{Table, TableColumn},
+    select_parser::{
+        JoinKind, OrderingTerm, ParsedSelect, ResultColumn, SortOrder, TableReference,
+    },
+    statistics::Statistics,
+    table_parser::{ColumnStorage, Table, TableColumn},
+    value::{Affinity, Collation, Value},
 };
 
-pub fn unquote(value: &str) -> String {
-    if value.starts_with("\"") && value.ends_with("\"") && value.len() >= 2 {
-        return value[1..value.len() - 1].to_string();
-    }
-    if value.starts_with("'") && value.ends_with("'") && value.len() >= 2 {
-        return value[1..value.len() - 1].to_string();
+/// A column of the table, for queries built without SQL.
+pub enum Column {
+    RowId,
+    Column(usize, Affinity),
+}
+
+impl From<Column> for Expression {
+    fn from(column: Column) -> Self {
+        match column {
+            Column::RowId => Expression::Column(WhereColumn::RowId, Affinity::Integer),
+            Column::Column(index, affinity) => {
+                Expression::Column(WhereColumn::Column(index), affinity)
+            }
+        }
     }
-    value.to_string()
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-pub enum Column {
-    RowId,
-    Count,
-    Column(usize),
+/// An aggregate function call bound to the expression it runs over.
+pub struct AggregateCall {
+    pub function: AggregateFunction,
+    /// Argument of the aggregate, `None` for `COUNT(*)`.
+    pub argument: Option<Expression>,
+    pub distinct: bool,
+    pub separator: String,
+    /// Collation of the argument, which DISTINCT, MIN and MAX compare with.
+    pub collation: Collation,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum WhereColumn {
     RowId,
     Column(usize),
 }
 
-#[derive(Clone, Copy)]
+impl WhereColumn {
+    pub fn read(&self, cell: &LazyLeafCell) -> Result<Value, ParsingError> {
+        match self {
+            WhereColumn::Column(column) => cell.get_column(*column),
+            WhereColumn::RowId => Ok(Value::Integer(cell.rowid as i64)),
+        }
+    }
+
+    /// Same as `read`, converting integers stored in REAL columns back to reals.
+    pub fn read_with_affinity(
+        &self,
+        cell: &LazyLeafCell,
+        affinity: Affinity,
+    ) -> Result<Value, ParsingError> {
+        match self {
+            WhereColumn::Column(column) => cell.get_column_with_affinity(*column, affinity),
+            WhereColumn::RowId => Ok(Value::Integer(cell.rowid as i64)),
+        }
+    }
+
+    /// Position of the column in a row, the rowid coming first.
+    pub(crate) fn position(&self) -> usize {
+        match self {
+            WhereColumn::RowId => 0,
+            WhereColumn::Column(position) => *position,
+        }
+    }
+}
+
+/// Row that expressions read columns from: a row of the table, or a row joined from several
+/// tables or computed by an aggregate query, whose columns are read by position.
+pub trait Row {
+    fn read(&self, column: &WhereColumn) -> Result<Value, ParsingError>;
+
+    /// Same as `read`, converting integers stored in REAL columns back to reals when that
+    /// wasn't done when the row was built.
+    fn read_with_affinity(
+        &self,
+        column: &WhereColumn,
+        _affinity: Affinity,
+    ) -> Result<Value, ParsingError> {
+        self.read(column)
+    }
+
+    /// Result of the aggregate at `position` among the aggregates of the query, which only
+    /// the rows of groups have.
+    fn aggregate(&self, _position: usize) -> Result<Value, ParsingError> {
+        Err(ParsingError::InvalidStatement)
+    }
+}
+
+impl Row for LazyLeafCell {
+    fn read(&self, column: &WhereColumn) -> Result<Value, ParsingError> {
+        column.read(self)
+    }
+
+    fn read_with_affinity(
+        &self,
+        column: &WhereColumn,
+        affinity: Affinity,
+    ) -> Result<Value, ParsingError> {
+        column.read_with_affinity(self, affinity)
+    }
+}
+
+impl Row for Vec<Value> {
+    fn read(&self, column: &WhereColumn) -> Result<Value, ParsingError> {
+        match column {
+            WhereColumn::Column(position) => self
+                .get(*position)
+                .cloned()
+                .ok_or(ParsingError::InvalidStatement),
+            WhereColumn::RowId => Ok(Value::Null),
+        }
+    }
+}
+
+/// One key of an ORDER BY clause.
+#[derive(Clone)]
+pub struct SortKey {
+    pub expression: Expression,
+    pub collation: Collation,
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    fn compare(&self, lhs: &Value, rhs: &Value) -> Ordering {
+        match (lhs.is_null(), rhs.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ordering = lhs.compare_with(rhs, self.collation);
+                if self.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        }
+    }
+}
+
+/// Values of the sort keys for a row.
+fn sort_values<R: Row>(keys: &[SortKey], row: &R) -> Result<Vec<Value>, ParsingError> {
+    keys.iter()
+        .map(|key| key.expression.evaluate(row))
+        .collect()
+}
+
+/// Stable sort of rows paired with the values of their sort keys.
+fn sort_keyed<T>(rows: &mut [(Vec<Value>, T)], keys: &[SortKey]) {
+    rows.sort_by(|(lhs, _), (rhs, _)| {
+        keys.iter()
+            .zip(lhs.iter().zip(rhs))
+            .map(|(key, (lhs, rhs))| key.compare(lhs, rhs))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Stable sort of the rows on the given keys.
+fn sort_rows<R: Row>(rows: Vec<R>, keys: &[SortKey]) -> Result<Vec<R>, ParsingError> {
+    let mut keyed_rows = rows
+        .into_iter()
+        .map(|row| Ok((sort_values(keys, &row)?, row)))
+        .collect::<Result<Vec<_>, ParsingError>>()?;
+    sort_keyed(&mut keyed_rows, keys);
+    Ok(keyed_rows.into_iter().map(|(_, row)| row).collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Op {
     Eq,
+    NotEq,
     Lt,
     Gt,
     GtEq,
@@ -41,19 +206,41 @@ pub enum Op {
 }
 
 impl Op {
-    pub fn apply(&self, lhs: &String, rhs: &String) -> bool {
+    /// Compares two values, a NULL on either side never matches.
+    pub fn apply(&self, lhs: &Value, rhs: &Value) -> bool {
+        if lhs.is_null() || rhs.is_null() {
+            return false;
+        }
+        self.matches(lhs.compare(rhs))
+    }
+
+    /// Whether operands ordered this way satisfy the comparison.
+    pub fn matches(&self, ordering: Ordering) -> bool {
         match self {
-            Op::Eq => lhs == rhs,
-            Op::Lt => lhs < rhs,
-            Op::Gt => lhs > rhs,
-            Op::GtEq => lhs >= rhs,
-            Op::LtEq => lhs <= rhs,
+            Op::Eq => ordering == Ordering::Equal,
+            Op::NotEq => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::GtEq => ordering != Ordering::Less,
+            Op::LtEq => ordering != Ordering::Greater,
+        }
+    }
+
+    /// The operator comparing the operands the other way around, `a < b` being `b > a`.
+    pub fn flip(&self) -> Self {
+        match self {
+            Op::Lt => Op::Gt,
+            Op::Gt => Op::Lt,
+            Op::GtEq => Op::LtEq,
+            Op::LtEq => Op::GtEq,
+            op => *op,
         }
     }
 
     pub fn as_str(&self) -> &'static str {
         match self {
             Op::Eq => "=",
+            Op::NotEq => "!=",
             Op::Lt => "<",
             Op::Gt => ">",
             Op::GtEq => ">=",
@@ -61,245 +248,1394 @@ impl Op {
         }
     }
 }
-#[derive(Clone)]
-pub struct Expression {
-    column: WhereColumn,
-    op: Op,
-    value: String,
+
+/// `column op value`, comparing the column as it's stored.
+pub fn where_builder(column: WhereColumn, op: Op, value: Value) -> Expression {
+    Expression::comparison(
+        Expression::Column(column, Affinity::Blob),
+        op,
+        Expression::Literal(value),
+        Collation::Binary,
+    )
 }
 
-#[derive(Clone)]
-pub enum Combinator {
-    And(Box<Where>),
-    Or(Box<Where>),
+/// A table of the FROM clause, with its schema and indexes from sqlite_schema.
+pub struct SourceTable {
+    pub root_page: u64,
+    pub table: Table,
+    pub indexes: Vec<IndexData>,
+    pub statistics: Statistics,
+    /// Query computing the rows of a view, whose columns are the result columns of the query.
+    pub view: Option<SelectBuilder>,
 }
 
-#[derive(Clone)]
-pub struct Where {
-    expression: Expression,
-    combinator: Option<Combinator>,
+impl SourceTable {
+    /// The view `name` over the rows of `select`, its columns being named `column_names` or
+    /// else keeping the names of the result columns.
+    pub fn view(
+        name: &str,
+        column_names: &[String],
+        select: SelectBuilder,
+    ) -> Result<Self, ParsingError> {
+        if !column_names.is_empty() && column_names.len() != select.output_columns.len() {
+            return Err(ParsingError::Semantic(format!(
+                "expected {} columns for '{name}' but got {}",
+                column_names.len(),
+                select.output_columns.len()
+            )));
+        }
+        let columns = select
+            .output_columns
+            .iter()
+            .enumerate()
+            .map(|(position, column)| TableColumn {
+                name: column_names.get(position).unwrap_or(&column.name).clone(),
+                declared_type: String::new(),
+                affinity: column.affinity,
+                storage: ColumnStorage::Record(position),
+                default: None,
+                collation: column.collation.clone(),
+                constraints: vec![],
+            })
+            .collect();
+        Ok(SourceTable {
+            root_page: 0,
+            table: Table {
+                name: name.to_string(),
+                columns,
+                constraints: vec![],
+                // Views have no rowid
+                without_rowid: true,
+                strict: false,
+            },
+            indexes: vec![],
+            statistics: Statistics::default(),
+            view: Some(select),
+        })
+    }
 }
 
-impl Where {
-    pub fn or(self, other: Where) -> Self {
-        return Self {
-            combinator: Some(Combinator::Or(Box::new(other))),
-            ..self
-        };
+/// A result column of a query, as the columns of a view over it see it.
+pub struct OutputColumn {
+    pub name: String,
+    /// Affinity of the column of a table the result column is, or of its CAST, and no affinity
+    /// otherwise.
+    pub affinity: Affinity,
+    pub collation: Option<String>,
+}
+
+/// Finds the rows of a joined table equal on a column to a value of the tables before it,
+/// instead of reading the whole table.
+pub enum JoinLookup {
+    /// The rowid equals the value at this position of the joined row.
+    RowId(usize),
+    /// The first key column of the index equals the value at this position of the joined row,
+    /// converted to the affinity.
+    Index(IndexData, usize, Option<Affinity>),
+}
+
+impl JoinLookup {
+    /// Value the rows have to match for the joined row built so far.
+    fn probe(&self, row: &[Value]) -> Value {
+        match self {
+            JoinLookup::RowId(position) => {
+                match row[*position].clone().apply_affinity(Affinity::Numeric) {
+                    Value::Real(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+                        Value::Integer(value as i64)
+                    }
+                    value => value,
+                }
+            }
+            JoinLookup::Index(_, position, affinity) => match affinity {
+                Some(affinity) => row[*position].clone().apply_affinity(*affinity),
+                None => row[*position].clone(),
+            },
+        }
     }
+}
 
-    pub fn and(self, other: Where) -> Self {
-        return Self {
-            combinator: Some(Combinator::And(Box::new(other))),
-            ..self
-        };
+/// A table joined to the rows of the tables before it with a nested loop. Joined rows hold,
+/// for each table in turn, its rowid followed by its columns.
+pub struct Join {
+    pub table: u64,
+    /// Query computing the rows of a joined view.
+    pub view: Option<SelectBuilder>,
+    /// Affinities of the columns of the table.
+    pub columns: Vec<Affinity>,
+    /// Values of the columns that older rows don't have.
+    pub defaults: Rc<[Value]>,
+    /// Whether rows without a match are kept, with NULLs for this table, like a LEFT JOIN.
+    pub left: bool,
+    pub on: Option<Expression>,
+    /// Conditions of the WHERE clause that can be checked once this table is joined.
+    pub filter: Option<Expression>,
+    pub lookup: Option<JoinLookup>,
+}
+
+/// A row of a table of the FROM clause, read from its b-tree or computed for a view.
+enum SourceRow<'r> {
+    Cell(&'r LazyLeafCell),
+    View(&'r [Value]),
+}
+
+/// Appends a table row to a joined row, or NULLs for the unmatched side of a LEFT JOIN. The
+/// rowid of a view row is NULL.
+fn append_row(
+    row: &mut Vec<Value>,
+    source_row: Option<SourceRow>,
+    columns: &[Affinity],
+) -> Result<(), ParsingError> {
+    match source_row {
+        Some(SourceRow::Cell(cell)) => {
+            row.push(Value::Integer(cell.rowid as i64));
+            for (column, affinity) in columns.iter().enumerate() {
+                row.push(cell.get_column_with_affinity(column, *affinity)?);
+            }
+        }
+        Some(SourceRow::View(values)) => {
+            row.push(Value::Null);
+            row.extend_from_slice(values);
+        }
+        None => row.extend(std::iter::repeat_n(Value::Null, columns.len() + 1)),
     }
+    Ok(())
+}
 
-    pub fn execute(&self, page_bytes: &[u8], cell: &LazyLeafCell) -> Result<bool, ParsingError> {
-        let column = match self.expression.column {
-            WhereColumn::Column(column) => cell.get_column_cmp(column)?,
-            WhereColumn::RowId => cell.rowid.to_string(),
-        };
+/// Entries of an index whose first key columns are equal to `prefix`, and whose next key column
+/// is within the bounds. Without a prefix or bounds, every entry.
+#[derive(Default)]
+pub struct KeyRange {
+    pub prefix: Vec<Value>,
+    /// `>` or `>=` bound.
+    pub lower: Option<(Op, Value)>,
+    /// `<` or `<=` bound.
+    pub upper: Option<(Op, Value)>,
+}
 
-        let own_comp = compare(&self.expression.value, &column, self.expression.op);
-        match &self.combinator {
-            Some(Combinator::And(value)) => Ok(own_comp && value.execute(page_bytes, cell)?),
-            Some(Combinator::Or(value)) => Ok(own_comp || value.execute(page_bytes, cell)?),
-            None => Ok(own_comp),
+impl KeyRange {
+    /// Entries whose first key column satisfies `op value`, every entry being able to differ
+    /// from the value.
+    pub fn from_comparison(op: Op, value: Value) -> Self {
+        let mut range = KeyRange::default();
+        match op {
+            Op::Eq => range.prefix.push(value),
+            Op::Gt | Op::GtEq => range.lower = Some((op, value)),
+            Op::Lt | Op::LtEq => range.upper = Some((op, value)),
+            Op::NotEq => {}
         }
+        range
     }
 
-    fn from_table(comp: &ParsedWhere, table: &Table) -> Result<Where, ParsingError> {
-        let column = table
-            .get_column_by_name(&comp.expression.column)
-            .ok_or(ParsingError::InvalidStatement)?;
-        let column = match column {
-            TableColumn::RowId(_) => WhereColumn::RowId,
-            TableColumn::Column(index, _) => WhereColumn::Column(*index),
+    pub(crate) fn is_everything(&self) -> bool {
+        self.prefix.is_empty() && self.lower.is_none() && self.upper.is_none()
+    }
+
+    /// Whether the range holds the key. Keys of the range are next to each other in the index,
+    /// so reading stops at the first key outside of it.
+    fn contains(&self, entries: &IndexCursor, key: &[Value]) -> bool {
+        let within = |bound: &Option<(Op, Value)>| {
+            bound.as_ref().is_none_or(|(op, value)| {
+                key.get(self.prefix.len())
+                    .is_some_and(|key| op.apply(key, value))
+            })
         };
+        entries.compare_key(key, &self.prefix).is_eq() && within(&self.lower) && within(&self.upper)
+    }
 
-        let combinator = match &comp.combinator {
-            Some(ParsedCombinator::And(comb)) => {
-                Some(Combinator::And(Box::new(Self::from_table(&comb, table)?)))
+    /// Positions `entries` on the first entry of the range in the direction the index is read
+    /// in.
+    fn start(
+        &self,
+        entries: &mut IndexCursor,
+        descending: bool,
+    ) -> Result<Option<IndexEntry>, ParsingError> {
+        let probe = |value: &Value| [&self.prefix[..], std::slice::from_ref(value)].concat();
+        Ok(match (descending, &self.lower, &self.upper) {
+            (false, Some((Op::Gt, value)), _) => entries.seek_past(&probe(value))?,
+            (false, Some((_, value)), _) => entries.seek(&probe(value))?,
+            // NULL keys sort first and are never within a bound
+            (false, None, Some(_)) => entries.seek_past(&probe(&Value::Null))?,
+            (false, None, None) => entries.seek(&self.prefix)?,
+            (true, _, Some((Op::Lt, value))) => {
+                entries.seek(&probe(value))?;
+                entries.prev()?
             }
-            Some(ParsedCombinator::Or(comb)) => {
-                Some(Combinator::Or(Box::new(Self::from_table(&comb, table)?)))
+            (true, _, Some((_, value))) => {
+                entries.seek_past(&probe(value))?;
+                entries.prev()?
             }
-            None => None,
-        };
-
-        return Ok(Where {
-            expression: Expression {
-                column,
-                op: comp.expression.op,
-                value: comp.expression.value.clone(),
-            },
-            combinator,
-        });
+            (true, _, None) => {
+                entries.seek_past(&self.prefix)?;
+                entries.prev()?
+            }
+        })
     }
 }
 
-pub fn compare(lhs: &str, rhs: &str, op: Op) -> bool {
-    // Remove quotes from both sides for comparison
-    let lhs_unquoted = unquote(lhs);
-    let rhs_unquoted = unquote(rhs);
+/// Reads the rows of a table through the entries of one of its indexes within a range.
+pub struct IndexScan {
+    pub index: IndexData,
+    pub range: KeyRange,
+}
 
-    return op.apply(&lhs_unquoted, &rhs_unquoted);
+/// Rowids from `start` to `end` included, whose rows are read from the table b-tree directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowidRange {
+    pub start: i128,
+    pub end: i128,
 }
 
-pub fn where_builder(column: WhereColumn, op: Op, value: String) -> Where {
-    return Where {
-        expression: Expression { column, op, value },
-        combinator: None,
-    };
+impl Default for RowidRange {
+    fn default() -> Self {
+        RowidRange {
+            start: i64::MIN.into(),
+            end: i64::MAX.into(),
+        }
+    }
 }
 
 pub struct SelectBuilder {
-    pub(crate) index: Option<(u64, (Op, String, WhereColumn))>,
+    pub(crate) index: Option<IndexScan>,
+    pub(crate) rowids: Option<RowidRange>,
     pub(crate) table: u64,
-    pub(crate) columns: Vec<Column>,
-    pub(crate) where_comps: Option<Where>,
+    /// Query computing the rows of the first table when it's a view, whose columns are then
+    /// read from joined rows.
+    pub(crate) table_view: Option<Box<SelectBuilder>>,
+    /// Tables joined to the first one, whose columns are then read from joined rows.
+    pub(crate) joins: Vec<Join>,
+    /// Affinities of the columns of the first table, to build joined rows.
+    pub(crate) table_columns: Vec<Affinity>,
+    /// Values of the columns of the first table that older rows don't have.
+    pub(crate) table_defaults: Rc<[Value]>,
+    /// Result columns, followed in aggregate queries by the hidden ones that only ORDER BY
+    /// reads.
+    pub(crate) columns: Vec<Expression>,
+    pub(crate) visible_columns: usize,
+    /// Collations the visible columns are compared in to drop duplicate rows, for
+    /// `SELECT DISTINCT`.
+    pub(crate) distinct: Option<Vec<Collation>>,
+    /// Names and types of the visible columns, for queries built from SQL.
+    pub(crate) output_columns: Vec<OutputColumn>,
+    /// Aggregates computed for each group, which expressions refer to by position.
+    pub(crate) aggregates: Vec<AggregateCall>,
+    pub(crate) where_comps: Option<Expression>,
+    /// GROUP BY terms, compared in their collation and ascending to sort the groups.
+    pub(crate) group_by: Vec<SortKey>,
+    /// Filter on the groups of an aggregate query.
+    pub(crate) having: Option<Expression>,
+    /// Keys to sort on, which read the computed row by position in aggregate queries.
+    pub(crate) order_by: Vec<SortKey>,
+    /// Direction to read the index, or else the table b-tree, in when it already yields the
+    /// ORDER BY order, or the GROUP BY order in aggregate queries.
+    pub(crate) index_order: Option<SortOrder>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) offset: usize,
 }
 
 impl SelectBuilder {
     pub fn new(table: u64, columns: Vec<Column>) -> Self {
-        return SelectBuilder {
+        SelectBuilder {
             index: None,
+            rowids: None,
             table,
-            columns,
+            table_view: None,
+            joins: vec![],
+            table_columns: vec![],
+            table_defaults: Rc::from([]),
+            visible_columns: columns.len(),
+            distinct: None,
+            output_columns: vec![],
+            columns: columns.into_iter().map(Expression::from).collect(),
+            aggregates: vec![],
             where_comps: None,
-        };
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            index_order: None,
+            limit: None,
+            offset: 0,
+        }
     }
 
-    pub fn where_cmp(self, comp: Where) -> Self {
-        return Self {
+    pub fn where_cmp(self, comp: Expression) -> Self {
+        Self {
             where_comps: Some(comp),
             ..self
-        };
+        }
     }
 
-    pub fn with_index(self, index_page: u64, op: Op, value: String, index_column: WhereColumn) -> Self {
-        return Self {
-            index: Some((index_page, (op, value, index_column))),
+    pub fn group_by(self, columns: Vec<Expression>) -> Self {
+        Self {
+            group_by: columns
+                .into_iter()
+                .map(|expression| SortKey {
+                    expression,
+                    collation: Collation::Binary,
+                    descending: false,
+                    nulls_first: true,
+                })
+                .collect(),
             ..self
-        };
+        }
     }
 
-    pub fn execute(
-        self,
-        sqlite_reader: &mut SqliteReader,
-    ) -> Result<Vec<Vec<String>>, ParsingError> {
-        if self.index.is_some() && self.where_comps.is_none() {
-            panic!("Can't read index without a comp");
+    pub fn having(self, comp: Expression) -> Self {
+        Self {
+            having: Some(comp),
+            ..self
         }
+    }
 
-        let page = sqlite_reader.read_page(self.table)?;
+    pub fn order_by(self, keys: Vec<SortKey>) -> Self {
+        Self {
+            order_by: keys,
+            ..self
+        }
+    }
+
+    /// Keeps at most `limit` rows after skipping `offset` ones, a negative limit meaning no limit.
+    pub fn limit(self, limit: i64, offset: i64) -> Self {
+        Self {
+            limit: usize::try_from(limit).ok(),
+            offset: usize::try_from(offset).unwrap_or(0),
+            ..self
+        }
+    }
+
+    pub fn with_index(self, index: IndexData, range: KeyRange) -> Self {
+        Self {
+            index: Some(IndexScan { index, range }),
+            ..self
+        }
+    }
+
+    pub fn execute(&self, sqlite_reader: &SqliteReader) -> Result<Vec<Vec<Value>>, ParsingError> {
+        let mut rows = vec![];
+        self.for_each_row(sqlite_reader, |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        Ok(rows)
+    }
+
+    /// Feeds the result rows to `visit` as they're produced, without keeping them.
+    pub fn for_each_row<F>(
+        &self,
+        sqlite_reader: &SqliteReader,
+        mut visit: F,
+    ) -> Result<(), ParsingError>
+    where
+        F: FnMut(Vec<Value>) -> Result<(), ParsingError>,
+    {
+        let visit = &mut visit;
+        let reads_table = self.joins.is_empty() && self.table_view.is_none();
+        match (reads_table, self.is_aggregate()) {
+            (true, false) => self.table_rows(|scan| self.scan(sqlite_reader, scan), visit),
+            (true, true) => self.aggregate_rows(|scan| self.scan(sqlite_reader, scan), visit),
+            (false, false) => self.table_rows(|scan| self.scan_joins(sqlite_reader, scan), visit),
+            (false, true) => {
+                self.aggregate_rows(|scan| self.scan_joins(sqlite_reader, scan), visit)
+            }
+        }
+    }
+
+    /// Names and types of the result columns.
+    pub fn output_columns(&self) -> &[OutputColumn] {
+        &self.output_columns
+    }
+
+    fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || self.having.is_some() || !self.aggregates.is_empty()
+    }
 
-        let cells = if let Some((index_page, (op, value, index_column))) = self.index {
-            let index_page = sqlite_reader.read_page(index_page)?;
-            let rowids = index_search(&index_page, sqlite_reader, index_column, value, op)?;
-            binary_search_cells_lazy(&page, sqlite_reader, &rowids)?
+    /// When rows are produced in their final order, producing them can stop once the rows
+    /// skipped by OFFSET and the ones kept by LIMIT are there.
+    fn row_limit(&self, needs_sort: bool) -> Option<usize> {
+        match self.limit {
+            Some(limit) if !needs_sort => Some(self.offset.saturating_add(limit)),
+            _ => None,
+        }
+    }
+
+    /// Feeds the rows matching the WHERE clause to `visit` in the order they're read in, until
+    /// it breaks.
+    fn scan<F>(&self, sqlite_reader: &SqliteReader, mut visit: F) -> Result<(), ParsingError>
+    where
+        F: FnMut(LazyLeafCell) -> Result<ControlFlow<()>, ParsingError>,
+    {
+        let matches = |cell: &LazyLeafCell| match &self.where_comps {
+            Some(comp) => comp.matches(cell),
+            None => Ok(true),
+        };
+
+        if let Some(IndexScan { index, range }) = &self.index {
+            let descending = self.index_order == Some(SortOrder::Desc);
+            let mut entries = index_cursor(sqlite_reader, index, self.table);
+            let mut rows = sqlite_reader
+                .table_cursor(self.table)
+                .with_defaults(self.table_defaults.clone());
+
+            let mut entry = range.start(&mut entries, descending)?;
+            while let Some(current) = entry {
+                if !range.contains(&entries, &current.key) {
+                    break;
+                }
+                if let Some(cell) = rows.seek_entry(&entries, &current)?
+                    && matches(&cell)?
+                    && visit(cell)?.is_break()
+                {
+                    break;
+                }
+
+                entry = if descending {
+                    entries.prev()?
+                } else {
+                    entries.next().transpose()?
+                };
+            }
         } else {
-            let cells = get_cells_lazy(&page, sqlite_reader)?;
+            // Without an index the rows are read by rowid, within the range of rowids if any
+            let descending = self.index_order == Some(SortOrder::Desc);
+            let mut rows = sqlite_reader
+                .table_cursor(self.table)
+                .with_defaults(self.table_defaults.clone());
+            let mut row = match (self.rowids, descending) {
+                (Some(RowidRange { end, .. }), true) => {
+                    rows.seek(end + 1)?;
+                    rows.prev()?
+                }
+                (Some(RowidRange { start, .. }), false) => rows.seek(start)?,
+                (None, true) => rows.seek_last()?,
+                (None, false) => rows.first()?,
+            };
+            while let Some(cell) = row {
+                if self
+                    .rowids
+                    .is_some_and(|rowids| !(rowids.start..=rowids.end).contains(&cell.rowid))
+                {
+                    break;
+                }
+                if matches(&cell)? && visit(cell)?.is_break() {
+                    break;
+                }
+                row = if descending {
+                    rows.prev()?
+                } else {
+                    rows.next().transpose()?
+                };
+            }
+        }
+        Ok(())
+    }
 
-            if let Some(comp) = self.where_comps {
-                cells
-                    .iter()
-                    .filter(|cell| comp.execute(&page.page, cell).is_ok_and(|result| result))
-                    .cloned()
-                    .collect::<Vec<_>>()
-            } else {
-                cells
+    /// Feeds the joined rows matching the WHERE clause to `visit`, until it breaks. The WHERE
+    /// clause of a join only holds the conditions on the first table, the others are checked
+    /// by the joins. Rows of views are read the same way, joined or not.
+    fn scan_joins<F>(&self, sqlite_reader: &SqliteReader, mut visit: F) -> Result<(), ParsingError>
+    where
+        F: FnMut(Vec<Value>) -> Result<ControlFlow<()>, ParsingError>,
+    {
+        // Joined views are computed once, instead of for every row they're joined to
+        let views = self
+            .joins
+            .iter()
+            .map(|join| {
+                join.view
+                    .as_ref()
+                    .map(|view| view.execute(sqlite_reader))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut row = vec![];
+        let mut visit_row = |source_row: SourceRow| {
+            row.clear();
+            append_row(&mut row, Some(source_row), &self.table_columns)?;
+            if let Some(comp) = &self.where_comps
+                && !comp.matches(&row)?
+            {
+                return Ok(ControlFlow::Continue(()));
+            }
+            self.join_rows(sqlite_reader, &views, 0, &mut row, &mut visit)
+        };
+
+        match &self.table_view {
+            Some(view) => {
+                for values in view.execute(sqlite_reader)? {
+                    if visit_row(SourceRow::View(&values))?.is_break() {
+                        break;
+                    }
+                }
+            }
+            None => {
+                for cell in sqlite_reader
+                    .table_cursor(self.table)
+                    .with_defaults(self.table_defaults.clone())
+                {
+                    if visit_row(SourceRow::Cell(&cell?))?.is_break() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes `row` with the matching rows of the joined table at `level` and the ones after
+    /// it, and feeds the complete rows matching the WHERE clause to `visit`. `views` holds the
+    /// rows of the joined views.
+    fn join_rows<F>(
+        &self,
+        sqlite_reader: &SqliteReader,
+        views: &[Option<Vec<Vec<Value>>>],
+        level: usize,
+        row: &mut Vec<Value>,
+        visit: &mut F,
+    ) -> Result<ControlFlow<()>, ParsingError>
+    where
+        F: FnMut(Vec<Value>) -> Result<ControlFlow<()>, ParsingError>,
+    {
+        let Some(join) = self.joins.get(level) else {
+            return visit(row.clone());
+        };
+        let matches_filter = |row: &Vec<Value>| match &join.filter {
+            Some(filter) => filter.matches(row),
+            None => Ok(true),
+        };
+
+        let width = row.len();
+        let probe = join.lookup.as_ref().map(|lookup| lookup.probe(row));
+        let mut matched = false;
+        let mut flow = ControlFlow::Continue(());
+        let mut visit_row = |source_row: SourceRow| {
+            row.truncate(width);
+            append_row(row, Some(source_row), &join.columns)?;
+            if join.on.as_ref().map_or(Ok(true), |on| on.matches(row))? {
+                matched = true;
+                if matches_filter(row)? {
+                    flow = self.join_rows(sqlite_reader, views, level + 1, row, visit)?;
+                }
+            }
+            Ok::<_, ParsingError>(flow)
+        };
+
+        let mut rows = sqlite_reader
+            .table_cursor(join.table)
+            .with_defaults(join.defaults.clone());
+        match (&views[level], &join.lookup, probe) {
+            (Some(view_rows), ..) => {
+                for values in view_rows {
+                    if visit_row(SourceRow::View(values))?.is_break() {
+                        break;
+                    }
+                }
+            }
+            (None, None, _) => {
+                for cell in rows {
+                    if visit_row(SourceRow::Cell(&cell?))?.is_break() {
+                        break;
+                    }
+                }
+            }
+            (None, Some(JoinLookup::RowId(_)), Some(Value::Integer(rowid))) => {
+                if let Some(cell) = rows.seek(rowid as i128)?
+                    && cell.rowid == rowid as i128
+                {
+                    // The only candidate, there's nothing left to stop reading
+                    let _ = visit_row(SourceRow::Cell(&cell))?;
+                }
+            }
+            (None, Some(JoinLookup::Index(index, ..)), Some(probe)) if !probe.is_null() => {
+                let mut entries = index_cursor(sqlite_reader, index, join.table);
+                let mut entry = entries.seek(std::slice::from_ref(&probe))?;
+                while let Some(current) = entry {
+                    if current
+                        .key
+                        .first()
+                        .is_none_or(|key| key.compare(&probe).is_ne())
+                    {
+                        break;
+                    }
+                    if let Some(cell) = rows.seek_entry(&entries, &current)?
+                        && visit_row(SourceRow::Cell(&cell))?.is_break()
+                    {
+                        break;
+                    }
+                    entry = entries.next().transpose()?;
+                }
             }
+            // NULLs and values of the wrong type can't be equal to any row
+            _ => {}
+        }
+
+        if !matched && join.left && flow.is_continue() {
+            row.truncate(width);
+            append_row(row, None, &join.columns)?;
+            if matches_filter(row)? {
+                flow = self.join_rows(sqlite_reader, views, level + 1, row, visit)?;
+            }
+        }
+        row.truncate(width);
+        Ok(flow)
+    }
+
+    /// Feeds to `visit` the rows of a query without aggregation, one per row fed by `scan` past
+    /// the OFFSET and within the LIMIT. Rows are turned into their columns as they're fed, and
+    /// only kept when they have to be sorted.
+    fn table_rows<R, S>(
+        &self,
+        scan: S,
+        visit: &mut dyn FnMut(Vec<Value>) -> Result<(), ParsingError>,
+    ) -> Result<(), ParsingError>
+    where
+        R: Row,
+        S: FnOnce(
+            &mut dyn FnMut(R) -> Result<ControlFlow<()>, ParsingError>,
+        ) -> Result<(), ParsingError>,
+    {
+        let project = |cell: &R| {
+            self.columns
+                .iter()
+                .map(|column| column.evaluate(cell))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let mut seen = HashSet::new();
+        let mut is_new = |row: &[Value]| match &self.distinct {
+            Some(collations) => seen.insert(group_key(row, collations)),
+            None => true,
         };
 
-        let count = cells.len();
-        if &self.columns == &[Column::Count] {
-            return Ok(vec![vec![count.to_string()]]);
+        // Rows to sort are kept as their columns and the values of the sort keys, and with a
+        // LIMIT only as many as come first in order. Distinct rows are all kept, since a row
+        // dropped for coming late could otherwise come back.
+        if self.index_order.is_none() && !self.order_by.is_empty() {
+            let keep = match self.distinct {
+                Some(_) => usize::MAX,
+                None => self.offset.saturating_add(limit),
+            };
+            let mut rows = vec![];
+            scan(&mut |cell| {
+                let row = project(&cell)?;
+                if !is_new(&row) {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                rows.push((sort_values(&self.order_by, &cell)?, row));
+                if rows.len() >= keep.saturating_mul(2).max(1024) {
+                    sort_keyed(&mut rows, &self.order_by);
+                    rows.truncate(keep);
+                }
+                Ok(ControlFlow::Continue(()))
+            })?;
+            sort_keyed(&mut rows, &self.order_by);
+            for (_, row) in rows.into_iter().skip(self.offset).take(limit) {
+                visit(row)?;
+            }
+            return Ok(());
+        }
+
+        if limit == 0 {
+            return Ok(());
         }
+        let (mut skipped, mut kept) = (0, 0);
+        scan(&mut |cell| {
+            let row = project(&cell)?;
+            if !is_new(&row) {
+                return Ok(ControlFlow::Continue(()));
+            }
+            if skipped < self.offset {
+                skipped += 1;
+                return Ok(ControlFlow::Continue(()));
+            }
+            visit(row)?;
+            kept += 1;
+            Ok(if kept >= limit {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })
+    }
 
-        return cells
+    /// Feeds to `visit` the rows of an aggregate query over the rows fed by `scan`: one per
+    /// group passing the HAVING clause, or a single one over all the rows without GROUP BY.
+    fn aggregate_rows<R, S>(
+        &self,
+        scan: S,
+        visit: &mut dyn FnMut(Vec<Value>) -> Result<(), ParsingError>,
+    ) -> Result<(), ParsingError>
+    where
+        R: Row + Clone,
+        S: FnOnce(
+            &mut dyn FnMut(R) -> Result<ControlFlow<()>, ParsingError>,
+        ) -> Result<(), ParsingError>,
+    {
+        let aggregates = &self.aggregates[..];
+        let extreme_aggregate = extreme_aggregate(aggregates);
+        let group_values = |cell: &R| {
+            self.group_by
+                .iter()
+                .map(|key| key.expression.evaluate(cell))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let collations = self
+            .group_by
             .iter()
-            .map(|cell| {
-                self.columns
+            .map(|key| key.collation)
+            .collect::<Vec<_>>();
+
+        let mut rows = vec![];
+        if self.group_by.is_empty() {
+            let mut group = Group::new(aggregates);
+            scan(&mut |cell| {
+                group.step(aggregates, extreme_aggregate, &cell)?;
+                Ok(ControlFlow::Continue(()))
+            })?;
+            rows.extend(self.group_row(group)?);
+        } else if self.index_order.is_some() {
+            // The index yields the rows group after group, so each group is complete as soon
+            // as the next one starts.
+            let row_limit = self.row_limit(!self.order_by.is_empty() || self.distinct.is_some());
+            let mut current: Option<(Vec<u8>, Group<R>)> = None;
+            scan(&mut |cell| {
+                let key = group_key(&group_values(&cell)?, &collations);
+                if current
+                    .as_ref()
+                    .is_none_or(|(current_key, _)| *current_key != key)
+                {
+                    if let Some((_, group)) = current.take() {
+                        rows.extend(self.group_row(group)?);
+                        if row_limit.is_some_and(|row_limit| rows.len() >= row_limit) {
+                            return Ok(ControlFlow::Break(()));
+                        }
+                    }
+                    current = Some((key, Group::new(aggregates)));
+                }
+                if let Some((_, group)) = &mut current {
+                    group.step(aggregates, extreme_aggregate, &cell)?;
+                }
+                Ok(ControlFlow::Continue(()))
+            })?;
+            if let Some((_, group)) = current {
+                rows.extend(self.group_row(group)?);
+            }
+        } else {
+            let mut positions = HashMap::new();
+            let mut groups: Vec<(Vec<Value>, Group<R>)> = vec![];
+            scan(&mut |cell| {
+                let values = group_values(&cell)?;
+                let key = group_key(&values, &collations);
+                let position = *positions.entry(key).or_insert_with(|| {
+                    groups.push((values, Group::new(aggregates)));
+                    groups.len() - 1
+                });
+                groups[position]
+                    .1
+                    .step(aggregates, extreme_aggregate, &cell)?;
+                Ok(ControlFlow::Continue(()))
+            })?;
+
+            // Groups come out sorted by their GROUP BY values, like SQLite's
+            groups.sort_by(|(lhs, _), (rhs, _)| {
+                self.group_by
                     .iter()
-                    .map(|column| match column {
-                        Column::RowId => Ok(cell.rowid.to_string()),
-                        Column::Count => Ok(count.to_string()),
-                        Column::Column(column) => cell.get_column(*column),
-                    })
-                    .collect()
+                    .zip(lhs.iter().zip(rhs))
+                    .map(|(key, (lhs, rhs))| key.compare(lhs, rhs))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+            for (_, group) in groups {
+                rows.extend(self.group_row(group)?);
+            }
+        }
+
+        let rows = if self.order_by.is_empty() {
+            rows
+        } else {
+            sort_rows(rows, &self.order_by)?
+        };
+        let mut seen = HashSet::new();
+        for row in rows
+            .into_iter()
+            .map(|mut row| {
+                row.truncate(self.visible_columns);
+                row
             })
-            .collect();
+            .filter(|row| match &self.distinct {
+                Some(collations) => seen.insert(group_key(row, collations)),
+                None => true,
+            })
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+        {
+            visit(row)?;
+        }
+        Ok(())
     }
 
-    pub fn from_select_and_table(
-        root_page: u64,
-        select: ParsedSelect,
-        table: Table,
-        table_index: Option<IndexData>,
+    /// Computes the columns of a finished group, `None` when it doesn't pass the HAVING clause.
+    fn group_row<R: Row>(&self, group: Group<R>) -> Result<Option<Vec<Value>>, ParsingError> {
+        let group = group.finish();
+        if let Some(having) = &self.having
+            && !having.matches(&group)?
+        {
+            return Ok(None);
+        }
+        self.columns
+            .iter()
+            .map(|column| column.evaluate(&group))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// Binds a parsed SELECT to the tables of its FROM clause, given in the same order, and
+    /// to the scalar functions of `functions`.
+    pub fn from_select(
+        mut select: ParsedSelect,
+        mut tables: Vec<SourceTable>,
+        functions: &FunctionRegistry,
     ) -> Result<SelectBuilder, ParsingError> {
-        let columns = select
+        if select.tables().count() != tables.len() {
+            return Err(ParsingError::InvalidStatement);
+        }
+        expand_stars(&mut select, &tables)?;
+        let references = select.tables().collect::<Vec<_>>();
+        let is_join = tables.len() > 1;
+        let mut binder = Binder::new(&tables, references, &select.columns, functions);
+        let offsets = binder.offsets.clone();
+
+        let is_aggregate = !select.group_by.is_empty()
+            || select.having.is_some()
+            || select.columns.iter().any(|column| match column {
+                ResultColumn::Expression { expression, .. } => expression.contains_aggregate(),
+                ResultColumn::All | ResultColumn::TableAll(_) => false,
+            });
+
+        let (mut columns, output_columns): (Vec<_>, Vec<_>) = select
             .columns
             .iter()
             .map(|column| match column {
-                Some(column) => table
-                    .get_column_by_name(column)
-                    .ok_or(ParsingError::InvalidStatement)
-                    .map(|value| match value {
-                        TableColumn::RowId(_) => Column::RowId,
-                        TableColumn::Column(index, _) => Column::Column(*index),
-                    }),
-                None => Ok(Column::Count),
+                ResultColumn::Expression {
+                    expression,
+                    alias,
+                    text,
+                } => {
+                    let bound = binder.bind(expression, true)?;
+                    let output_column = OutputColumn {
+                        name: match alias {
+                            Some(alias) => alias.clone(),
+                            None => binder.result_column_name(expression, text)?,
+                        },
+                        affinity: bound.affinity().unwrap_or(Affinity::Blob),
+                        collation: binder.result_collation(expression)?,
+                    };
+                    Ok((bound, output_column))
+                }
+                // Expanded before binding
+                ResultColumn::All | ResultColumn::TableAll(_) => {
+                    Err(ParsingError::InvalidStatement)
+                }
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Result<Vec<_>, ParsingError>>()?
+            .into_iter()
+            .unzip();
+        let visible_columns = columns.len();
+        let distinct = select
+            .distinct
+            .then(|| {
+                select
+                    .columns
+                    .iter()
+                    .map(|column| match column {
+                        ResultColumn::Expression { expression, .. } => {
+                            binder.value_collation(expression)
+                        }
+                        ResultColumn::All | ResultColumn::TableAll(_) => {
+                            Err(ParsingError::InvalidStatement)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
 
         let where_comps = select
             .where_comp
-            .map(|comp| Where::from_table(&comp, &table))
+            .as_ref()
+            .map(|comp| binder.bind(comp, false))
             .transpose()?;
 
-        let table_index = table_index
-            .map(|index_data| {
-                let where_value = where_comps.clone().ok_or(ParsingError::InvalidStatement);
-                let where_column = where_value
-                    .map(|where_column| match where_column.expression.column {
-                        WhereColumn::RowId => Ok((
-                            where_column.expression.op,
-                            where_column.expression.value,
-                            WhereColumn::RowId,
-                        )),
-                        WhereColumn::Column(index) => match &table.columns[index] {
-                            TableColumn::RowId(_) => Ok((
-                                where_column.expression.op,
-                                where_column.expression.value,
-                                WhereColumn::RowId,
-                            )),
-                            TableColumn::Column(_, b) => index_data
-                                .columns
-                                .iter()
-                                .position(|v| v.as_str() == b.as_str())
-                                .map(|index| {
-                                    (
-                                        where_column.expression.op,
-                                        where_column.expression.value,
-                                        WhereColumn::Column(index),
-                                    )
-                                })
-                                .ok_or(ParsingError::InvalidStatement),
-                        },
-                    })
-                    .flatten();
+        let joins_on = select
+            .joins
+            .iter()
+            .enumerate()
+            .map(|(position, join)| {
+                let Some(on) = &join.on else {
+                    return Ok(None);
+                };
+                let on = binder.bind(on, false)?;
+                if offsets
+                    .get(position + 2)
+                    .is_some_and(|next_table| last_column(&on) >= *next_table)
+                {
+                    return Err(ParsingError::Semantic(
+                        "ON clause references tables to its right".to_string(),
+                    ));
+                }
+                Ok(Some(on))
+            })
+            .collect::<Result<Vec<_>, ParsingError>>()?;
 
-                where_column.map(|column| (index_data.root_page, column))
+        let group_by = select
+            .group_by
+            .iter()
+            .map(|term| {
+                if term.contains_aggregate() {
+                    return Err(ParsingError::Semantic(
+                        "aggregate functions are not allowed in the GROUP BY clause".to_string(),
+                    ));
+                }
+                Ok(SortKey {
+                    expression: binder.bind(term, false)?,
+                    collation: binder.value_collation(term)?,
+                    descending: false,
+                    nulls_first: true,
+                })
             })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let having = select
+            .having
+            .as_ref()
+            .map(|comp| binder.bind(comp, true))
             .transpose()?;
 
-        return Ok(SelectBuilder {
+        // ORDER BY of aggregate queries reads the rows computed for the groups, so its terms
+        // are computed in hidden columns when they aren't result columns.
+        let mut row_terms = select
+            .columns
+            .iter()
+            .map(|column| match column {
+                ResultColumn::Expression { expression, .. } => Some(expression),
+                ResultColumn::All | ResultColumn::TableAll(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let order_by = select
+            .order_by
+            .iter()
+            .map(|term| {
+                let expression = if is_aggregate {
+                    let position = match row_terms
+                        .iter()
+                        .position(|row_term| *row_term == Some(&term.expression))
+                    {
+                        Some(position) => position,
+                        None => {
+                            columns.push(binder.bind(&term.expression, true)?);
+                            row_terms.push(Some(&term.expression));
+                            row_terms.len() - 1
+                        }
+                    };
+                    Expression::Column(WhereColumn::Column(position), Affinity::Blob)
+                } else {
+                    binder.bind(&term.expression, false)?
+                };
+                let collation = match &term.collation {
+                    Some(name) => collation(name)?,
+                    None => binder
+                        .declared_collation(&term.expression)?
+                        .unwrap_or_default(),
+                };
+                Ok(SortKey {
+                    expression,
+                    collation,
+                    descending: term.order == SortOrder::Desc,
+                    nulls_first: term.nulls_first(),
+                })
+            })
+            .collect::<Result<Vec<_>, ParsingError>>()?;
+        let aggregates = binder.aggregates;
+
+        // Aggregate queries want the rows in the order of their GROUP BY columns, in either
+        // direction, to aggregate each group as it's read.
+        let grouping_terms = select
+            .group_by
+            .iter()
+            .map(|expression| OrderingTerm {
+                expression: expression.clone(),
+                collation: None,
+                order: SortOrder::Asc,
+                nulls: None,
+            })
+            .collect::<Vec<_>>();
+        let scan_order = if is_aggregate {
+            &grouping_terms
+        } else {
+            &select.order_by
+        };
+
+        // A joined table is looked up by rowid or through an index when an equality of its ON
+        // clause, or of the WHERE clause for inner joins, compares one of its columns to one of
+        // the tables before it.
+        let lookups = select
+            .joins
+            .iter()
+            .zip(&joins_on)
+            .enumerate()
+            .map(|(position, (join, on))| {
+                let mut equalities = vec![];
+                if let Some(on) = on {
+                    collect_equalities(on, &mut equalities);
+                }
+                if let (Some(comp), false) = (&where_comps, join.kind == JoinKind::Left) {
+                    collect_equalities(comp, &mut equalities);
+                }
+                let source = &tables[position + 1];
+                join_lookup(&equalities, offsets[position + 1], source)
+            })
+            .collect::<Vec<_>>();
+
+        // Conditions of the WHERE clause of a join are checked as soon as the tables they read
+        // are joined, the WHERE clause keeping those on the first table.
+        let mut join_filters: Vec<Option<Expression>> = select.joins.iter().map(|_| None).collect();
+        let where_comps = match where_comps {
+            Some(comp) if is_join => {
+                let mut conjuncts = vec![];
+                comp.into_conjuncts(&mut conjuncts);
+                let mut first_table_comps = None;
+                for conjunct in conjuncts {
+                    let table = offsets
+                        .iter()
+                        .rposition(|offset| *offset <= last_column(&conjunct))
+                        .unwrap_or(0);
+                    let filter = match table {
+                        0 => &mut first_table_comps,
+                        table => &mut join_filters[table - 1],
+                    };
+                    *filter = Some(match filter.take() {
+                        Some(filter) => filter.and(conjunct),
+                        None => conjunct,
+                    });
+                }
+                first_table_comps
+            }
+            where_comps => where_comps,
+        };
+
+        let table = &tables[0].table;
+
+        // The first table of a join is read in full, joined rows being sorted afterwards anyway.
+        let access_path = if is_join {
+            AccessPath::FullScan(None)
+        } else {
+            plan_access_path(where_comps.as_ref(), &tables[0], scan_order)
+        };
+        let table_columns = table.record_affinities();
+        let table_defaults = record_defaults(table, functions)?;
+
+        let (table_index, rowids, index_order) = match access_path {
+            AccessPath::FullScan(index_order) => (None, None, index_order),
+            AccessPath::Rowids(rowids, index_order) => (None, Some(rowids), index_order),
+            AccessPath::Index(position, range, index_order) => (
+                Some(IndexScan {
+                    index: tables[0].indexes.swap_remove(position),
+                    range,
+                }),
+                None,
+                index_order,
+            ),
+        };
+        // Any direction yields the groups one after the other, so the rows are read forwards.
+        let index_order = index_order.map(|index_order| {
+            if is_aggregate {
+                SortOrder::Asc
+            } else {
+                index_order
+            }
+        });
+
+        let mut tables = tables.into_iter();
+        let (root_page, table_view) = tables.next().map_or((0, None), |source| {
+            (source.root_page, source.view.map(Box::new))
+        });
+        let joins = tables
+            .zip(&select.joins)
+            .zip(joins_on)
+            .zip(join_filters)
+            .zip(lookups)
+            .map(|((((mut source, join), on), filter), lookup)| {
+                Ok(Join {
+                    table: source.root_page,
+                    view: source.view,
+                    columns: source.table.record_affinities(),
+                    defaults: record_defaults(&source.table, functions)?,
+                    left: join.kind == JoinKind::Left,
+                    on,
+                    filter,
+                    lookup: lookup.map(|lookup| match lookup {
+                        PlannedLookup::RowId(outer) => JoinLookup::RowId(outer),
+                        PlannedLookup::Index(position, outer, affinity) => {
+                            JoinLookup::Index(source.indexes.swap_remove(position), outer, affinity)
+                        }
+                    }),
+                })
+            })
+            .collect::<Result<_, ParsingError>>()?;
+
+        let select_builder = SelectBuilder {
             index: table_index,
+            rowids,
             table: root_page,
+            table_view,
+            joins,
+            table_columns,
+            table_defaults,
             columns,
-            where_comps: where_comps,
-        });
+            visible_columns,
+            distinct,
+            output_columns,
+            aggregates,
+            where_comps,
+            group_by,
+            having,
+            order_by,
+            index_order,
+            limit: None,
+            offset: 0,
+        };
+
+        Ok(match (select.limit, select.offset) {
+            (None, None) => select_builder,
+            (limit, offset) => select_builder.limit(limit.unwrap_or(-1), offset.unwrap_or(0)),
+        })
+    }
+}
+
+/// Cursor over the entries of `index`, which is the b-tree of the table rooted at `table` for
+/// the primary key of a WITHOUT ROWID table.
+fn index_cursor<'a>(
+    sqlite_reader: &'a SqliteReader,
+    index: &IndexData,
+    table: u64,
+) -> IndexCursor<'a> {
+    let entries = sqlite_reader.index_cursor(index);
+    if index.root_page == table {
+        entries.without_rowid()
+    } else {
+        entries
+    }
+}
+
+/// Values that the columns of `table` read in rows written before the column was added with
+/// `ALTER TABLE ADD COLUMN`, by position in records: its DEFAULT with its affinity applied,
+/// or NULL.
+fn record_defaults(
+    table: &Table,
+    functions: &FunctionRegistry,
+) -> Result<Rc<[Value]>, ParsingError> {
+    table
+        .columns
+        .iter()
+        .filter(|column| column.storage != ColumnStorage::Virtual)
+        .map(|column| {
+            let Some(default) = &column.default else {
+                return Ok(Value::Null);
+            };
+            // SQLite only accepts constant defaults for added columns
+            let value = Binder::new(&[], vec![], &[], functions)
+                .bind(default, false)?
+                .evaluate(&Vec::new())?;
+            Ok(value.apply_affinity(column.affinity()))
+        })
+        .collect()
+}
+
+/// Replaces `*` and `table.*` among the result columns by the columns of the tables, in the
+/// order they are declared, and then the positions of result columns that came after them in
+/// GROUP BY and ORDER BY by their expressions.
+fn expand_stars(select: &mut ParsedSelect, tables: &[SourceTable]) -> Result<(), ParsingError> {
+    if !select.columns.iter().any(ResultColumn::is_star) {
+        return Ok(());
+    }
+
+    let references = select.tables().cloned().collect::<Vec<_>>();
+    let sources = tables.iter().zip(&references);
+    let table_columns = |(source, reference): (&SourceTable, &TableReference)| {
+        source
+            .table
+            .columns
+            .iter()
+            .map(|column| ResultColumn::Expression {
+                expression: ParsedExpression::Column(ColumnName {
+                    table: Some(reference.visible_name().to_string()),
+                    name: column.name.clone(),
+                }),
+                alias: None,
+                text: column.name.clone(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut columns = vec![];
+    for column in std::mem::take(&mut select.columns) {
+        match column {
+            ResultColumn::All => columns.extend(sources.clone().flat_map(table_columns)),
+            ResultColumn::TableAll(name) => {
+                let Some(source) = sources
+                    .clone()
+                    .find(|(_, reference)| reference.visible_name().eq_ignore_ascii_case(&name))
+                else {
+                    return Err(ParsingError::Semantic(format!("no such table: {name}")));
+                };
+                columns.extend(table_columns(source));
+            }
+            column => columns.push(column),
+        }
+    }
+
+    // The parser leaves the positions it couldn't resolve as integers
+    let resolve_position = |clause: &str, term: &mut ParsedExpression| {
+        let ParsedExpression::Literal(Value::Integer(position)) = term else {
+            return Ok(());
+        };
+        match usize::try_from(*position)
+            .ok()
+            .and_then(|position| position.checked_sub(1))
+            .and_then(|index| columns.get(index))
+        {
+            Some(ResultColumn::Expression { expression, .. }) => {
+                *term = expression.clone();
+                Ok(())
+            }
+            _ => Err(ParsingError::Semantic(format!(
+                "{clause} term {position} does not match a result column"
+            ))),
+        }
+    };
+    for term in &mut select.group_by {
+        resolve_position("GROUP BY", term)?;
+    }
+    for term in &mut select.order_by {
+        resolve_position("ORDER BY", &mut term.expression)?;
+    }
+    select.columns = columns;
+    Ok(())
+}
+
+/// Position of the MIN or MAX when it's the only one among the aggregates, since the columns
+/// that aren't aggregates are then read from the row holding its result.
+fn extreme_aggregate(aggregates: &[AggregateCall]) -> Option<usize> {
+    let extremes = aggregates
+        .iter()
+        .enumerate()
+        .filter(|(_, call)| {
+            matches!(
+                call.function,
+                AggregateFunction::Min | AggregateFunction::Max
+            )
+        })
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+    match extremes[..] {
+        [position] => Some(position),
+        _ => None,
+    }
+}
+
+/// Aggregates of one group while its rows are fed. Columns that aren't aggregates are read from
+/// the row holding the result of a lone MIN or MAX, like SQLite does, and from the first row
+/// otherwise or while the MIN or MAX has only seen NULLs.
+struct Group<R> {
+    accumulators: Vec<Accumulator>,
+    row: Option<R>,
+}
+
+impl<R: Row + Clone> Group<R> {
+    fn new(aggregates: &[AggregateCall]) -> Self {
+        let accumulators = aggregates
+            .iter()
+            .map(|call| {
+                Accumulator::new(
+                    call.function,
+                    call.distinct,
+                    call.separator.clone(),
+                    call.collation,
+                )
+            })
+            .collect();
+        Group {
+            accumulators,
+            row: None,
+        }
+    }
+
+    fn step(
+        &mut self,
+        aggregates: &[AggregateCall],
+        extreme_aggregate: Option<usize>,
+        cell: &R,
+    ) -> Result<(), ParsingError> {
+        let mut is_bare_row = self.row.is_none();
+        for (position, (call, accumulator)) in
+            aggregates.iter().zip(&mut self.accumulators).enumerate()
+        {
+            let value = call
+                .argument
+                .as_ref()
+                .map(|argument| argument.evaluate(cell))
+                .transpose()?;
+            if accumulator.step(value)? && extreme_aggregate == Some(position) {
+                is_bare_row = true;
+            }
+        }
+
+        if is_bare_row {
+            self.row = Some(cell.clone());
+        }
+        Ok(())
+    }
+}
+
+impl<R> Group<R> {
+    fn finish(self) -> GroupRow<R> {
+        GroupRow {
+            row: self.row,
+            aggregates: self
+                .accumulators
+                .into_iter()
+                .map(Accumulator::finish)
+                .collect(),
+        }
+    }
+}
+
+/// A finished group, whose columns are read from its bare row, NULL when it's empty.
+struct GroupRow<R> {
+    row: Option<R>,
+    aggregates: Vec<Value>,
+}
+
+impl<R: Row> Row for GroupRow<R> {
+    fn read(&self, column: &WhereColumn) -> Result<Value, ParsingError> {
+        match &self.row {
+            Some(row) => row.read(column),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn read_with_affinity(
+        &self,
+        column: &WhereColumn,
+        affinity: Affinity,
+    ) -> Result<Value, ParsingError> {
+        match &self.row {
+            Some(row) => row.read_with_affinity(column, affinity),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn aggregate(&self, position: usize) -> Result<Value, ParsingError> {
+        self.aggregates
+            .get(position)
+            .cloned()
+            .ok_or(ParsingError::InvalidStatement)
     }
 }
@@ -1,93 +1,202 @@
 use std::fmt::Display;
 
-use crate::{parsing_error::ParsingError, parsing_utils::find_keyword, select_builder::Op};
+use crate::{
+    expression_parser::{ColumnName, ParsedExpression},
+    lexer::TokenKind,
+    parser::{Parser, is_reserved},
+    parsing_error::ParsingError,
+    value::Value,
+};
 
 const WHERE_KEYWORD: &str = "WHERE";
 const SELECT_KEYWORD: &str = "SELECT";
+const DISTINCT_KEYWORD: &str = "DISTINCT";
+const ALL_KEYWORD: &str = "ALL";
 const FROM_KEYWORD: &str = "FROM";
-const OR_KEYWORD: &str = "OR";
-const AND_KEYWORD: &str = "AND";
+const JOIN_KEYWORD: &str = "JOIN";
+const ON_KEYWORD: &str = "ON";
+const GROUP_KEYWORD: &str = "GROUP";
+const HAVING_KEYWORD: &str = "HAVING";
+const ORDER_KEYWORD: &str = "ORDER";
+const BY_KEYWORD: &str = "BY";
+const LIMIT_KEYWORD: &str = "LIMIT";
+const OFFSET_KEYWORD: &str = "OFFSET";
+
+/// Keywords that can follow a table name in the FROM clause, and so can't be its alias.
+const TABLE_ALIAS_STOP_KEYWORDS: [&str; 13] = [
+    "WHERE", "GROUP", "HAVING", "ORDER", "LIMIT", "JOIN", "INNER", "LEFT", "CROSS", "NATURAL",
+    "OUTER", "ON", "USING",
+];
+
+/// Keywords that aren't reserved but still can't be the alias of a result column without AS.
+const COLUMN_ALIAS_STOP_KEYWORDS: [&str; 12] = [
+    "CROSS", "FULL", "GLOB", "INDEXED", "INNER", "LEFT", "LIKE", "MATCH", "NATURAL", "OUTER",
+    "REGEXP", "RIGHT",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
 
-pub fn is_quoted(value: &str) -> bool {
-    return value.starts_with("\"") && value.ends_with("\"");
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NullsOrder {
+    First,
+    Last,
 }
 
-pub fn quoted<T: AsRef<str>>(value: T) -> String {
-    let v = value.as_ref();
-    if v.starts_with("\"") && v.ends_with("\"") {
-        return v.to_string();
+/// One key of an ORDER BY clause.
+#[derive(Clone, Debug)]
+pub struct OrderingTerm {
+    pub expression: ParsedExpression,
+    pub collation: Option<String>,
+    pub order: SortOrder,
+    pub nulls: Option<NullsOrder>,
+}
+
+impl OrderingTerm {
+    /// Whether NULLs come first, SQLite defaults to NULLS FIRST for ASC and NULLS LAST for DESC.
+    pub fn nulls_first(&self) -> bool {
+        match self.nulls {
+            Some(nulls) => nulls == NullsOrder::First,
+            None => self.order == SortOrder::Asc,
+        }
     }
-    if v.starts_with("'") && v.ends_with("'") {
-        return format!("\"{}\"", &v[1..v.len() - 1]);
+}
+
+impl Display for OrderingTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.expression.fmt(f)?;
+        if let Some(collation) = &self.collation {
+            f.write_fmt(format_args!(" COLLATE {collation}"))?;
+        }
+        if self.order == SortOrder::Desc {
+            f.write_str(" DESC")?;
+        }
+        match self.nulls {
+            Some(NullsOrder::First) => f.write_str(" NULLS FIRST"),
+            Some(NullsOrder::Last) => f.write_str(" NULLS LAST"),
+            None => Ok(()),
+        }
     }
-    return format!("\"{v}\"");
 }
 
-pub fn parse_value(value: &str) -> String {
-    if value.starts_with("\"") || value.starts_with("\'") {
-        return quoted(value);
-    };
-    return value.to_string();
+/// A column of the result: `*`, `table.*`, or an expression with an optional alias.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResultColumn {
+    All,
+    /// The columns of the table of the FROM clause with this name or alias.
+    TableAll(String),
+    Expression {
+        expression: ParsedExpression,
+        alias: Option<String>,
+        /// The expression as written, which names the column when it has no alias.
+        text: String,
+    },
 }
 
-pub enum ParsedCombinator {
-    And(Box<ParsedWhere>),
-    Or(Box<ParsedWhere>),
+impl ResultColumn {
+    /// Whether the column stands for the columns of tables.
+    pub fn is_star(&self) -> bool {
+        matches!(self, ResultColumn::All | ResultColumn::TableAll(_))
+    }
 }
 
-impl ParsedCombinator {
-    pub fn get_where(&self) -> &ParsedWhere {
+impl Display for ResultColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParsedCombinator::And(parsed_where) => &parsed_where,
-            ParsedCombinator::Or(parsed_where) => &parsed_where,
+            ResultColumn::All => f.write_str("*"),
+            ResultColumn::TableAll(table) => f.write_fmt(format_args!("{table}.*")),
+            ResultColumn::Expression {
+                expression, alias, ..
+            } => {
+                expression.fmt(f)?;
+                if let Some(alias) = alias {
+                    f.write_fmt(format_args!(" AS {alias}"))?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
-pub struct ParsedExpression {
-    pub column: String,
-    pub op: Op,
-    pub value: String,
+/// A table of the FROM clause.
+#[derive(Clone, Debug)]
+pub struct TableReference {
+    pub name: String,
+    pub alias: Option<String>,
 }
 
-pub struct ParsedWhere {
-    pub expression: ParsedExpression,
-    pub combinator: Option<ParsedCombinator>,
+impl TableReference {
+    /// Name the columns of the table are qualified with.
+    pub fn visible_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
 }
 
-pub struct ParsedSelect {
-    pub table_name: String,
-    pub columns: Vec<Option<String>>,
-    pub where_comp: Option<ParsedWhere>,
+impl Display for TableReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(alias) = &self.alias {
+            f.write_fmt(format_args!(" AS {alias}"))?;
+        }
+        Ok(())
+    }
 }
 
-impl ParsedWhere {
-    pub fn get_columns(&self) -> Vec<String> {
-        let mut return_val = vec![self.expression.column.clone()];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    /// `a, b`
+    Comma,
+    /// `a [INNER | CROSS] JOIN b`
+    Inner,
+    /// `a LEFT [OUTER] JOIN b`
+    Left,
+}
 
-        match &self.combinator {
-            Some(comp) => return_val.append(&mut comp.get_where().get_columns()),
-            None => {},
-        }
+/// A table joined to the ones before it in the FROM clause.
+#[derive(Clone, Debug)]
+pub struct ParsedJoin {
+    pub kind: JoinKind,
+    pub table: TableReference,
+    pub on: Option<ParsedExpression>,
+}
 
-        return return_val;
+impl Display for ParsedJoin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            JoinKind::Comma => f.write_str(", ")?,
+            JoinKind::Inner => f.write_str(" JOIN ")?,
+            JoinKind::Left => f.write_str(" LEFT JOIN ")?,
+        }
+        self.table.fmt(f)?;
+        if let Some(on) = &self.on {
+            f.write_fmt(format_args!(" ON {on}"))?;
+        }
+        Ok(())
     }
 }
 
-impl Display for ParsedWhere {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "{} {} {}",
-            self.expression.column,
-            self.expression.op.as_str(),
-            self.expression.value
-        ))?;
+#[derive(Clone, Debug)]
+pub struct ParsedSelect {
+    /// Whether duplicate rows are dropped, for `SELECT DISTINCT`.
+    pub distinct: bool,
+    pub table: TableReference,
+    pub joins: Vec<ParsedJoin>,
+    pub columns: Vec<ResultColumn>,
+    pub where_comp: Option<ParsedExpression>,
+    pub group_by: Vec<ParsedExpression>,
+    pub having: Option<ParsedExpression>,
+    pub order_by: Vec<OrderingTerm>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
 
-        match &self.combinator {
-            Some(ParsedCombinator::And(value)) => f.write_fmt(format_args!(" AND {}", value)),
-            Some(ParsedCombinator::Or(value)) => f.write_fmt(format_args!(" OR {}", value)),
-            None => Ok(()),
-        }
+impl ParsedSelect {
+    /// Tables of the FROM clause, in order.
+    pub fn tables(&self) -> impl Iterator<Item = &TableReference> {
+        std::iter::once(&self.table).chain(self.joins.iter().map(|join| &join.table))
     }
 }
 
@@ -96,167 +205,343 @@ impl Display for ParsedSelect {
         let columns = self
             .columns
             .iter()
-            .map(|c| match c {
-                None => "COUNT(*)".to_string(),
-                Some(v) => v.to_string(),
-            })
+            .map(|column| column.to_string())
             .collect::<Vec<_>>()
             .join(", ");
-        f.write_fmt(format_args!("SELECT {} FROM {}", columns, self.table_name))?;
-        match &self.where_comp {
-            Some(where_v) => f.write_fmt(format_args!(" WHERE {}", where_v)),
-            None => return Ok(()),
+        let distinct = if self.distinct { "DISTINCT " } else { "" };
+        f.write_fmt(format_args!(
+            "SELECT {distinct}{} FROM {}",
+            columns, self.table
+        ))?;
+        for join in &self.joins {
+            join.fmt(f)?;
+        }
+        if let Some(where_v) = &self.where_comp {
+            f.write_fmt(format_args!(" WHERE {}", where_v))?;
+        }
+        if !self.group_by.is_empty() {
+            let columns = self
+                .group_by
+                .iter()
+                .map(|column| column.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            f.write_fmt(format_args!(" GROUP BY {columns}"))?;
+        }
+        if let Some(having) = &self.having {
+            f.write_fmt(format_args!(" HAVING {having}"))?;
         }
+        if !self.order_by.is_empty() {
+            let terms = self
+                .order_by
+                .iter()
+                .map(|term| term.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            f.write_fmt(format_args!(" ORDER BY {terms}"))?;
+        }
+        if let Some(limit) = self.limit {
+            f.write_fmt(format_args!(" LIMIT {limit}"))?;
+        }
+        if let Some(offset) = self.offset {
+            f.write_fmt(format_args!(" OFFSET {offset}"))?;
+        }
+        Ok(())
     }
 }
 
-pub fn parse_comma_separated_after(
-    select: &str,
-    keyword: &str,
-    index: usize,
-    limit: Option<usize>,
-) -> Vec<String> {
-    let begin_index = index + keyword.len();
-    let value = match limit {
-        Some(end) => &select[begin_index..end],
-        None => &select[begin_index..],
-    };
-    return value
-        .split(",")
-        .map(|value| value.trim().to_string())
-        .collect();
-}
+impl Parser<'_> {
+    /// `SELECT [DISTINCT | ALL] result_column [, ...] FROM table [join ...] [WHERE where_clause]
+    /// [GROUP BY ...] [HAVING ...] [ORDER BY ...] [LIMIT ...]`
+    pub fn parse_select(&mut self) -> Result<ParsedSelect, ParsingError> {
+        self.expect_keyword(SELECT_KEYWORD)?;
+        let distinct = self.consume_keyword(DISTINCT_KEYWORD);
+        if !distinct {
+            self.consume_keyword(ALL_KEYWORD);
+        }
+        let mut columns = vec![self.parse_result_column()?];
+        while self.consume(&TokenKind::Comma) {
+            columns.push(self.parse_result_column()?);
+        }
 
-/*
- * (.1 == true => And)
- * (.1 == false => Or)
- */
-pub fn find_next_where_comp(select: &str, index: usize) -> Option<(usize, bool)> {
-    let and_index = find_keyword(&select[index..], AND_KEYWORD);
-    let or_index = find_keyword(&select[index..], OR_KEYWORD);
-
-    match (and_index, or_index) {
-        (Some(and_index), Some(or_index)) => Some((and_index.min(or_index), and_index < or_index)),
-        (None, Some(or_index)) => Some((or_index, false)),
-        (Some(and_index), None) => Some((and_index, true)),
-        (None, None) => None,
-    }
-}
+        self.expect_keyword(FROM_KEYWORD)?;
+        let table = self.parse_table_reference()?;
+        let mut joins = vec![];
+        while let Some(join) = self.parse_join()? {
+            joins.push(join);
+        }
 
-pub fn parse_where_cmp(select: &str) -> Result<ParsedWhere, ParsingError> {
-    let select = select.trim();
-    let tokens = select
-        .split(" ")
-        .filter(|entry| !entry.is_empty())
-        .collect::<Vec<_>>();
-    assert!(tokens.len() >= 3);
-
-    let column = tokens[0].trim();
-    let op = match tokens[1] {
-        "=" => Op::Eq,
-        ">" => Op::Gt,
-        ">=" => Op::GtEq,
-        "<" => Op::Lt,
-        "<=" => Op::LtEq,
-        _ => {
-            return {
-                eprintln!("Coudln't find op");
-                Err(ParsingError::InvalidStatement)
-            };
+        let where_comp = if self.consume_keyword(WHERE_KEYWORD) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        let mut group_by = vec![];
+        if self.consume_keywords(&[GROUP_KEYWORD, BY_KEYWORD]) {
+            group_by.push(self.parse_grouping_term(&columns)?);
+            while self.consume(&TokenKind::Comma) {
+                group_by.push(self.parse_grouping_term(&columns)?);
+            }
         }
-    };
-    let op_index = match select.find(op.as_str()) {
-        Some(value) => value + op.as_str().len(),
-        None => {
-            eprintln!("Coudln't find op (second time)");
-            return Err(ParsingError::InvalidStatement);
+
+        let having = if self.consume_keyword(HAVING_KEYWORD) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        let mut order_by = vec![];
+        if self.consume_keywords(&[ORDER_KEYWORD, BY_KEYWORD]) {
+            order_by.push(self.parse_ordering_term(&columns)?);
+            while self.consume(&TokenKind::Comma) {
+                order_by.push(self.parse_ordering_term(&columns)?);
+            }
+        }
+
+        let (mut limit, mut offset) = (None, None);
+        if self.consume_keyword(LIMIT_KEYWORD) {
+            limit = Some(self.parse_integer()?);
+            if self.consume_keyword(OFFSET_KEYWORD) {
+                offset = Some(self.parse_integer()?);
+            } else if self.consume(&TokenKind::Comma) {
+                // `LIMIT offset, count`
+                offset = limit;
+                limit = Some(self.parse_integer()?);
+            }
         }
-    };
-
-    let value = parse_value(&select[op_index..].trim());
-
-    return Ok(ParsedWhere {
-        expression: ParsedExpression {
-            column: column.to_string(),
-            op,
-            value: value.to_string(),
-        },
-        combinator: None,
-    });
-}
 
-pub fn parse_where(select: &str, index: usize) -> Result<ParsedWhere, ParsingError> {
-    let next_cmp = find_next_where_comp(select, index);
-    match next_cmp {
-        Some((end, /* is_and = */ true)) => Ok(ParsedWhere {
-            combinator: Some(ParsedCombinator::And(Box::new(parse_where(
-                select,
-                index + end + AND_KEYWORD.len(),
-            )?))),
-            ..parse_where_cmp(&select[index..(index + end)])?
-        }),
-        Some((end, /* is_and = */ false)) => Ok(ParsedWhere {
-            combinator: Some(ParsedCombinator::Or(Box::new(parse_where(
-                select,
-                index + end + OR_KEYWORD.len(),
-            )?))),
-            ..parse_where_cmp(&select[index..(index + end)])?
-        }),
-        None => parse_where_cmp(&select[index..]),
+        Ok(ParsedSelect {
+            distinct,
+            table,
+            joins,
+            columns,
+            where_comp,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
     }
-}
 
-pub fn parse_select(select: &str) -> Result<ParsedSelect, ParsingError> {
-    let select = select.trim_start();
-    let select_keyword = find_keyword(select, SELECT_KEYWORD);
-    let from_keyword = find_keyword(select, FROM_KEYWORD);
-    let where_keyword = find_keyword(select, WHERE_KEYWORD);
-
-    let (Some(select_keyword), Some(from_keyword)) = (select_keyword, from_keyword) else {
-        eprintln!("Couldn't find SELECT or FROM");
-        return Err(ParsingError::InvalidStatement);
-    };
-
-    if !(select_keyword < from_keyword) {
-        eprintln!("Select keyword should always be before from");
-        return Err(ParsingError::InvalidStatement);
+    /// `table_name [[AS] alias]`
+    fn parse_table_reference(&mut self) -> Result<TableReference, ParsingError> {
+        let name = self.parse_qualified_name()?;
+        let alias = if self.consume_keyword("AS")
+            || matches!(&self.peek().kind, TokenKind::Identifier(word)
+                if !TABLE_ALIAS_STOP_KEYWORDS.iter().any(|keyword| word.eq_ignore_ascii_case(keyword)))
+            || matches!(self.peek().kind, TokenKind::QuotedIdentifier(_))
+        {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(TableReference { name, alias })
     }
-    if let Some(where_keyword) = where_keyword
-        && !(from_keyword < where_keyword)
-    {
-        eprintln!("Where keyword should always be after from");
-        return Err(ParsingError::InvalidStatement);
+
+    /// `, table` or `[INNER | CROSS | LEFT [OUTER]] JOIN table [ON where_clause]`, `None` when
+    /// the FROM clause doesn't go on.
+    fn parse_join(&mut self) -> Result<Option<ParsedJoin>, ParsingError> {
+        let kind = if self.consume(&TokenKind::Comma) {
+            JoinKind::Comma
+        } else if self.consume_keywords(&["LEFT", "OUTER", JOIN_KEYWORD])
+            || self.consume_keywords(&["LEFT", JOIN_KEYWORD])
+        {
+            JoinKind::Left
+        } else if self.consume_keywords(&["INNER", JOIN_KEYWORD])
+            || self.consume_keywords(&["CROSS", JOIN_KEYWORD])
+            || self.consume_keyword(JOIN_KEYWORD)
+        {
+            JoinKind::Inner
+        } else {
+            return Ok(None);
+        };
+
+        let table = self.parse_table_reference()?;
+        let on = if kind != JoinKind::Comma && self.consume_keyword(ON_KEYWORD) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        Ok(Some(ParsedJoin { kind, table, on }))
     }
 
-    let column_names: Vec<Option<String>> =
-        parse_comma_separated_after(select, SELECT_KEYWORD, select_keyword, Some(from_keyword))
-            .iter()
-            .map(|column| match column.to_uppercase().as_str() {
-                "COUNT(*)" => None,
-                _ => Some(column.clone()),
-            })
-            .collect();
-    let table_name = parse_comma_separated_after(select, FROM_KEYWORD, from_keyword, where_keyword);
-
-    let where_cmp = where_keyword
-        .map(|index| parse_where(select, index + WHERE_KEYWORD.len()))
-        .transpose()?;
-
-    if table_name.len() > 1 {
-        eprintln!("Can't currently handle more than one table");
-        return Err(ParsingError::InvalidStatement);
+    fn parse_integer(&mut self) -> Result<i64, ParsingError> {
+        let token = self.peek().clone();
+        match self.parse_literal()? {
+            Value::Integer(value) => Ok(value),
+            _ => Err(self.error_at(&token, "datatype mismatch, expected an integer")),
+        }
     }
 
-    if column_names.is_empty() {
-        eprintln!("Couldn't find any column names");
-        return Err(ParsingError::InvalidStatement);
+    /// A term of a GROUP BY clause, which can also be the 1-based position of a result
+    /// column.
+    fn parse_grouping_term(
+        &mut self,
+        result_columns: &[ResultColumn],
+    ) -> Result<ParsedExpression, ParsingError> {
+        let TokenKind::Integer(position) = self.peek().kind else {
+            return self.parse_expression();
+        };
+        let expression = self.result_column_at(result_columns, position, "GROUP BY")?;
+        if expression.contains_aggregate() {
+            return Err(self.error("aggregate functions are not allowed in the GROUP BY clause"));
+        }
+        self.advance();
+        Ok(expression)
     }
 
-    let table_name = table_name[0].clone();
+    /// Expression of the result column at a 1-based `position`. Past a `*`, the position is
+    /// kept as an integer literal until the tables give the columns the `*` stands for.
+    fn result_column_at(
+        &self,
+        result_columns: &[ResultColumn],
+        position: i64,
+        clause: &str,
+    ) -> Result<ParsedExpression, ParsingError> {
+        let index = usize::try_from(position)
+            .ok()
+            .and_then(|position| position.checked_sub(1));
+        if let Some(index) = index
+            && result_columns
+                .iter()
+                .take(index + 1)
+                .any(ResultColumn::is_star)
+        {
+            return Ok(ParsedExpression::Literal(Value::Integer(position)));
+        }
+        match index.and_then(|index| result_columns.get(index)) {
+            Some(ResultColumn::Expression { expression, .. }) => Ok(expression.clone()),
+            _ => Err(self.error(format!(
+                "{clause} term {position} does not match a result column"
+            ))),
+        }
+    }
+
+    /// `expr [COLLATE name] [ASC | DESC] [NULLS FIRST | NULLS LAST]`, where the term can
+    /// also be the 1-based position or the alias of a result column.
+    fn parse_ordering_term(
+        &mut self,
+        result_columns: &[ResultColumn],
+    ) -> Result<OrderingTerm, ParsingError> {
+        let expression = match self.peek().kind {
+            TokenKind::Integer(position) => {
+                let expression = self.result_column_at(result_columns, position, "ORDER BY")?;
+                self.advance();
+                expression
+            }
+            _ => self.parse_expression()?,
+        };
+        let (expression, collation) = match expression {
+            ParsedExpression::Collate(expression, collation) => (*expression, Some(collation)),
+            expression => (expression, None),
+        };
+        // Aliases of result columns take precedence over the columns of the tables
+        let expression = match &expression {
+            ParsedExpression::Column(ColumnName { table: None, name }) => result_columns
+                .iter()
+                .find_map(|column| match column {
+                    ResultColumn::Expression {
+                        expression,
+                        alias: Some(alias),
+                        ..
+                    } if alias.eq_ignore_ascii_case(name) => Some(expression.clone()),
+                    _ => None,
+                })
+                .unwrap_or(expression),
+            _ => expression,
+        };
+
+        let order = if self.consume_keyword("DESC") {
+            SortOrder::Desc
+        } else {
+            self.consume_keyword("ASC");
+            SortOrder::Asc
+        };
+
+        let nulls = if self.consume_keywords(&["NULLS", "FIRST"]) {
+            Some(NullsOrder::First)
+        } else if self.consume_keywords(&["NULLS", "LAST"]) {
+            Some(NullsOrder::Last)
+        } else {
+            None
+        };
+
+        Ok(OrderingTerm {
+            expression,
+            collation,
+            order,
+            nulls,
+        })
+    }
+
+    /// `*`, `table.*` or `expr [[AS] alias]`
+    fn parse_result_column(&mut self) -> Result<ResultColumn, ParsingError> {
+        if self.consume(&TokenKind::Star) {
+            return Ok(ResultColumn::All);
+        }
+        if matches!(
+            self.peek().kind,
+            TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_)
+        ) && self.peek_nth(1).kind == TokenKind::Dot
+            && self.peek_nth(2).kind == TokenKind::Star
+        {
+            let table = self.parse_identifier()?;
+            self.advance();
+            self.advance();
+            return Ok(ResultColumn::TableAll(table));
+        }
+        let begin = self.mark();
+        let expression = self.parse_expression()?;
+        let text = self.source_since(begin).to_string();
+        let alias = if self.consume_keyword("AS")
+            || matches!(&self.peek().kind, TokenKind::Identifier(word)
+                if !is_reserved(word)
+                    && !COLUMN_ALIAS_STOP_KEYWORDS
+                        .iter()
+                        .any(|keyword| word.eq_ignore_ascii_case(keyword)))
+            || matches!(self.peek().kind, TokenKind::QuotedIdentifier(_))
+        {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(ResultColumn::Expression {
+            expression,
+            alias,
+            text,
+        })
+    }
+}
+
+pub fn parse_select(select: &str) -> Result<ParsedSelect, ParsingError> {
+    let mut parser = Parser::new(select)?;
+    let select = parser.parse_select()?;
+    parser.expect_end()?;
+    Ok(select)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    return Ok(ParsedSelect {
-        table_name,
-        columns: column_names,
-        where_comp: where_cmp,
-    });
+    #[test]
+    fn distinct_and_all_are_read_before_the_result_columns() {
+        let select = parse_select("SELECT DISTINCT kind FROM pets").unwrap();
+        assert!(select.distinct);
+        assert_eq!(select.to_string(), "SELECT DISTINCT kind FROM pets");
+
+        let select = parse_select("SELECT ALL name FROM pets").unwrap();
+        assert!(!select.distinct);
+        assert_eq!(select.to_string(), "SELECT name FROM pets");
+    }
+
+    #[test]
+    fn reserved_keywords_are_neither_columns_nor_implicit_aliases() {
+        assert!(parse_select("SELECT where FROM pets").is_err());
+        assert!(parse_select("SELECT kind select FROM pets").is_err());
+        assert!(parse_select("SELECT kind left FROM pets").is_err());
+        assert!(parse_select("SELECT kind \"left\", name AS nothing_reserved FROM pets").is_ok());
+    }
 }
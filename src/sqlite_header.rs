@@ -30,13 +30,21 @@ pub struct SqliteHeader {
 
 
 impl SqliteHeader {
+    /// Page size in bytes. The stored value 1 stands for 65536, which doesn't fit in a u16.
+    pub fn page_size_in_bytes(&self) -> usize {
+        match self.page_size {
+            1 => 65536,
+            size => size as usize,
+        }
+    }
+
     pub fn from_bytes(buffer: &[u8; 100]) -> Result<Self, ParsingError> {
         let mut offset = 0;
         if offset_range(buffer, &mut offset, 16) != b"SQLite format 3\0" {
             return Err(ParsingError::InvalidHeaderString)
         };
     
-        return Ok(SqliteHeader {
+        Ok(SqliteHeader {
             page_size: get_num_from_be(&mut offset, buffer)?,
             file_format_write_version: get_num_from_be(&mut offset, buffer)?,
             file_format_read_version: get_num_from_be(&mut offset, buffer)?,
@@ -59,7 +67,6 @@ impl SqliteHeader {
             reserved_for_expansion: offset_range(buffer, &mut offset, 20).try_into()?,
             version_valid_for_number: get_num_from_be(&mut offset, buffer)?,
             sqlite_version_number: get_num_from_be(&mut offset, buffer)?,
-            
         })
     }
 }
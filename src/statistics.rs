@@ -0,0 +1,63 @@
+use crate::{
+    parsing_error::ParsingError,
+    reader::{SqliteReader, get_num_from_be},
+};
+
+/// Size of the b-tree of a table or index, which the planner compares the ways of reading a
+/// table with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    pub rows: f64,
+    /// Pages read from the root to reach a leaf.
+    pub depth: u32,
+    /// Average number of entries of an index sharing the values of its first 1, 2, ... key
+    /// columns, when ANALYZE measured them.
+    pub equal_rows: Vec<f64>,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Statistics {
+            rows: 0.0,
+            depth: 1,
+            equal_rows: vec![],
+        }
+    }
+}
+
+impl Statistics {
+    /// Estimates the size of the b-tree rooted at `root_page` from the pages on the path to its
+    /// first leaf, assuming the other pages hold as many cells.
+    pub fn estimate(reader: &SqliteReader, root_page: u64) -> Result<Self, ParsingError> {
+        let mut statistics = Statistics {
+            rows: 1.0,
+            ..Statistics::default()
+        };
+        let mut page = reader.read_page(root_page)?;
+        while let Some(rightmost_pointer) = page.page_header.rightmost_pointer {
+            let cells = page.parse_cell_pointer_array();
+            statistics.rows *= (cells.len() + 1) as f64;
+            let child: u32 = match cells.first() {
+                Some(&offset) => get_num_from_be(&mut (offset as usize), &page.page)?,
+                None => rightmost_pointer,
+            };
+            page = reader.read_page(child as u64)?;
+            statistics.depth += 1;
+        }
+        statistics.rows *= page.page_header.cell_count as f64;
+        Ok(statistics)
+    }
+
+    /// Replaces the estimates with a `stat` of sqlite_stat1: the number of rows, followed for
+    /// an index by the average number of entries for each prefix of its key.
+    pub fn apply_stat1(&mut self, stat: &str) {
+        let mut numbers = stat
+            .split_whitespace()
+            .map_while(|number| number.parse::<u64>().ok())
+            .map(|number| number as f64);
+        if let Some(rows) = numbers.next() {
+            self.rows = rows;
+            self.equal_rows = numbers.collect();
+        }
+    }
+}
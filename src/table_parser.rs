@@ -1,83 +1,756 @@
-use crate::{parsing_error::ParsingError, parsing_utils::find_keyword};
+use crate::{
+    expression_parser::ParsedExpression,
+    index_parser::IndexedColumn,
+    lexer::TokenKind,
+    parser::Parser,
+    parsing_error::ParsingError,
+    select_parser::SortOrder,
+    value::{Affinity, Value},
+};
 
-#[derive(Debug)]
-pub enum TableColumn {
-    RowId(String),
-    Column(usize, String),
+/// Keywords that start a table constraint instead of a column definition.
+const TABLE_CONSTRAINT_KEYWORDS: [&str; 5] =
+    ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+/// Keywords that end the type name in a column definition.
+const COLUMN_CONSTRAINT_KEYWORDS: [&str; 11] = [
+    "constraint",
+    "primary",
+    "not",
+    "null",
+    "unique",
+    "check",
+    "default",
+    "collate",
+    "references",
+    "generated",
+    "as",
+];
+
+/// Names that read the rowid of a table that has no column with that name.
+const ROWID_NAMES: [&str; 3] = ["rowid", "oid", "_rowid_"];
+
+/// The rowid of a table without an alias column.
+static ROWID: TableColumn = TableColumn {
+    name: String::new(),
+    declared_type: String::new(),
+    affinity: Affinity::Integer,
+    storage: ColumnStorage::RowId,
+    default: None,
+    collation: None,
+    constraints: Vec::new(),
+};
+
+/// Where the value of a column is read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnStorage {
+    /// The column is an alias of the rowid, the record holds NULL in its place.
+    RowId,
+    /// Position of the value in the record.
+    Record(usize),
+    /// A VIRTUAL generated column, computed when read instead of being stored.
+    Virtual,
+}
+
+/// The table and columns a foreign key refers to, no columns meaning its primary key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForeignKey {
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnConstraint {
+    PrimaryKey {
+        order: SortOrder,
+        autoincrement: bool,
+    },
+    NotNull,
+    Unique,
+    Check(ParsedExpression),
+    References(ForeignKey),
+    /// `[GENERATED ALWAYS] AS (expression) [STORED | VIRTUAL]`
+    Generated {
+        expression: ParsedExpression,
+        stored: bool,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableConstraint {
+    PrimaryKey(Vec<IndexedColumn>),
+    Unique(Vec<IndexedColumn>),
+    Check(ParsedExpression),
+    ForeignKey {
+        columns: Vec<String>,
+        references: ForeignKey,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct TableColumn {
+    pub name: String,
+    /// The type name as written, empty when there is none.
+    pub declared_type: String,
+    pub affinity: Affinity,
+    pub storage: ColumnStorage,
+    pub default: Option<ParsedExpression>,
+    /// Name of the collation of the column, BINARY when there is none.
+    pub collation: Option<String>,
+    /// Constraints other than DEFAULT and COLLATE, in the order they are declared.
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+impl TableColumn {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn affinity(&self) -> Affinity {
+        self.affinity
+    }
+
+    pub fn is_rowid(&self) -> bool {
+        self.storage == ColumnStorage::RowId
+    }
+
+    pub fn is_not_null(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|constraint| matches!(constraint, ColumnConstraint::NotNull))
+    }
+
+    /// Whether the column is generated when read instead of being stored.
+    pub fn is_virtual(&self) -> bool {
+        self.constraints.iter().any(|constraint| {
+            matches!(
+                constraint,
+                ColumnConstraint::Generated { stored: false, .. }
+            )
+        })
+    }
+
+    /// The expression of a generated column.
+    pub fn generated(&self) -> Option<&ParsedExpression> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                ColumnConstraint::Generated { expression, .. } => Some(expression),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<TableColumn>,
+    /// Constraints declared after the columns.
+    pub constraints: Vec<TableConstraint>,
+    pub without_rowid: bool,
+    pub strict: bool,
 }
 
 impl Table {
+    /// Looks up a column by name, ignoring case like SQLite does, the names of the rowid
+    /// reading it when no column has them.
     pub fn get_column_by_name(&self, column_name: &str) -> Option<&TableColumn> {
-        self.columns.iter().find(|column| {
-            match column {
-                TableColumn::RowId(name) => return name.as_str() == column_name,
-                TableColumn::Column(_, name) =>  return name.as_str() == column_name,
+        self.columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(column_name))
+            .or_else(|| {
+                let is_rowid = ROWID_NAMES
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(column_name));
+                (is_rowid && !self.without_rowid).then_some(&ROWID)
+            })
+    }
+
+    /// Position of a column in the table, looked up like SQLite does, ignoring case.
+    fn column_position(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Columns of the primary key, declared on a column or after the columns, with the
+    /// collation of their column unless they have one.
+    pub fn primary_key(&self) -> Vec<IndexedColumn> {
+        let table_key = self
+            .constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                TableConstraint::PrimaryKey(columns) => Some(columns.clone()),
+                _ => None,
+            });
+        let column_key = || {
+            self.columns.iter().find_map(|column| {
+                column
+                    .constraints
+                    .iter()
+                    .find_map(|constraint| match constraint {
+                        ColumnConstraint::PrimaryKey { order, .. } => Some(vec![IndexedColumn {
+                            name: column.name.clone(),
+                            collation: None,
+                            order: *order,
+                        }]),
+                        _ => None,
+                    })
+            })
+        };
+        let mut key = table_key.or_else(column_key).unwrap_or_default();
+        for key_column in &mut key {
+            if key_column.collation.is_none() {
+                key_column.collation = self
+                    .column_position(&key_column.name)
+                    .and_then(|position| self.columns[position].collation.clone());
             }
-        })
+        }
+        key
+    }
+
+    /// Columns stored in records, in the order they are stored. The rowid alias holds NULL and
+    /// VIRTUAL columns aren't stored.
+    pub fn record_columns(&self) -> Vec<&TableColumn> {
+        let mut columns = self
+            .columns
+            .iter()
+            .filter(|column| column.storage != ColumnStorage::Virtual)
+            .enumerate()
+            // The rowid alias keeps its place among the columns
+            .map(|(rank, column)| match column.storage {
+                ColumnStorage::Record(position) => (position, column),
+                _ => (rank, column),
+            })
+            .collect::<Vec<_>>();
+        columns.sort_by_key(|(position, _)| *position);
+        columns.into_iter().map(|(_, column)| column).collect()
+    }
+
+    /// The column stored at `position` in the records of the table.
+    pub fn get_record_column(&self, position: usize) -> Option<&TableColumn> {
+        self.record_columns().get(position).copied()
+    }
+
+    /// Affinities of the values of records, by position.
+    pub fn record_affinities(&self) -> Vec<Affinity> {
+        self.record_columns()
+            .into_iter()
+            .map(TableColumn::affinity)
+            .collect()
     }
 }
 
-pub fn parse_table(sql: &str) -> Result<Table, ParsingError> {
-    let sql = sql.trim();
-    let create_keyword = find_keyword(sql, "CREATE").ok_or(ParsingError::InvalidStatement)?;
-    let table_keyword = find_keyword(sql, "TABLE").ok_or(ParsingError::InvalidStatement)?;
-
-    if create_keyword != 0 || table_keyword <= create_keyword {
-        return Err(ParsingError::InvalidStatement);
-    };
-
-    let table_name_end = sql.find("(").ok_or(ParsingError::InvalidStatement)?;
-    let table_name = sql[(table_keyword + "table".len())..table_name_end].trim();
-    if table_name.is_empty() {
-        return Err(ParsingError::InvalidStatement);
-    };
-
-    let columns_end = sql.rfind(")").ok_or(ParsingError::InvalidStatement)?;
-    let columns = &sql[(table_name_end + 1)..columns_end];
-    let mut columns = columns
-        .split(",")
-        .map(|column| {
-            column
-                .trim()
-                .split(" ")
-                .map(|token| token.trim())
-                .filter(|token| !token.is_empty())
-                .collect::<Vec<_>>()
-        })
-        .map(|column_tokens| {
-            // oversimplification: it could come from a sequence too.
-            if column_tokens
+impl Parser<'_> {
+    /// `CREATE [TEMP] TABLE [IF NOT EXISTS] name (column_def [, ...] [, table_constraint ...])
+    /// [WITHOUT ROWID] [, STRICT]`
+    pub fn parse_create_table(&mut self) -> Result<Table, ParsingError> {
+        self.expect_keyword("CREATE")?;
+        if !self.consume_keyword("TEMP") {
+            self.consume_keyword("TEMPORARY");
+        }
+        self.expect_keyword("TABLE")?;
+        self.consume_keywords(&["IF", "NOT", "EXISTS"]);
+        let name = self.parse_qualified_name()?;
+        self.expect(TokenKind::LeftParen)?;
+
+        let mut columns = vec![];
+        let mut constraints = vec![];
+        loop {
+            if TABLE_CONSTRAINT_KEYWORDS
                 .iter()
-                .position(|v| v.to_lowercase() == "autoincrement")
-                .is_some_and(|position| position != 0)
+                .any(|keyword| self.check_keyword(keyword))
             {
-                return TableColumn::RowId(column_tokens[0].to_string());
+                constraints.push(self.parse_table_constraint()?);
+            } else if constraints.is_empty() {
+                columns.push(self.parse_column_definition()?);
+            } else {
+                return Err(self.unexpected("a table constraint"));
+            }
+
+            // Table constraints don't need to be separated by commas
+            let separated = self.consume(&TokenKind::Comma);
+            let constraint_follows = !constraints.is_empty()
+                && TABLE_CONSTRAINT_KEYWORDS
+                    .iter()
+                    .any(|keyword| self.check_keyword(keyword));
+            if !separated && !constraint_follows {
+                break;
+            }
+        }
+        self.expect(TokenKind::RightParen)?;
+
+        let mut without_rowid = false;
+        let mut strict = false;
+        while !self.check(&TokenKind::Eof) && !self.check(&TokenKind::Semicolon) {
+            if self.consume_keywords(&["WITHOUT", "ROWID"]) {
+                without_rowid = true;
+            } else if self.consume_keyword("STRICT") {
+                strict = true;
+            } else {
+                return Err(self.unexpected("table options"));
+            }
+            if !self.consume(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        let mut table = Table {
+            name,
+            columns,
+            constraints,
+            without_rowid,
+            strict,
+        };
+        assign_storage(&mut table);
+        Ok(table)
+    }
+
+    /// `name [type_name] [column_constraint ...]`
+    fn parse_column_definition(&mut self) -> Result<TableColumn, ParsingError> {
+        let name = self.parse_identifier()?;
+
+        let type_begin = self.mark();
+        let mut quoted_type = None;
+        while let TokenKind::Identifier(word) | TokenKind::QuotedIdentifier(word) =
+            &self.peek().kind
+        {
+            if COLUMN_CONSTRAINT_KEYWORDS.contains(&word.to_lowercase().as_str()) {
+                break;
+            }
+            quoted_type = match (self.mark() == type_begin, &self.peek().kind) {
+                (true, TokenKind::QuotedIdentifier(word)) => Some(word.clone()),
+                _ => None,
+            };
+            self.advance();
+        }
+        if self.mark() != type_begin && self.consume(&TokenKind::LeftParen) {
+            quoted_type = None;
+            self.skip_parenthesized()?;
+        }
+        // A type that is a single quoted name is declared without its quotes
+        let declared_type =
+            quoted_type.unwrap_or_else(|| self.source_since(type_begin).to_string());
+
+        let mut column = TableColumn {
+            name,
+            affinity: Affinity::from_declared_type(&declared_type),
+            declared_type,
+            storage: ColumnStorage::Record(0),
+            default: None,
+            collation: None,
+            constraints: vec![],
+        };
+        while !matches!(
+            self.peek().kind,
+            TokenKind::Comma | TokenKind::RightParen | TokenKind::Eof
+        ) {
+            self.parse_column_constraint(&mut column)?;
+        }
+        Ok(column)
+    }
+
+    /// `[CONSTRAINT name]` followed by one constraint, added to `column`.
+    fn parse_column_constraint(&mut self, column: &mut TableColumn) -> Result<(), ParsingError> {
+        if self.consume_keyword("CONSTRAINT") {
+            self.parse_identifier()?;
+        }
+
+        let constraint = if self.consume_keywords(&["PRIMARY", "KEY"]) {
+            let order = if self.consume_keyword("DESC") {
+                SortOrder::Desc
             } else {
-                return TableColumn::Column(0, column_tokens[0].to_string());
+                self.consume_keyword("ASC");
+                SortOrder::Asc
+            };
+            self.parse_conflict_clause()?;
+            ColumnConstraint::PrimaryKey {
+                order,
+                autoincrement: self.consume_keyword("AUTOINCREMENT"),
+            }
+        } else if self.consume_keywords(&["NOT", "NULL"]) {
+            self.parse_conflict_clause()?;
+            ColumnConstraint::NotNull
+        } else if self.consume_keyword("NULL") {
+            self.parse_conflict_clause()?;
+            return Ok(());
+        } else if self.consume_keyword("UNIQUE") {
+            self.parse_conflict_clause()?;
+            ColumnConstraint::Unique
+        } else if self.consume_keyword("CHECK") {
+            ColumnConstraint::Check(self.parse_parenthesized_expression()?)
+        } else if self.consume_keyword("DEFAULT") {
+            column.default = Some(self.parse_default()?);
+            return Ok(());
+        } else if self.consume_keyword("COLLATE") {
+            column.collation = Some(self.parse_identifier()?);
+            return Ok(());
+        } else if self.consume_keyword("REFERENCES") {
+            ColumnConstraint::References(self.parse_foreign_key_clause()?)
+        } else if self.consume_keywords(&["GENERATED", "ALWAYS"]) || self.check_keyword("AS") {
+            self.expect_keyword("AS")?;
+            let expression = self.parse_parenthesized_expression()?;
+            let stored = self.consume_keyword("STORED");
+            if !stored {
+                self.consume_keyword("VIRTUAL");
+            }
+            ColumnConstraint::Generated { expression, stored }
+        } else {
+            return Err(self.unexpected("a column constraint"));
+        };
+        column.constraints.push(constraint);
+        Ok(())
+    }
+
+    /// `[CONSTRAINT name]` followed by a PRIMARY KEY, UNIQUE, CHECK or FOREIGN KEY constraint.
+    fn parse_table_constraint(&mut self) -> Result<TableConstraint, ParsingError> {
+        if self.consume_keyword("CONSTRAINT") {
+            self.parse_identifier()?;
+        }
+
+        let constraint = if self.consume_keywords(&["PRIMARY", "KEY"]) {
+            let columns = self.parse_indexed_columns()?;
+            self.consume_keyword("AUTOINCREMENT");
+            TableConstraint::PrimaryKey(columns)
+        } else if self.consume_keyword("UNIQUE") {
+            TableConstraint::Unique(self.parse_indexed_columns()?)
+        } else if self.consume_keyword("CHECK") {
+            TableConstraint::Check(self.parse_parenthesized_expression()?)
+        } else if self.consume_keywords(&["FOREIGN", "KEY"]) {
+            self.expect(TokenKind::LeftParen)?;
+            let mut columns = vec![self.parse_identifier()?];
+            while self.consume(&TokenKind::Comma) {
+                columns.push(self.parse_identifier()?);
+            }
+            self.expect(TokenKind::RightParen)?;
+            self.expect_keyword("REFERENCES")?;
+            TableConstraint::ForeignKey {
+                columns,
+                references: self.parse_foreign_key_clause()?,
             }
+        } else {
+            return Err(self.unexpected("a table constraint"));
+        };
+        self.parse_conflict_clause()?;
+        Ok(constraint)
+    }
+
+    /// `(indexed_column [, ...])`
+    fn parse_indexed_columns(&mut self) -> Result<Vec<IndexedColumn>, ParsingError> {
+        self.expect(TokenKind::LeftParen)?;
+        let mut columns = vec![self.parse_indexed_column()?];
+        while self.consume(&TokenKind::Comma) {
+            columns.push(self.parse_indexed_column()?);
+        }
+        self.expect(TokenKind::RightParen)?;
+        Ok(columns)
+    }
+
+    fn parse_parenthesized_expression(&mut self) -> Result<ParsedExpression, ParsingError> {
+        self.expect(TokenKind::LeftParen)?;
+        let expression = self.parse_expression()?;
+        self.expect(TokenKind::RightParen)?;
+        Ok(expression)
+    }
+
+    /// A signed number, a literal, a parenthesized expression, `CURRENT_TIME`, `CURRENT_DATE`,
+    /// `CURRENT_TIMESTAMP`, or a name which SQLite takes as a string.
+    fn parse_default(&mut self) -> Result<ParsedExpression, ParsingError> {
+        if self.check(&TokenKind::LeftParen) {
+            return self.parse_parenthesized_expression();
+        }
+        let TokenKind::Identifier(word) = &self.peek().kind else {
+            return self.parse_literal().map(ParsedExpression::Literal);
+        };
+        let word = word.clone();
+        let function = match word.to_uppercase().as_str() {
+            "NULL" => return self.parse_literal().map(ParsedExpression::Literal),
+            "TRUE" | "FALSE" => {
+                self.advance();
+                return Ok(ParsedExpression::Literal(Value::Integer(i64::from(
+                    word.eq_ignore_ascii_case("TRUE"),
+                ))));
+            }
+            "CURRENT_TIME" => "time",
+            "CURRENT_DATE" => "date",
+            "CURRENT_TIMESTAMP" => "datetime",
+            _ => {
+                self.advance();
+                return Ok(ParsedExpression::Literal(Value::Text(word)));
+            }
+        };
+        self.advance();
+        Ok(ParsedExpression::Function {
+            name: function.to_string(),
+            arguments: vec![],
         })
-        .collect::<Vec<_>>();
-    let mut column_index = 0;
-    for column in columns.iter_mut() {
-        match column {
-            TableColumn::RowId(_) => {
-                column_index += 1;
+    }
+
+    /// `table [(column [, ...])]` followed by the actions and deferment of a foreign key, which
+    /// are skipped.
+    fn parse_foreign_key_clause(&mut self) -> Result<ForeignKey, ParsingError> {
+        let table = self.parse_identifier()?;
+        let mut columns = vec![];
+        if self.consume(&TokenKind::LeftParen) {
+            columns.push(self.parse_identifier()?);
+            while self.consume(&TokenKind::Comma) {
+                columns.push(self.parse_identifier()?);
             }
-            TableColumn::Column(index, _) => {
-                *index = column_index;
-                column_index += 1;
+            self.expect(TokenKind::RightParen)?;
+        }
+
+        loop {
+            if self.consume_keyword("ON") {
+                if !self.consume_keyword("DELETE") {
+                    self.expect_keyword("UPDATE")?;
+                }
+                let is_action = ["CASCADE", "RESTRICT"]
+                    .iter()
+                    .any(|action| self.consume_keyword(action))
+                    || self.consume_keywords(&["SET", "NULL"])
+                    || self.consume_keywords(&["SET", "DEFAULT"])
+                    || self.consume_keywords(&["NO", "ACTION"]);
+                if !is_action {
+                    return Err(self.unexpected("a foreign key action"));
+                }
+            } else if self.consume_keyword("MATCH") {
+                self.parse_identifier()?;
+            } else if self.check_keyword("DEFERRABLE") || self.check_keyword_at(1, "DEFERRABLE") {
+                self.consume_keyword("NOT");
+                self.expect_keyword("DEFERRABLE")?;
+                if self.consume_keyword("INITIALLY")
+                    && !self.consume_keyword("DEFERRED")
+                    && !self.consume_keyword("IMMEDIATE")
+                {
+                    return Err(self.unexpected("DEFERRED or IMMEDIATE"));
+                }
+            } else {
+                return Ok(ForeignKey { table, columns });
             }
         }
     }
 
-    return Ok(Table {
-        name: table_name.to_string(),
-        columns,
+    /// `[ON CONFLICT ROLLBACK | ABORT | FAIL | IGNORE | REPLACE]`, which only matters to writes.
+    fn parse_conflict_clause(&mut self) -> Result<(), ParsingError> {
+        if self.consume_keywords(&["ON", "CONFLICT"]) {
+            self.parse_identifier()?;
+        }
+        Ok(())
+    }
+}
+
+/// Makes the rowid alias read the rowid and numbers the columns stored in records. A single
+/// INTEGER column primary key of a rowid table is an alias of the rowid, unless it's declared
+/// `PRIMARY KEY DESC` on the column. WITHOUT ROWID tables store their primary key first.
+fn assign_storage(table: &mut Table) {
+    let column_key = table.columns.iter().position(|column| {
+        column
+            .constraints
+            .iter()
+            .any(|constraint| matches!(constraint, ColumnConstraint::PrimaryKey { .. }))
+    });
+    let table_key = table
+        .constraints
+        .iter()
+        .find_map(|constraint| match constraint {
+            TableConstraint::PrimaryKey(columns) => Some(columns),
+            _ => None,
+        });
+    let rowid_alias = match (column_key, table_key) {
+        (Some(position), None) => {
+            let is_desc = table.columns[position]
+                .constraints
+                .iter()
+                .any(|constraint| {
+                    matches!(
+                        constraint,
+                        ColumnConstraint::PrimaryKey {
+                            order: SortOrder::Desc,
+                            ..
+                        }
+                    )
+                });
+            (!is_desc).then_some(position)
+        }
+        (None, Some(columns)) => match columns.as_slice() {
+            [key] => table.column_position(&key.name),
+            _ => None,
+        },
+        _ => None,
+    }
+    .filter(|&position| {
+        !table.without_rowid
+            && table.columns[position]
+                .declared_type
+                .eq_ignore_ascii_case("INTEGER")
     });
+
+    // The records of WITHOUT ROWID tables start with the primary key
+    let mut stored = (0..table.columns.len())
+        .filter(|&position| !table.columns[position].is_virtual())
+        .collect::<Vec<_>>();
+    if table.without_rowid {
+        let key = table
+            .primary_key()
+            .iter()
+            .filter_map(|key| table.column_position(&key.name))
+            .collect::<Vec<_>>();
+        stored.retain(|position| !key.contains(position));
+        stored.splice(0..0, key);
+        stored.dedup();
+    }
+
+    for column in &mut table.columns {
+        column.storage = ColumnStorage::Virtual;
+    }
+    for (record_position, position) in stored.into_iter().enumerate() {
+        table.columns[position].storage = if Some(position) == rowid_alias {
+            ColumnStorage::RowId
+        } else {
+            ColumnStorage::Record(record_position)
+        };
+    }
+}
+
+pub fn parse_table(sql: &str) -> Result<Table, ParsingError> {
+    let mut parser = Parser::new(sql)?;
+    let table = parser.parse_create_table()?;
+    parser.expect_end()?;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Where each column of the table of `sql` is read from.
+    fn storage(sql: &str) -> Vec<ColumnStorage> {
+        let table = parse_table(sql).unwrap();
+        table.columns.iter().map(|column| column.storage).collect()
+    }
+
+    #[test]
+    fn only_an_integer_primary_key_aliases_the_rowid() {
+        use ColumnStorage::{Record, RowId};
+        let cases = [
+            (
+                "CREATE TABLE t(id INTEGER PRIMARY KEY, a)",
+                vec![RowId, Record(1)],
+            ),
+            (
+                "CREATE TABLE t(a, id integer, PRIMARY KEY(id))",
+                vec![Record(0), RowId],
+            ),
+            (
+                "CREATE TABLE t(id INTEGER PRIMARY KEY ASC, a)",
+                vec![RowId, Record(1)],
+            ),
+            (
+                "CREATE TABLE t(id INTEGER PRIMARY KEY DESC, a)",
+                vec![Record(0), Record(1)],
+            ),
+            (
+                "CREATE TABLE t(id INT PRIMARY KEY, a)",
+                vec![Record(0), Record(1)],
+            ),
+            (
+                "CREATE TABLE t(id INTEGER, a, PRIMARY KEY(id, a))",
+                vec![Record(0), Record(1)],
+            ),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(storage(sql), expected, "{sql}");
+        }
+
+        let table = parse_table("CREATE TABLE t(id INTEGER PRIMARY KEY, a)").unwrap();
+        assert!(
+            table
+                .get_column_by_name("_ROWID_")
+                .is_some_and(TableColumn::is_rowid)
+        );
+        let table = parse_table("CREATE TABLE t(a, rowid)").unwrap();
+        assert_eq!(
+            table
+                .get_column_by_name("rowid")
+                .map(|column| column.name()),
+            Some("rowid")
+        );
+        assert!(
+            table
+                .get_column_by_name("oid")
+                .is_some_and(TableColumn::is_rowid)
+        );
+    }
+
+    #[test]
+    fn column_types_defaults_and_constraints() {
+        let table = parse_table(
+            "CREATE TABLE IF NOT EXISTS main.t (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name VARCHAR(20) NOT NULL COLLATE NOCASE DEFAULT 'x',
+                owner INT CONSTRAINT fk REFERENCES people(id) ON DELETE CASCADE,
+                total AS (id * 2) STORED,
+                label TEXT GENERATED ALWAYS AS (upper(name)),
+                UNIQUE (name, owner)
+                CHECK (id > 0)
+            )",
+        )
+        .unwrap();
+        assert_eq!(table.name, "t");
+
+        let [id, name, owner, total, label] = &table.columns[..] else {
+            panic!("expected 5 columns");
+        };
+        assert_eq!(
+            id.constraints,
+            [ColumnConstraint::PrimaryKey {
+                order: SortOrder::Asc,
+                autoincrement: true
+            }]
+        );
+        assert_eq!(name.declared_type, "VARCHAR(20)");
+        assert_eq!(name.affinity, Affinity::Text);
+        assert_eq!(name.collation.as_deref(), Some("NOCASE"));
+        assert_eq!(
+            name.default,
+            Some(ParsedExpression::Literal(Value::Text("x".to_string())))
+        );
+        assert!(name.is_not_null());
+        assert_eq!(
+            owner.constraints,
+            [ColumnConstraint::References(ForeignKey {
+                table: "people".to_string(),
+                columns: vec!["id".to_string()],
+            })]
+        );
+
+        // STORED columns are in the records, VIRTUAL ones are computed
+        assert!(matches!(
+            total.constraints[..],
+            [ColumnConstraint::Generated { stored: true, .. }]
+        ));
+        assert_eq!(total.storage, ColumnStorage::Record(3));
+        assert!(label.is_virtual());
+        assert_eq!(label.storage, ColumnStorage::Virtual);
+        assert_eq!(table.record_columns().len(), 4);
+
+        assert!(matches!(
+            &table.constraints[..],
+            [TableConstraint::Unique(columns), TableConstraint::Check(_)] if columns.len() == 2
+        ));
+    }
+
+    #[test]
+    fn without_rowid_tables_store_their_primary_key_first() {
+        use ColumnStorage::Record;
+        let sql = "CREATE TABLE t(a, b TEXT, c INTEGER, PRIMARY KEY(c, b)) WITHOUT ROWID";
+        let table = parse_table(sql).unwrap();
+        assert!(table.without_rowid);
+        assert_eq!(storage(sql), [Record(2), Record(1), Record(0)]);
+        assert!(table.get_column_by_name("rowid").is_none());
+
+        let sql = "CREATE TABLE t(id INTEGER PRIMARY KEY, a) WITHOUT ROWID, STRICT";
+        assert!(parse_table(sql).unwrap().strict);
+        assert_eq!(storage(sql), [Record(0), Record(1)]);
+    }
 }
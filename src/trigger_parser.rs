@@ -0,0 +1,168 @@
+use crate::{
+    expression_parser::ParsedExpression, lexer::TokenKind, parser::Parser,
+    parsing_error::ParsingError,
+};
+
+/// When a trigger runs, relative to the statement that fires it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    InsteadOf,
+}
+
+/// The kind of statement that fires a trigger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Delete,
+    Insert,
+    /// Updates of the listed columns, or of any column when there are none.
+    Update(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Trigger {
+    pub name: String,
+    pub table_name: String,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    /// Condition of the WHEN clause, which may read the `OLD` and `NEW` rows.
+    pub when: Option<ParsedExpression>,
+    /// Statements between BEGIN and END as written, since this reader never runs them.
+    pub body: String,
+}
+
+const TRIGGER_KEYWORD: &str = "TRIGGER";
+const CREATE_KEYWORD: &str = "CREATE";
+const ON_KEYWORD: &str = "ON";
+const BEGIN_KEYWORD: &str = "BEGIN";
+const END_KEYWORD: &str = "END";
+
+impl Parser<'_> {
+    /// `CREATE [TEMP | TEMPORARY] TRIGGER [IF NOT EXISTS] name [BEFORE | AFTER | INSTEAD OF]
+    /// (DELETE | INSERT | UPDATE [OF column [, ...]]) ON table [FOR EACH ROW] [WHEN expr]
+    /// BEGIN statement; [...] END`
+    pub fn parse_create_trigger(&mut self) -> Result<Trigger, ParsingError> {
+        self.expect_keyword(CREATE_KEYWORD)?;
+        let _ = self.consume_keyword("TEMP") || self.consume_keyword("TEMPORARY");
+        self.expect_keyword(TRIGGER_KEYWORD)?;
+        self.consume_keywords(&["IF", "NOT", "EXISTS"]);
+        let name = self.parse_qualified_name()?;
+
+        let timing = if self.consume_keyword("AFTER") {
+            TriggerTiming::After
+        } else if self.consume_keywords(&["INSTEAD", "OF"]) {
+            TriggerTiming::InsteadOf
+        } else {
+            self.consume_keyword("BEFORE");
+            TriggerTiming::Before
+        };
+
+        let event = if self.consume_keyword("DELETE") {
+            TriggerEvent::Delete
+        } else if self.consume_keyword("INSERT") {
+            TriggerEvent::Insert
+        } else if self.consume_keyword("UPDATE") {
+            let mut columns = vec![];
+            if self.consume_keyword("OF") {
+                columns.push(self.parse_identifier()?);
+                while self.consume(&TokenKind::Comma) {
+                    columns.push(self.parse_identifier()?);
+                }
+            }
+            TriggerEvent::Update(columns)
+        } else {
+            return Err(self.unexpected("DELETE, INSERT or UPDATE"));
+        };
+
+        self.expect_keyword(ON_KEYWORD)?;
+        let table_name = self.parse_qualified_name()?;
+        self.consume_keywords(&["FOR", "EACH", "ROW"]);
+        let when = if self.consume_keyword("WHEN") {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        // The body ends at the END closing the statement, the statements in it can have the
+        // END of CASE expressions
+        self.expect_keyword(BEGIN_KEYWORD)?;
+        let begin = self.mark();
+        while !self.is_trigger_end() {
+            if self.check(&TokenKind::Eof) {
+                return Err(self.unexpected(END_KEYWORD));
+            }
+            self.advance();
+        }
+        let body = self.source_since(begin).to_string();
+        self.expect_keyword(END_KEYWORD)?;
+
+        Ok(Trigger {
+            name,
+            table_name,
+            timing,
+            event,
+            when,
+            body,
+        })
+    }
+
+    /// Whether the next token is the END of the statement, only followed by a semicolon.
+    fn is_trigger_end(&self) -> bool {
+        let after = match self.peek_nth(1).kind {
+            TokenKind::Semicolon => &self.peek_nth(2).kind,
+            ref kind => kind,
+        };
+        self.check_keyword(END_KEYWORD) && *after == TokenKind::Eof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Statement, parser::parse_statement};
+
+    fn parse_trigger(sql: &str) -> Trigger {
+        match parse_statement(sql).unwrap() {
+            Statement::CreateTrigger(trigger) => trigger,
+            statement => panic!("expected a trigger, got {statement:?}"),
+        }
+    }
+
+    #[test]
+    fn timing_event_and_table() {
+        let trigger = parse_trigger(
+            "CREATE TRIGGER IF NOT EXISTS main.audit AFTER UPDATE OF name, kind ON pets \
+             FOR EACH ROW WHEN new.name <> old.name BEGIN INSERT INTO log VALUES (old.name); END",
+        );
+        assert_eq!(trigger.name, "audit");
+        assert_eq!(trigger.table_name, "pets");
+        assert_eq!(trigger.timing, TriggerTiming::After);
+        assert_eq!(
+            trigger.event,
+            TriggerEvent::Update(vec!["name".to_string(), "kind".to_string()])
+        );
+        assert!(trigger.when.is_some());
+        assert_eq!(trigger.body, "INSERT INTO log VALUES (old.name);");
+
+        let trigger = parse_trigger("CREATE TEMP TRIGGER t DELETE ON pets BEGIN SELECT 1; END;");
+        assert_eq!(trigger.timing, TriggerTiming::Before);
+        assert_eq!(trigger.event, TriggerEvent::Delete);
+
+        let trigger = parse_trigger("CREATE TRIGGER t INSTEAD OF INSERT ON v BEGIN SELECT 1; END");
+        assert_eq!(trigger.timing, TriggerTiming::InsteadOf);
+        assert_eq!(trigger.event, TriggerEvent::Insert);
+    }
+
+    #[test]
+    fn the_body_runs_to_the_last_end() {
+        let trigger = parse_trigger(
+            "CREATE TRIGGER t UPDATE ON pets BEGIN SELECT CASE WHEN 1 THEN 2 END; END",
+        );
+        assert_eq!(trigger.event, TriggerEvent::Update(vec![]));
+        assert_eq!(trigger.body, "SELECT CASE WHEN 1 THEN 2 END;");
+
+        assert!(parse_statement("CREATE TRIGGER t DELETE ON pets BEGIN SELECT 1;").is_err());
+        assert!(parse_statement("CREATE TRIGGER t TRUNCATE ON pets BEGIN SELECT 1; END").is_err());
+    }
+}
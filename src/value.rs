@@ -0,0 +1,556 @@
+use std::{borrow::Cow, cmp::Ordering, fmt::Display};
+
+use crate::printf::{Decimal, exponent_suffix};
+
+/// A single SQL value, using SQLite's storage classes.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Column type affinity, see https://www.sqlite.org/datatype3.html#type_affinity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Affinity {
+    Text,
+    Numeric,
+    Integer,
+    Real,
+    Blob,
+}
+
+impl Affinity {
+    /// Computes the affinity of a declared column type using SQLite's rules, in order.
+    pub fn from_declared_type(declared_type: &str) -> Self {
+        let declared_type = declared_type.to_uppercase();
+        if declared_type.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|name| declared_type.contains(name))
+        {
+            Affinity::Text
+        } else if declared_type.contains("BLOB") || declared_type.trim().is_empty() {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|name| declared_type.contains(name))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Affinity::Numeric | Affinity::Integer | Affinity::Real)
+    }
+}
+
+/// Collating sequence used to compare text values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Collation {
+    #[default]
+    Binary,
+    NoCase,
+    RTrim,
+}
+
+impl Collation {
+    /// Looks up one of SQLite's built-in collations by (case-insensitive) name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "BINARY" => Some(Collation::Binary),
+            "NOCASE" => Some(Collation::NoCase),
+            "RTRIM" => Some(Collation::RTrim),
+            _ => None,
+        }
+    }
+
+    pub fn compare(&self, lhs: &str, rhs: &str) -> Ordering {
+        match self {
+            Collation::Binary => lhs.as_bytes().cmp(rhs.as_bytes()),
+            // NOCASE only folds ASCII letters
+            Collation::NoCase => lhs
+                .bytes()
+                .map(|byte| byte.to_ascii_lowercase())
+                .cmp(rhs.bytes().map(|byte| byte.to_ascii_lowercase())),
+            Collation::RTrim => lhs
+                .trim_end_matches(' ')
+                .as_bytes()
+                .cmp(rhs.trim_end_matches(' ').as_bytes()),
+        }
+    }
+
+    /// The text two texts compare equal with when they're equal under the collation.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Collation::Binary => Cow::Borrowed(text),
+            Collation::NoCase => Cow::Owned(text.to_ascii_lowercase()),
+            Collation::RTrim => Cow::Borrowed(text.trim_end_matches(' ')),
+        }
+    }
+}
+
+/// Parses text that looks like a number (with optional surrounding spaces) the way
+/// NUMERIC affinity does: integers that fit in an i64 stay integers, other numbers are reals.
+pub fn parse_numeric(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    if let Ok(value) = text.parse::<i64>() {
+        return Some(Value::Integer(value));
+    }
+
+    let looks_numeric = text
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+        && text.chars().any(|c| c.is_ascii_digit());
+    if !looks_numeric {
+        return None;
+    }
+
+    text.parse::<f64>().ok().map(Value::Real)
+}
+
+/// Length of the longest prefix of `text` that reads as a number, and whether that prefix is
+/// an integer (no decimal point nor exponent).
+fn numeric_prefix(text: &str) -> (usize, bool) {
+    let bytes = text.as_bytes();
+    let digits_from = |mut end: usize| {
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        end
+    };
+
+    let mut end = if matches!(bytes.first(), Some(b'+' | b'-')) {
+        1
+    } else {
+        0
+    };
+    end = digits_from(end);
+    let mut is_integer = true;
+    if bytes.get(end) == Some(&b'.') {
+        end = digits_from(end + 1);
+        is_integer = false;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent = end + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        let exponent_end = digits_from(exponent);
+        if exponent_end > exponent {
+            end = exponent_end;
+            is_integer = false;
+        }
+    }
+    (end, is_integer)
+}
+
+/// Value of the longest prefix of `text` (after leading spaces) that reads as a real number.
+fn real_prefix(text: &str) -> f64 {
+    let text = text.trim_start();
+    text[..numeric_prefix(text).0].parse().unwrap_or(0.0)
+}
+
+/// Value of the longest prefix of `text` (after leading spaces) that reads as a number, an
+/// integer when it's written as one and fits in an i64, or 0 when there's none.
+fn number_prefix(text: &str) -> Value {
+    let text = text.trim_start();
+    let (end, is_integer) = numeric_prefix(text);
+    match (text[..end].parse::<i64>(), text[..end].parse::<f64>()) {
+        (Ok(value), _) if is_integer => Value::Integer(value),
+        (_, Ok(value)) => Value::Real(value),
+        _ => Value::Integer(0),
+    }
+}
+
+/// Value of the longest prefix of `text` (after leading spaces) that reads as an integer,
+/// saturating on overflow.
+fn integer_prefix(text: &str) -> i64 {
+    let text = text.trim_start();
+    let (sign, digits) = match text.as_bytes().first() {
+        Some(b'-') => (-1, &text[1..]),
+        Some(b'+') => (1, &text[1..]),
+        _ => (1, text),
+    };
+    digits
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .fold(0i64, |value, digit| {
+            value
+                .saturating_mul(10)
+                .saturating_add(sign * i64::from(digit - b'0'))
+        })
+}
+
+/// Formats a real the way SQLite does (`%!.15g`): at most 15 significant digits and
+/// always a decimal point, e.g. `1.0`, `0.1`, `1.0e+20`.
+pub fn format_real(value: f64) -> String {
+    if value.is_infinite() {
+        return if value > 0.0 { "Inf" } else { "-Inf" }.to_string();
+    }
+    if value == 0.0 {
+        return "0.0".to_string();
+    }
+
+    let decimal = Decimal::with_digits(value.abs(), 15);
+    let exponent = decimal.exponent;
+    if !(-4..15).contains(&exponent) {
+        let sign = if value < 0.0 { "-" } else { "" };
+        let mantissa = trim_fraction(&decimal.mantissa());
+        return format!("{sign}{mantissa}{}", exponent_suffix(exponent));
+    }
+
+    let decimals = (14 - exponent) as usize;
+    trim_fraction(&format!("{:.*}", decimals, value))
+}
+
+fn trim_fraction(number: &str) -> String {
+    if !number.contains('.') {
+        return format!("{number}.0");
+    }
+    let number = number.trim_end_matches('0');
+    if number.ends_with('.') {
+        format!("{number}0")
+    } else {
+        number.to_string()
+    }
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Name of the storage class, as returned by `typeof()`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Integer(_) => "integer",
+            Value::Real(_) => "real",
+            Value::Text(_) => "text",
+            Value::Blob(_) => "blob",
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to a real the way SQLite casts to REAL: text (and blobs) are read
+    /// up to the end of their longest numeric prefix, NULL and non-numeric text give 0.
+    pub fn to_real(&self) -> f64 {
+        match self {
+            Value::Null => 0.0,
+            Value::Integer(value) => *value as f64,
+            Value::Real(value) => *value,
+            Value::Text(text) => real_prefix(text),
+            Value::Blob(bytes) => real_prefix(&String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    /// Converts the value to an integer the way SQLite casts to INTEGER: reals are truncated
+    /// and saturate, text (and blobs) are read up to the end of their longest integer prefix.
+    pub fn to_integer(&self) -> i64 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(value) => *value,
+            Value::Real(value) => *value as i64,
+            Value::Text(text) => integer_prefix(text),
+            Value::Blob(bytes) => integer_prefix(&String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    /// Converts the value to a number for arithmetic: text (and blobs) are read up to the end
+    /// of their longest numeric prefix, NULL and non-numeric text give 0.
+    pub fn to_numeric(&self) -> Value {
+        match self {
+            Value::Null => Value::Integer(0),
+            Value::Integer(_) | Value::Real(_) => self.clone(),
+            Value::Text(text) => number_prefix(text),
+            Value::Blob(bytes) => number_prefix(&String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    /// Truth value of the value used as a condition, `None` for NULL. Text is true when its
+    /// numeric prefix isn't zero.
+    pub fn truth(&self) -> Option<bool> {
+        match self {
+            Value::Null => None,
+            Value::Integer(value) => Some(*value != 0),
+            value => Some(value.to_real() != 0.0),
+        }
+    }
+
+    /// Converts the value like `CAST(value AS type)` does, for a type of the given affinity.
+    pub fn cast(self, affinity: Affinity) -> Value {
+        match (affinity, self) {
+            (_, Value::Null) => Value::Null,
+            (Affinity::Blob, Value::Blob(bytes)) => Value::Blob(bytes),
+            (Affinity::Blob, value) => Value::Blob(value.to_text().into_bytes()),
+            (Affinity::Text, value) => Value::Text(value.to_text()),
+            (Affinity::Integer, value) => Value::Integer(value.to_integer()),
+            (Affinity::Real, value) => Value::Real(value.to_real()),
+            (Affinity::Numeric, value @ (Value::Integer(_) | Value::Real(_))) => value,
+            (Affinity::Numeric, value) => match value.to_numeric() {
+                Value::Real(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+                    Value::Integer(value as i64)
+                }
+                value => value,
+            },
+        }
+    }
+
+    /// Converts the value to text the way SQLite casts to TEXT, NULL giving an empty string.
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => format_real(*value),
+            Value::Text(text) => text.clone(),
+            Value::Blob(bytes) => String::from_utf8_lossy(bytes).to_string(),
+        }
+    }
+
+    /// Storage class rank used for cross-type ordering: NULL < numbers < TEXT < BLOB.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+
+    /// Orders two values with SQLite's rules and the BINARY collation. NULLs compare equal to
+    /// each other here, callers that need SQL's three-valued logic have to check for them first.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        self.compare_with(other, Collation::Binary)
+    }
+
+    /// Same as `compare`, using `collation` when both values are text.
+    pub fn compare_with(&self, other: &Value, collation: Collation) -> Ordering {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => lhs.cmp(rhs),
+            (Value::Integer(lhs), Value::Real(rhs)) => compare_integer_real(*lhs, *rhs),
+            (Value::Real(lhs), Value::Integer(rhs)) => compare_integer_real(*rhs, *lhs).reverse(),
+            (Value::Real(lhs), Value::Real(rhs)) => lhs.total_cmp(rhs),
+            (Value::Text(lhs), Value::Text(rhs)) => collation.compare(lhs, rhs),
+            (Value::Blob(lhs), Value::Blob(rhs)) => lhs.cmp(rhs),
+            (lhs, rhs) => lhs.type_rank().cmp(&rhs.type_rank()),
+        }
+    }
+
+    /// Converts the value the way storing it in a column with the given affinity would.
+    pub fn apply_affinity(self, affinity: Affinity) -> Value {
+        match (affinity, self) {
+            (Affinity::Text, Value::Integer(value)) => Value::Text(value.to_string()),
+            (Affinity::Text, Value::Real(value)) => Value::Text(format_real(value)),
+            (Affinity::Numeric | Affinity::Integer, Value::Text(text)) => {
+                match parse_numeric(&text) {
+                    Some(Value::Real(value))
+                        if value.fract() == 0.0 && value.abs() < i64::MAX as f64 =>
+                    {
+                        Value::Integer(value as i64)
+                    }
+                    Some(value) => value,
+                    None => Value::Text(text),
+                }
+            }
+            (Affinity::Real, Value::Text(text)) => match parse_numeric(&text) {
+                Some(Value::Integer(value)) => Value::Real(value as f64),
+                Some(value) => value,
+                None => Value::Text(text),
+            },
+            (Affinity::Real, Value::Integer(value)) => Value::Real(value as f64),
+            (_, value) => value,
+        }
+    }
+
+    /// Renders the value as an SQL literal, e.g. `'it''s'` or `NULL`.
+    pub fn sql_literal(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => format_real(*value),
+            Value::Text(value) => format!("'{}'", value.replace('\'', "''")),
+            Value::Blob(value) => format!("X'{}'", to_hex(value)),
+        }
+    }
+}
+
+/// Encodes bytes as upper case hexadecimal, like SQLite's `hex()`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// Decodes the hexadecimal digits of a blob literal such as `X'CAFE'`.
+pub fn from_hex(digits: &str) -> Option<Vec<u8>> {
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+        .collect()
+}
+
+fn compare_integer_real(lhs: i64, rhs: f64) -> Ordering {
+    if rhs.is_nan() {
+        return Ordering::Greater;
+    }
+    if rhs >= 9223372036854775808.0 {
+        return Ordering::Less;
+    }
+    if rhs < -9223372036854775808.0 {
+        return Ordering::Greater;
+    }
+    let truncated = rhs as i64;
+    match lhs.cmp(&truncated) {
+        Ordering::Equal => (truncated as f64).total_cmp(&rhs),
+        ordering => ordering,
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
+
+impl Display for Value {
+    /// Formats the value like the `sqlite3` shell does in list mode, except for blobs which
+    /// are written as hexadecimal so they stay printable.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Real(value) => f.write_str(&format_real(*value)),
+            Value::Text(value) => f.write_str(value),
+            Value::Blob(value) => f.write_str(&to_hex(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    /// Checks the storage class too, which `==` doesn't since 1 equals 1.0.
+    fn assert_same(actual: Value, expected: Value) {
+        assert_eq!(actual.type_name(), expected.type_name(), "{actual:?}");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn affinity_of_declared_types() {
+        let cases = [
+            ("INT", Affinity::Integer),
+            ("VARCHAR(10)", Affinity::Text),
+            ("BLOB", Affinity::Blob),
+            ("", Affinity::Blob),
+            ("DOUBLE PRECISION", Affinity::Real),
+            ("DECIMAL(10,2)", Affinity::Numeric),
+            // The INT rule comes first
+            ("FLOATING POINT", Affinity::Integer),
+        ];
+        for (declared_type, affinity) in cases {
+            assert_eq!(
+                Affinity::from_declared_type(declared_type),
+                affinity,
+                "{declared_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn storing_with_an_affinity() {
+        let cases = [
+            (text(" 12 "), Affinity::Integer, Value::Integer(12)),
+            (text("1e2"), Affinity::Integer, Value::Integer(100)),
+            (text("3"), Affinity::Real, Value::Real(3.0)),
+            (text("x"), Affinity::Real, text("x")),
+            (text("1.0"), Affinity::Numeric, Value::Integer(1)),
+            (text("2.5"), Affinity::Numeric, Value::Real(2.5)),
+            (Value::Integer(5), Affinity::Text, text("5")),
+            (Value::Real(1.5), Affinity::Text, text("1.5")),
+            (text("7"), Affinity::Blob, text("7")),
+        ];
+        for (value, affinity, expected) in cases {
+            assert_same(value.apply_affinity(affinity), expected);
+        }
+    }
+
+    #[test]
+    fn casts() {
+        assert_same(text("12abc").cast(Affinity::Integer), Value::Integer(12));
+        assert_same(text("1.5e1x").cast(Affinity::Real), Value::Real(15.0));
+        assert_same(text("3.0").cast(Affinity::Numeric), Value::Integer(3));
+        assert_same(Value::Integer(12).cast(Affinity::Real), Value::Real(12.0));
+        assert_same(Value::Real(4.5).cast(Affinity::Text), text("4.5"));
+        assert_same(Value::Real(1e15).cast(Affinity::Text), text("1.0e+15"));
+        assert_same(text("10").to_numeric(), Value::Integer(10));
+        assert_same(text("x").to_numeric(), Value::Integer(0));
+    }
+
+    #[test]
+    fn reals_as_text() {
+        assert_eq!(format_real(1.0), "1.0");
+        assert_eq!(format_real(0.1), "0.1");
+        assert_eq!(format_real(-1.5e-7), "-1.5e-07");
+        assert_eq!(format_real(1e20), "1.0e+20");
+        assert_eq!(format_real(123456789012345678.0), "1.23456789012346e+17");
+        assert_eq!(format_real(f64::NEG_INFINITY), "-Inf");
+    }
+
+    #[test]
+    fn comparisons_across_storage_classes() {
+        assert_eq!(Value::Integer(1).compare(&Value::Real(1.5)), Ordering::Less);
+        assert_eq!(
+            Value::Integer(2).compare(&Value::Real(2.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Integer(9_007_199_254_740_993).compare(&Value::Real(9_007_199_254_740_992.0)),
+            Ordering::Greater
+        );
+        assert_eq!(Value::Null.compare(&Value::Integer(1)), Ordering::Less);
+        assert_eq!(Value::Integer(5).compare(&text("a")), Ordering::Less);
+        assert_eq!(text("a").compare(&Value::Blob(vec![0])), Ordering::Less);
+    }
+
+    #[test]
+    fn collations() {
+        let compare =
+            |lhs: &str, rhs: &str, collation| text(lhs).compare_with(&text(rhs), collation);
+        assert_eq!(compare("ABC", "abc", Collation::NoCase), Ordering::Equal);
+        assert_eq!(compare("ABC", "abc", Collation::Binary), Ordering::Less);
+        assert_eq!(compare("b", "A", Collation::NoCase), Ordering::Greater);
+        assert_eq!(compare("a  ", "a", Collation::RTrim), Ordering::Equal);
+        assert_eq!(compare("É", "é", Collation::NoCase), Ordering::Less);
+        assert_eq!(Collation::NoCase.normalize("AbC"), "abc");
+        assert_eq!(Collation::RTrim.normalize("a  "), "a");
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    lexer::TokenKind, parser::Parser, parsing_error::ParsingError, select_parser::ParsedSelect,
+};
+
+#[derive(Debug, Clone)]
+pub struct View {
+    pub name: String,
+    /// Names given to the result columns of the SELECT, none meaning they keep their own.
+    pub columns: Vec<String>,
+    pub select: ParsedSelect,
+}
+
+const VIEW_KEYWORD: &str = "VIEW";
+const CREATE_KEYWORD: &str = "CREATE";
+const AS_KEYWORD: &str = "AS";
+
+impl Parser<'_> {
+    /// `CREATE [TEMP | TEMPORARY] VIEW [IF NOT EXISTS] name [(column [, ...])] AS select`
+    pub fn parse_create_view(&mut self) -> Result<View, ParsingError> {
+        self.expect_keyword(CREATE_KEYWORD)?;
+        let _ = self.consume_keyword("TEMP") || self.consume_keyword("TEMPORARY");
+        self.expect_keyword(VIEW_KEYWORD)?;
+        self.consume_keywords(&["IF", "NOT", "EXISTS"]);
+        let name = self.parse_qualified_name()?;
+
+        let mut columns = vec![];
+        if self.consume(&TokenKind::LeftParen) {
+            columns.push(self.parse_identifier()?);
+            while self.consume(&TokenKind::Comma) {
+                columns.push(self.parse_identifier()?);
+            }
+            self.expect(TokenKind::RightParen)?;
+        }
+
+        self.expect_keyword(AS_KEYWORD)?;
+        let select = self.parse_select()?;
+        Ok(View {
+            name,
+            columns,
+            select,
+        })
+    }
+}